@@ -0,0 +1,238 @@
+use serde::Deserialize;
+use std::{fmt, io, path::Path, process::Command};
+
+const LOOKUP_URL: &str = "https://api.acoustid.org/v2/lookup";
+const SUBMIT_URL: &str = "https://api.acoustid.org/v2/submit";
+const MUSICBRAINZ_URL: &str = "https://musicbrainz.org/ws/2/recording";
+// musicbrainz rejects requests without a descriptive user agent
+pub const USER_AGENT: &str = concat!("rmus/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug)]
+pub enum AcoustIdError {
+    // ACOUSTID_API_KEY isn't set
+    MissingApiKey,
+    // submitting needs the user's own key from settings
+    MissingUserKey,
+    // fpcalc is missing or couldn't read the file
+    Fingerprint(io::Error),
+    Request(reqwest::Error),
+    NoMatch,
+    // acoustid turned the submission down
+    Rejected(String),
+}
+
+impl fmt::Display for AcoustIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcoustIdError::MissingApiKey => write!(f, "ACOUSTID_API_KEY is not set"),
+            AcoustIdError::Fingerprint(e) => write!(f, "fpcalc failed: {}", e),
+            AcoustIdError::Request(e) => write!(f, "lookup failed: {}", e),
+            AcoustIdError::MissingUserKey => write!(f, "acoustid_user_key is not set"),
+            AcoustIdError::NoMatch => write!(f, "no match found"),
+            AcoustIdError::Rejected(message) => write!(f, "submission rejected: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for AcoustIdError {
+    fn from(e: io::Error) -> Self {
+        AcoustIdError::Fingerprint(e)
+    }
+}
+
+impl From<reqwest::Error> for AcoustIdError {
+    fn from(e: reqwest::Error) -> Self {
+        AcoustIdError::Request(e)
+    }
+}
+
+pub struct Tags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+}
+
+#[derive(Deserialize)]
+struct Fingerprint {
+    duration: f64,
+    fingerprint: String,
+}
+
+// a fingerprint acoustid doesn't know yet, ready to be submitted
+pub struct UnmatchedFingerprint {
+    pub fingerprint: String,
+    pub duration: u32,
+}
+
+#[derive(Deserialize)]
+struct LookupResponse {
+    #[serde(default)]
+    results: Vec<LookupResult>,
+}
+
+#[derive(Deserialize)]
+struct LookupResult {
+    score: f64,
+    #[serde(default)]
+    recordings: Vec<LookupRecording>,
+}
+
+#[derive(Deserialize)]
+struct LookupRecording {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct SubmitResponse {
+    status: String,
+    error: Option<SubmitError>,
+}
+
+#[derive(Deserialize)]
+struct SubmitError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    title: String,
+}
+
+fn fingerprint(path: &Path) -> Result<Fingerprint, io::Error> {
+    let output = Command::new("fpcalc").arg("-json").arg(path).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    serde_json::from_slice(&output.stdout).map_err(io::Error::other)
+}
+
+// the musicbrainz recording id of the best scoring match
+fn lookup_recording(api_key: &str, fingerprint: &Fingerprint) -> Result<String, AcoustIdError> {
+    let duration = (fingerprint.duration.round() as u64).to_string();
+    let response: LookupResponse = reqwest::blocking::Client::new()
+        .get(LOOKUP_URL)
+        .query(&[
+            ("client", api_key),
+            ("meta", "recordingids"),
+            ("duration", duration.as_str()),
+            ("fingerprint", fingerprint.fingerprint.as_str()),
+        ])
+        .send()?
+        .error_for_status()?
+        .json()?;
+    response
+        .results
+        .into_iter()
+        .filter(|result| !result.recordings.is_empty())
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .and_then(|result| result.recordings.into_iter().next())
+        .map(|recording| recording.id)
+        .ok_or(AcoustIdError::NoMatch)
+}
+
+fn fetch_recording(id: &str) -> Result<Tags, AcoustIdError> {
+    let recording: Recording = reqwest::blocking::Client::new()
+        .get(format!("{}/{}", MUSICBRAINZ_URL, id))
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .query(&[("inc", "artists releases"), ("fmt", "json")])
+        .send()?
+        .error_for_status()?
+        .json()?;
+    let artist = recording
+        .artist_credit
+        .iter()
+        .map(|credit| format!("{}{}", credit.name, credit.joinphrase))
+        .collect::<String>();
+    Ok(Tags {
+        title: recording.title,
+        artist: if artist.is_empty() {
+            String::from("Unknown")
+        } else {
+            artist
+        },
+        album: recording
+            .releases
+            .into_iter()
+            .next()
+            .map(|release| release.title)
+            .unwrap_or_else(|| String::from("Unknown")),
+    })
+}
+
+// fingerprints the file with fpcalc and looks up its tags on acoustid and musicbrainz
+pub fn lookup(path: &Path) -> Result<Tags, AcoustIdError> {
+    let api_key = std::env::var("ACOUSTID_API_KEY").map_err(|_| AcoustIdError::MissingApiKey)?;
+    let fingerprint = fingerprint(path)?;
+    let id = lookup_recording(&api_key, &fingerprint)?;
+    fetch_recording(&id)
+}
+
+// fingerprints the file and hands the fingerprint back if acoustid has no
+// recording for it, None means it is already matched
+pub fn unmatched(path: &Path) -> Result<Option<UnmatchedFingerprint>, AcoustIdError> {
+    let api_key = std::env::var("ACOUSTID_API_KEY").map_err(|_| AcoustIdError::MissingApiKey)?;
+    let fingerprint = fingerprint(path)?;
+    match lookup_recording(&api_key, &fingerprint) {
+        Ok(_) => Ok(None),
+        Err(AcoustIdError::NoMatch) => Ok(Some(UnmatchedFingerprint {
+            duration: fingerprint.duration.round() as u32,
+            fingerprint: fingerprint.fingerprint,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// associates the fingerprint with a musicbrainz recording. acoustid takes
+// submissions in the background, so a lookup may not find it straight away
+pub fn submit(
+    fingerprint: &str,
+    duration: u32,
+    mb_recording_id: &str,
+    user_api_key: &str,
+) -> Result<(), AcoustIdError> {
+    let api_key = std::env::var("ACOUSTID_API_KEY").map_err(|_| AcoustIdError::MissingApiKey)?;
+    if user_api_key.is_empty() {
+        return Err(AcoustIdError::MissingUserKey);
+    }
+    let duration = duration.to_string();
+    // errors come back as json with a 400 status, so the body is read either way
+    let response: SubmitResponse = reqwest::blocking::Client::new()
+        .post(SUBMIT_URL)
+        .form(&[
+            ("client", api_key.as_str()),
+            ("user", user_api_key),
+            ("format", "json"),
+            ("duration.0", duration.as_str()),
+            ("fingerprint.0", fingerprint),
+            ("mbid.0", mb_recording_id),
+        ])
+        .send()?
+        .json()?;
+    match response.status.as_str() {
+        "ok" => Ok(()),
+        _ => Err(AcoustIdError::Rejected(
+            response
+                .error
+                .map(|error| error.message)
+                .unwrap_or(response.status),
+        )),
+    }
+}
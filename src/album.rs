@@ -0,0 +1,281 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    braille,
+    library::{
+        column_widths, default_columns, track_row, track_table, with_track_number_column,
+        LibraryWindow,
+    },
+    ui::Window,
+};
+use crossterm::event::KeyCode;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    io::{self, Stdout},
+    rc::Rc,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, TableState},
+    Frame,
+};
+
+// thumbnail size in terminal cells
+const THUMBNAIL_SIZE: u16 = 8;
+const CARD_WIDTH: u16 = 20;
+// thumbnail, album title and artist plus the card border
+const CARD_HEIGHT: u16 = THUMBNAIL_SIZE + 4;
+
+struct Album {
+    title: String,
+    artist: String,
+    tracks: Vec<AudioFile>,
+}
+
+pub struct AlbumBrowserWindow {
+    title: String,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    // told which album is open, so its track list follows along
+    library_window: Rc<RefCell<LibraryWindow>>,
+    albums: Vec<Album>,
+    selected: usize,
+    // number of cards per row, as of the last draw
+    columns: usize,
+    scroll_row: usize,
+    thumbnails: HashMap<usize, Option<Vec<String>>>,
+    open_album: Option<usize>,
+    track_state: TableState,
+}
+
+impl AlbumBrowserWindow {
+    pub fn new(
+        music_list: &[AudioFile],
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        library_window: Rc<RefCell<LibraryWindow>>,
+    ) -> Self {
+        Self {
+            title: String::from("Albums"),
+            audio_interface,
+            library_window,
+            albums: group_albums(music_list),
+            selected: 0,
+            columns: 1,
+            scroll_row: 0,
+            thumbnails: HashMap::new(),
+            open_album: None,
+            track_state: TableState::default(),
+        }
+    }
+
+    // decoding cover art is slow, so each thumbnail is rendered once on first draw
+    fn thumbnail(&mut self, i: usize) -> Option<Vec<String>> {
+        let album = &self.albums[i];
+        self.thumbnails
+            .entry(i)
+            .or_insert_with(|| {
+                album.tracks.iter().find_map(|track| {
+                    braille::render(
+                        &track.cached_album_art()?,
+                        THUMBNAIL_SIZE as u32,
+                        THUMBNAIL_SIZE as u32,
+                    )
+                })
+            })
+            .clone()
+    }
+
+    fn draw_grid(&mut self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let block = Block::default()
+            .title(self.get_title())
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Green));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        self.columns = (inner.width / CARD_WIDTH).max(1) as usize;
+        let visible_rows = (inner.height / CARD_HEIGHT).max(1) as usize;
+        let selected_row = self.selected / self.columns;
+        if selected_row < self.scroll_row {
+            self.scroll_row = selected_row;
+        } else if selected_row >= self.scroll_row + visible_rows {
+            self.scroll_row = selected_row + 1 - visible_rows;
+        }
+        let first = self.scroll_row * self.columns;
+        let last = (first + visible_rows * self.columns).min(self.albums.len());
+        for i in first..last {
+            let row = ((i - first) / self.columns) as u16;
+            let col = ((i - first) % self.columns) as u16;
+            let card = Rect::new(
+                inner.x + col * CARD_WIDTH,
+                inner.y + row * CARD_HEIGHT,
+                CARD_WIDTH.min(inner.width),
+                CARD_HEIGHT.min(inner.height),
+            );
+            let mut lines = match self.thumbnail(i) {
+                Some(thumbnail) => thumbnail.into_iter().map(Line::from).collect(),
+                None => {
+                    let mut placeholder = vec![
+                        Line::from(" ".repeat(THUMBNAIL_SIZE as usize));
+                        THUMBNAIL_SIZE as usize
+                    ];
+                    placeholder[THUMBNAIL_SIZE as usize / 2] = Line::from("No Art");
+                    placeholder
+                }
+            };
+            lines.push(Line::from(self.albums[i].title.clone()));
+            lines.push(Line::from(self.albums[i].artist.clone()));
+            let border_style = if i == self.selected {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            let card_widget = Paragraph::new(lines)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border_style),
+                )
+                .style(Style::default().fg(Color::Green))
+                .alignment(Alignment::Center);
+            f.render_widget(card_widget, card);
+        }
+    }
+
+    fn handle_grid_input(&mut self, key: KeyCode) {
+        let len = self.albums.len();
+        match key {
+            KeyCode::Left if self.selected > 0 => self.selected -= 1,
+            KeyCode::Right if self.selected + 1 < len => self.selected += 1,
+            KeyCode::Up if self.selected >= self.columns => self.selected -= self.columns,
+            KeyCode::Down if self.selected + self.columns < len => self.selected += self.columns,
+            KeyCode::Enter if self.selected < len => {
+                self.open_album = Some(self.selected);
+                self.track_state.select(Some(0));
+                let album = &self.albums[self.selected];
+                self.library_window
+                    .borrow_mut()
+                    .set_album_context(&album.title, &album.artist);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_album_input(&mut self, album: usize, key: KeyCode) {
+        let len = self.albums[album].tracks.len();
+        let selected = self.track_state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => self.track_state.select(Some(if selected == 0 {
+                len.saturating_sub(1)
+            } else {
+                selected - 1
+            })),
+            KeyCode::Down => {
+                self.track_state
+                    .select(Some(if selected + 1 >= len { 0 } else { selected + 1 }))
+            }
+            KeyCode::Enter => {
+                let mut tracks = self.albums[album].tracks[selected..].to_vec();
+                let mut audio_interface = self.audio_interface.borrow_mut();
+                audio_interface.hard_clear_queue();
+                audio_interface.append_to_queue(&mut tracks);
+            }
+            KeyCode::Char('p') => {
+                let mut tracks = self.albums[album].tracks.clone();
+                self.audio_interface
+                    .borrow_mut()
+                    .append_to_queue(&mut tracks);
+            }
+            KeyCode::Esc => {
+                self.open_album = None;
+                self.library_window.borrow_mut().clear_album_context();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Window for AlbumBrowserWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        match self.open_album {
+            Some(i) => {
+                let columns = with_track_number_column(&default_columns());
+                let widths = column_widths(&columns);
+                let audio_interface = self.audio_interface.borrow();
+                let rows = self.albums[i]
+                    .tracks
+                    .iter()
+                    .map(|file| track_row(file, audio_interface.get_currently_playing(), &columns))
+                    .collect::<Vec<_>>();
+                let table = track_table(rows, self.albums[i].title.as_str(), &columns, &widths);
+                f.render_stateful_widget(table, area, &mut self.track_state);
+            }
+            None => self.draw_grid(area, f),
+        }
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        match self.open_album {
+            Some(album) => self.handle_album_input(album, key),
+            None => self.handle_grid_input(key),
+        }
+        Ok(())
+    }
+
+    // the open album stays open if it is still in the library
+    fn on_library_changed(&mut self, music_list: &[AudioFile]) {
+        let open = self
+            .open_album
+            .map(|i| (self.albums[i].title.clone(), self.albums[i].artist.clone()));
+        self.albums = group_albums(music_list);
+        self.thumbnails.clear();
+        self.selected = self.selected.min(self.albums.len().saturating_sub(1));
+        self.open_album = open.as_ref().and_then(|(title, artist)| {
+            self.albums
+                .iter()
+                .position(|album| album.title == *title && album.artist == *artist)
+        });
+        match self.open_album {
+            Some(i) => {
+                let len = self.albums[i].tracks.len();
+                let selected = self.track_state.selected().unwrap_or(0);
+                self.track_state
+                    .select(Some(selected.min(len.saturating_sub(1))));
+            }
+            None if open.is_some() => self.library_window.borrow_mut().clear_album_context(),
+            None => {}
+        }
+    }
+}
+
+// by album artist, so a compilation stays one album whatever its track artists
+fn group_albums(music_list: &[AudioFile]) -> Vec<Album> {
+    let mut grouped: BTreeMap<(String, String), Vec<AudioFile>> = BTreeMap::new();
+    for file in music_list {
+        grouped
+            .entry((file.get_album_artist().clone(), file.get_album().clone()))
+            .or_default()
+            .push(file.clone());
+    }
+    grouped
+        .into_iter()
+        .map(|((artist, title), mut tracks)| {
+            tracks.sort_by_key(AudioFile::album_position);
+            Album {
+                title,
+                artist,
+                tracks,
+            }
+        })
+        .collect()
+}
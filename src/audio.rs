@@ -1,22 +1,97 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::io::Error;
-use std::io::{BufReader, ErrorKind};
+use std::io::{BufReader, Cursor, ErrorKind};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::acoustid::{self, AcoustIdError};
+use crate::bpm;
+use crate::cue::{CueTrack, FRAMES_PER_SECOND};
+use crate::library;
+use crate::log;
+use crate::log::Level;
+use crate::resample::{self, ResamplerQuality};
 
 use audiotags::Tag;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use rodio::cpal;
-use rodio::cpal::traits::HostTrait;
+use rodio::cpal::traits::{HostTrait, StreamTrait};
+use rodio::dynamic_mixer::{self, DynamicMixerController};
 use rodio::DeviceTrait;
+use rodio::{Sample, Source};
+use serde::{Deserialize, Serialize};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, StandardTagKey};
+use symphonia::core::probe::Hint;
 
-#[derive(Clone)]
+/// A track in the library, queue or a playlist, with the tags rmus shows.
+/// Most tracks are files on disk. Tracks from Tidal, Subsonic, podcasts and
+/// M3U playlists are streams, which are read from a URL instead.
+///
+/// Two `AudioFile`s are equal when they are the same file. Tracks split
+/// from one file by a cue sheet also compare their start offset.
+///
+/// # Examples
+///
+/// ```
+/// use music_player::audio::AudioFile;
+///
+/// let track = AudioFile::from_stream(
+///     String::from("https://example.com/track.mp3"),
+///     String::from("Title"),
+///     String::from("Artist"),
+///     String::from("Album"),
+///     2023,
+///     180.0,
+/// );
+/// assert_eq!(track.get_duration(), "3:00");
+/// assert!(!track.is_lossless());
+/// ```
+#[derive(Clone, Serialize)]
 pub struct AudioFile {
     path: PathBuf,
     title: String,
     artist: String,
+    // TPE2 / ALBUMARTIST, or the track artist when the tag isn't set
+    album_artist: String,
     year: i32,
     album: String,
+    // TRCK / TRACKNUMBER and TPOS / DISCNUMBER, without the totals
+    track_number: Option<u16>,
+    disc_number: Option<u16>,
+    genre: String,
     duration: f64,
+    // set for tracks streamed from a remote server instead of read from disk
+    stream_url: Option<String>,
+    // set for tracks that start part way through a file split by a cue sheet
+    cue_offset_frames: Option<u64>,
+    // 1-5 stars, kept in the library cache rather than the tags
+    rating: Option<u8>,
+    play_count: u64,
+    // contents of a matching .lrc file, or the lyrics embedded in the tags
+    lyrics: Option<String>,
+    // from the tags, or detected on demand and kept in the library cache
+    bpm: Option<f32>,
+    // silence at the start of the file that playback skips, kept in the library cache
+    start_offset_secs: f64,
+    // set for tracks streamed from tidal, so the queue can be saved as a tidal playlist
+    tidal_id: Option<u64>,
+    // whether a flac's audio still matches its md5, once verified, kept in the library cache
+    integrity_ok: Option<bool>,
+    // from the file extension, streams count as lossy
+    is_lossless: bool,
+}
+
+// tracks from a cue sheet share their path, so the offset is part of the identity
+impl PartialEq for AudioFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.cue_offset_frames == other.cue_offset_frames
+    }
 }
 
 const EMPTY_ALBUM: audiotags::types::Album = audiotags::types::Album {
@@ -26,61 +101,558 @@ const EMPTY_ALBUM: audiotags::types::Album = audiotags::types::Album {
 };
 
 impl AudioFile {
+    /// Reads the tags and length of the audio file at `path`.
+    ///
+    /// audiotags reads MP3, MP4 and FLAC. Other formats fall back to the tags
+    /// symphonia finds while probing the file. Tags that are missing read as
+    /// `"Unknown"`, or as `0` for the year.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::NotFound`] when neither reader
+    /// can open the file. This happens for missing files and for files that
+    /// aren't audio.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use music_player::audio::AudioFile;
+    ///
+    /// let track = AudioFile::new(&String::from("/music/song.flac"))?;
+    /// println!("{} by {}", track.get_title(), track.get_artist());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
     pub fn new(path: &String) -> Result<Self, std::io::Error> {
-        if let Ok(tag) = Tag::new().read_from_path(path) {
-            // get duration, scaffolding for when an implementation 
-            // for finding the bitrate and estimating the duration
-            let duration: f64 = match tag.duration() {
-                Some(duration) => duration,
-                None => 0.0, 
-            };
-            Ok(Self {
-                path: PathBuf::from(path),
-                title: tag.title().unwrap_or("Unknown").to_string(),
-                year: tag.year().unwrap_or(0),
-                artist: tag.artist().unwrap_or("Unknown").to_string(),
-                album: tag.album().unwrap_or(EMPTY_ALBUM).title.to_string(),
-                duration,
-            })
-        } else {
-            Err(std::io::Error::new(
-                ErrorKind::NotFound,
-                "Failed to read file",
-            ))
+        match Tag::new().read_from_path(path) {
+            Ok(tag) => {
+                // get duration, scaffolding for when an implementation 
+                // for finding the bitrate and estimating the duration
+                let duration: f64 = tag.duration().unwrap_or(0.0);
+                let artist = tag.artist().unwrap_or("Unknown").to_string();
+                Ok(Self {
+                    path: PathBuf::from(path),
+                    title: tag.title().unwrap_or("Unknown").to_string(),
+                    year: tag.year().unwrap_or(0),
+                    album_artist: tag
+                        .album_artist()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| artist.clone()),
+                    artist,
+                    album: tag.album().unwrap_or(EMPTY_ALBUM).title.to_string(),
+                    track_number: tag.track_number(),
+                    disc_number: tag.disc_number(),
+                    genre: tag.genre().unwrap_or("Unknown").to_string(),
+                    duration,
+                    stream_url: None,
+                    cue_offset_frames: None,
+                    rating: None,
+                    play_count: 0,
+                    lyrics: read_lyrics(Path::new(path)),
+                    bpm: bpm::read_tag(Path::new(path)),
+                    start_offset_secs: 0.0,
+                    tidal_id: None,
+                    integrity_ok: None,
+                    is_lossless: is_lossless(Path::new(path)),
+                })
+            }
+            // audiotags only knows mp3, mp4 and flac, symphonia reads the rest
+            Err(e) => Self::from_symphonia(Path::new(path)).map_err(|fallback| {
+                std::io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("Failed to read file: {} ({})", e, fallback),
+                )
+            }),
+        }
+    }
+
+    fn from_symphonia(path: &Path) -> Result<Self, std::io::Error> {
+        let file = std::fs::File::open(path)?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+        let mut probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let params = &probed
+            .format
+            .default_track()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no audio track"))?
+            .codec_params;
+        let duration = match (params.n_frames, params.sample_rate) {
+            (Some(frames), Some(rate)) if rate > 0 => frames as f64 / rate as f64,
+            _ => 0.0,
+        };
+        // tags are either part of the container or found in front of it while probing
+        let revision = probed.format.metadata().current().cloned().or_else(|| {
+            probed
+                .metadata
+                .get()
+                .and_then(|metadata| metadata.current().cloned())
+        });
+        let mut audio_file = Self::from_stream(
+            String::new(),
+            String::from("Unknown"),
+            String::from("Unknown"),
+            String::from("Unknown"),
+            0,
+            duration,
+        );
+        let mut album_artist = None;
+        for tag in revision.iter().flat_map(|revision| revision.tags()) {
+            // riff info strings keep their nul padding
+            let value = tag.value.to_string().trim_end_matches('\0').to_string();
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => audio_file.title = value,
+                Some(StandardTagKey::Artist) => audio_file.artist = value,
+                Some(StandardTagKey::AlbumArtist) => album_artist = Some(value),
+                Some(StandardTagKey::Album) => audio_file.album = value,
+                Some(StandardTagKey::Genre) => audio_file.genre = value,
+                Some(StandardTagKey::TrackNumber) => {
+                    audio_file.track_number = leading_number(&value)
+                }
+                Some(StandardTagKey::DiscNumber) => audio_file.disc_number = leading_number(&value),
+                Some(StandardTagKey::Date) => {
+                    audio_file.year = value.get(..4).and_then(|y| y.parse().ok()).unwrap_or(0)
+                }
+                _ => {}
+            }
         }
+        audio_file.album_artist = album_artist.unwrap_or_else(|| audio_file.artist.clone());
+        audio_file.path = path.to_path_buf();
+        audio_file.stream_url = None;
+        audio_file.lyrics = read_lyrics(path);
+        audio_file.bpm = bpm::read_tag(path);
+        audio_file.is_lossless = is_lossless(path);
+        Ok(audio_file)
+    }
+
+    /// A track played from `stream_url` rather than a file. Its path is the
+    /// URL, so it stays the same track even after
+    /// [`set_stream_url`](Self::set_stream_url) changes the URL.
+    pub fn from_stream(
+        stream_url: String,
+        title: String,
+        artist: String,
+        album: String,
+        year: i32,
+        duration: f64,
+    ) -> Self {
+        Self {
+            path: PathBuf::from(&stream_url),
+            title,
+            album_artist: artist.clone(),
+            artist,
+            year,
+            album,
+            track_number: None,
+            disc_number: None,
+            genre: String::from("Unknown"),
+            duration,
+            stream_url: Some(stream_url),
+            cue_offset_frames: None,
+            rating: None,
+            play_count: 0,
+            lyrics: None,
+            bpm: None,
+            start_offset_secs: 0.0,
+            tidal_id: None,
+            integrity_ok: None,
+            is_lossless: false,
+        }
+    }
+
+    /// The audio files directly inside `dir`, in album order.
+    ///
+    /// Tagged track and disc numbers decide the order, and filenames break
+    /// ties. Files that can't be read are skipped, and so are cue sheets.
+    pub fn from_dir(dir: &Path) -> Vec<AudioFile> {
+        let mut paths =
+            library::recursive_file_walk(dir, 1, &[], &mut HashSet::new(), &mut Vec::new());
+        paths.sort_by_key(|path| Path::new(path).file_name().map(|name| name.to_os_string()));
+        let mut files = paths
+            .iter()
+            .filter(|path| !path.ends_with(".cue"))
+            .filter_map(|path| AudioFile::new(path).ok())
+            .collect::<Vec<_>>();
+        // track numbers win where they're tagged, filenames break ties
+        files.sort_by_key(AudioFile::album_position);
+        files
     }
 
+    /// One track of a file that a cue sheet splits up. It uses the cue
+    /// sheet's tags and starts part way through the file.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`AudioFile::new`] when the file the cue sheet points to
+    /// can't be read.
+    pub fn from_cue_track(track: &CueTrack) -> Result<Self, std::io::Error> {
+        let mut audio_file = AudioFile::new(&track.file.to_string_lossy().to_string())?;
+        let start = track.start_frame as f64 / FRAMES_PER_SECOND;
+        let end = match track.end_frame {
+            Some(end_frame) => end_frame as f64 / FRAMES_PER_SECOND,
+            None => audio_file.duration,
+        };
+        audio_file.title = track.title.clone();
+        audio_file.artist = track.artist.clone();
+        audio_file.album_artist = track.album_artist.clone();
+        audio_file.album = track.album.clone();
+        audio_file.track_number = track.number;
+        audio_file.duration = (end - start).max(0.0);
+        audio_file.cue_offset_frames = Some(track.start_frame);
+        // lyrics belong to the whole file, so their timing wouldn't line up
+        audio_file.lyrics = None;
+        Ok(audio_file)
+    }
+
+    /// Where the file is, or the URL for streams.
     pub fn get_path(&self) -> &Path {
         self.path.as_path()
     }
 
+    /// The track title, `"Unknown"` when it isn't tagged.
     pub fn get_title(&self) -> &String {
         &self.title
     }
 
+    /// The album title, `"Unknown"` when it isn't tagged.
     pub fn get_album(&self) -> &String {
         &self.album
     }
 
+    /// The track artist, `"Unknown"` when it isn't tagged.
     pub fn get_artist(&self) -> &String {
         &self.artist
     }
 
+    /// The album artist. This is the track artist when the file has no
+    /// album artist tag.
+    pub fn get_album_artist(&self) -> &String {
+        &self.album_artist
+    }
+
+    /// The track number, without the track total.
+    pub fn get_track_number(&self) -> Option<u16> {
+        self.track_number
+    }
+
+    /// The disc and track number, for sorting tracks into album order.
+    /// Untagged tracks sort last.
+    pub fn album_position(&self) -> (u16, u16) {
+        (
+            self.disc_number.unwrap_or(1),
+            self.track_number.unwrap_or(u16::MAX),
+        )
+    }
+
+    /// The genre, `"Unknown"` when it isn't tagged.
+    pub fn get_genre(&self) -> &String {
+        &self.genre
+    }
+
+    /// The length in seconds, `0.0` when it isn't known.
     pub fn get_raw_duration(&self) -> f64 {
         self.duration
     }
 
+    /// The length as `m:ss`.
     pub fn get_duration(&self) -> String {
         let minutes = self.duration as i32 / 60;
         let seconds = self.duration as i32 % 60;
         format!("{}:{:02}", minutes, seconds)
     }
 
+    /// The release year, `0` when it isn't tagged.
     pub fn get_year(&self) -> i32 {
         self.year
     }
+
+    /// The cover embedded in the file's tags, as it was stored. This is
+    /// usually a JPEG or PNG. Streams have no embedded cover.
+    pub fn get_album_art(&self) -> Option<Vec<u8>> {
+        // audiotags panics on paths without a known extension, like stream urls
+        if self.stream_url.is_some() {
+            return None;
+        }
+        let tag = Tag::new().read_from_path(&self.path).ok()?;
+        tag.album_cover().map(|cover| cover.data.to_vec())
+    }
+
+    /// The embedded cover saved as a PNG under `cache_dir`.
+    ///
+    /// The cover is extracted the first time it is asked for, and the cached
+    /// file is reused after that. The file name is a hash of the track's path,
+    /// so tracks split by a cue sheet share one file. Returns `None` when the
+    /// track has no cover or the file can't be written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use music_player::audio::{art_cache_dir, AudioFile};
+    ///
+    /// let track = AudioFile::new(&String::from("/music/song.flac"))?;
+    /// if let Some(png) = track.embedded_art_path(&art_cache_dir()) {
+    ///     println!("cover art at {}", png.display());
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn embedded_art_path(&self, cache_dir: &Path) -> Option<PathBuf> {
+        let name = blake3::hash(self.path.to_string_lossy().as_bytes());
+        let path = cache_dir.join(format!("{}.png", name.to_hex()));
+        if path.exists() {
+            return Some(path);
+        }
+        let art = image::load_from_memory(&self.get_album_art()?).ok()?;
+        if let Err(e) = std::fs::create_dir_all(cache_dir) {
+            log!(
+                Level::Warn,
+                "could not create {}: {}",
+                cache_dir.display(),
+                e
+            );
+            return None;
+        }
+        art.save_with_format(&path, image::ImageFormat::Png).ok()?;
+        Some(path)
+    }
+
+    /// The PNG from [`embedded_art_path`](Self::embedded_art_path) in
+    /// [`art_cache_dir`]. Use it in place of
+    /// [`get_album_art`](Self::get_album_art) when drawing.
+    pub fn cached_album_art(&self) -> Option<Vec<u8>> {
+        std::fs::read(self.embedded_art_path(&art_cache_dir())?).ok()
+    }
+
+    /// The URL streams play from, `None` for files.
+    pub fn get_stream_url(&self) -> Option<&str> {
+        self.stream_url.as_deref()
+    }
+
+    /// Changes the URL a stream plays from. The path stays the URL the track
+    /// was queued with, so it is still the same track.
+    pub fn set_stream_url(&mut self, stream_url: String) {
+        self.stream_url = Some(stream_url);
+    }
+
+    /// Where a cue sheet track starts in its file, in CD frames. There are 75
+    /// frames a second.
+    pub fn get_cue_offset_frames(&self) -> Option<u64> {
+        self.cue_offset_frames
+    }
+
+    /// The rating from 1 to 5 stars. It is kept in the library cache, not
+    /// the tags.
+    pub fn get_rating(&self) -> Option<u8> {
+        self.rating
+    }
+
+    /// Sets the rating, clamped to 1 to 5 stars.
+    pub fn set_rating(&mut self, rating: Option<u8>) {
+        self.rating = rating.map(|stars| stars.clamp(1, 5));
+    }
+
+    /// How many times the track has been played, from the library cache.
+    pub fn get_play_count(&self) -> u64 {
+        self.play_count
+    }
+
+    /// Sets the play count.
+    pub fn set_play_count(&mut self, play_count: u64) {
+        self.play_count = play_count;
+    }
+
+    /// The tempo, from the tags or detected by the library.
+    pub fn get_bpm(&self) -> Option<f32> {
+        self.bpm
+    }
+
+    /// Sets the tempo.
+    pub fn set_bpm(&mut self, bpm: Option<f32>) {
+        self.bpm = bpm;
+    }
+
+    /// Whether a FLAC's audio matched its MD5 when it was last checked.
+    /// `None` until [`verify_flac`](Self::verify_flac) has run.
+    pub fn get_integrity_ok(&self) -> Option<bool> {
+        self.integrity_ok
+    }
+
+    /// Whether the file is lossless: FLAC, WAV, AIFF, Monkey's Audio or
+    /// WavPack. Streams always count as lossy.
+    pub fn is_lossless(&self) -> bool {
+        self.is_lossless
+    }
+
+    /// Records the result of [`verify_flac`](Self::verify_flac).
+    pub fn set_integrity_ok(&mut self, integrity_ok: Option<bool>) {
+        self.integrity_ok = integrity_ok;
+    }
+
+    /// Decodes the whole FLAC and compares it with the MD5 the encoder stored.
+    ///
+    /// Returns `Ok(true)` when the file has no MD5 to compare against.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidData`] when the file
+    /// isn't a FLAC or can't be decoded. A damaged file usually fails here
+    /// rather than returning `Ok(false)`.
+    pub fn verify_flac(&self) -> Result<bool, std::io::Error> {
+        let invalid = |e: claxon::Error| Error::new(ErrorKind::InvalidData, e);
+        let mut reader = claxon::FlacReader::open(&self.path).map_err(invalid)?;
+        let info = reader.streaminfo();
+        // encoders may leave it unset, there is nothing to compare against then
+        if info.md5sum == [0; 16] {
+            return Ok(true);
+        }
+        let bytes_per_sample = info.bits_per_sample.div_ceil(8) as usize;
+        let mut md5 = md5::Context::new();
+        let mut bytes = Vec::new();
+        let mut blocks = reader.blocks();
+        let mut buffer = Vec::new();
+        while let Some(block) = blocks.read_next_or_eof(buffer).map_err(invalid)? {
+            // the md5 covers the samples interleaved, little endian, at their own width
+            for i in 0..block.duration() {
+                for channel in 0..block.channels() {
+                    let sample = block.sample(channel, i).to_le_bytes();
+                    bytes.extend_from_slice(&sample[..bytes_per_sample]);
+                }
+            }
+            md5.consume(&bytes);
+            bytes.clear();
+            buffer = block.into_buffer();
+        }
+        Ok(md5.finalize().0 == info.md5sum)
+    }
+
+    /// How much silence at the start of the file playback skips, in seconds.
+    pub fn get_start_offset_secs(&self) -> f64 {
+        self.start_offset_secs
+    }
+
+    /// Sets the silence to skip. Negative values count as `0.0`.
+    pub fn set_start_offset_secs(&mut self, start_offset_secs: f64) {
+        self.start_offset_secs = start_offset_secs.max(0.0);
+    }
+
+    /// The Tidal track id, for tracks streamed from Tidal.
+    pub fn get_tidal_id(&self) -> Option<u64> {
+        self.tidal_id
+    }
+
+    /// Sets the Tidal track id.
+    pub fn set_tidal_id(&mut self, tidal_id: Option<u64>) {
+        self.tidal_id = tidal_id;
+    }
+
+    /// How long the track plays once the silence at the start is skipped, in
+    /// seconds.
+    pub fn get_playing_duration(&self) -> f64 {
+        (self.duration - self.start_offset_secs).max(0.0)
+    }
+
+    /// The contents of a matching `.lrc` file, or the lyrics in the tags.
+    pub fn get_lyrics(&self) -> Option<&str> {
+        self.lyrics.as_deref()
+    }
+
+    /// Replaces the title, artist and album. An album artist that only fell
+    /// back to the track artist follows the new artist.
+    ///
+    /// Only this copy changes. The file's tags are written by the tag editor.
+    pub fn set_tags(&mut self, title: String, artist: String, album: String) {
+        // an album artist that only fell back to the track artist follows it
+        if self.album_artist == self.artist {
+            self.album_artist = artist.clone();
+        }
+        self.title = title;
+        self.artist = artist;
+        self.album = album;
+    }
+
+    /// Sets the release year.
+    pub fn set_year(&mut self, year: i32) {
+        self.year = year;
+    }
+
+    /// Sets the genre.
+    pub fn set_genre(&mut self, genre: String) {
+        self.genre = genre;
+    }
+
+    /// Identifies the file by its audio fingerprint and fills in the title,
+    /// artist and album AcoustID has for it.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `ACOUSTID_API_KEY` isn't set, when fpcalc can't fingerprint
+    /// the file, when the request fails, or when AcoustID doesn't know the
+    /// recording.
+    pub fn lookup_and_fill_tags(&mut self) -> Result<(), AcoustIdError> {
+        let tags = acoustid::lookup(&self.path)?;
+        self.set_tags(tags.title, tags.artist, tags.album);
+        Ok(())
+    }
+}
+
+/// Where [`AudioFile::embedded_art_path`] is usually pointed. This is
+/// `$XDG_CACHE_HOME/rmus/art`, or `~/.cache/rmus/art` when
+/// `XDG_CACHE_HOME` isn't set.
+pub fn art_cache_dir() -> PathBuf {
+    let cache_home = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => home::home_dir().unwrap_or_default().join(".cache"),
+    };
+    cache_home.join("rmus").join("art")
+}
+
+// flac, wav, aiff, monkey's audio and wavpack. mp3, ogg, aac, opus and
+// wma are lossy
+fn is_lossless(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_lowercase().as_str(),
+                "flac" | "wav" | "aif" | "aiff" | "ape" | "wv"
+            )
+        })
+}
+
+// "3/12" style numbers, as vorbis comments and riff info often carry them
+fn leading_number(value: &str) -> Option<u16> {
+    value.split('/').next()?.trim().parse().ok()
 }
 
+// an .lrc file next to the track wins over embedded lyrics since it has timings
+fn read_lyrics(path: &Path) -> Option<String> {
+    if let Ok(lrc) = std::fs::read_to_string(path.with_extension("lrc")) {
+        return Some(lrc);
+    }
+    let tag = id3::Tag::read_from_path(path).ok()?;
+    let lyrics = tag.lyrics().next().map(|lyrics| lyrics.text.clone());
+    lyrics
+}
+
+/// The output devices of the default cpal host, and which one is in use.
+///
+/// # Examples
+///
+/// ```no_run
+/// use music_player::audio::Devices;
+///
+/// let devices = Devices::new(0);
+/// for (i, name) in devices.get_device_names().iter().enumerate() {
+///     println!("{}: {}", i, name);
+/// }
+/// ```
 pub struct Devices {
     devices: Vec<rodio::Device>,
     device_names: Vec<String>,
@@ -88,6 +660,13 @@ pub struct Devices {
 }
 
 impl Devices {
+    /// Lists the output devices and selects `curr_device`. An index past the
+    /// end selects the last device. Devices whose name can't be read are
+    /// left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the host can't list its output devices.
     pub fn new(curr_device: usize) -> Self {
         let device_list = match cpal::default_host().output_devices() {
             Ok(devices) => devices,
@@ -111,112 +690,1098 @@ impl Devices {
             .collect::<Vec<_>>();
         // get index of current device:
         Devices {
+            current_device: curr_device.min(devices.len().saturating_sub(1)),
             devices,
             device_names,
-            current_device: curr_device,
         }
     }
 
+    /// The device names, in the same order as their indices.
     pub fn get_device_names(&self) -> Vec<String> {
         self.device_names.clone()
     }
 
+    /// The device at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `index` is out of range. A saved index can go out of range
+    /// once a device is unplugged, so use
+    /// [`get_device_by_index_or_default`](Self::get_device_by_index_or_default)
+    /// for those.
     pub fn get_device_by_index(&self, index: usize) -> &rodio::Device {
         &self.devices[index]
     }
 
+    /// Misspelled [`get_device_by_index`](Self::get_device_by_index).
+    ///
+    /// # Panics
+    ///
+    /// Panics when `index` is out of range.
+    #[deprecated(note = "use get_device_by_index")]
+    #[allow(dead_code)]
+    pub fn get_deivce_by_index(&self, index: usize) -> &rodio::Device {
+        self.get_device_by_index(index)
+    }
+
+    /// The device at `index`, or the last device when `index` is out of range.
+    ///
+    /// # Panics
+    ///
+    /// Panics when there are no output devices at all.
+    pub fn get_device_by_index_or_default(&self, index: usize) -> &rodio::Device {
+        self.get_device_by_index(index.min(self.devices.len().saturating_sub(1)))
+    }
+
+    /// The index of the device in use.
     pub fn get_current_device(&self) -> usize {
         self.current_device
     }
+
+    fn set_current_device(&mut self, index: usize) {
+        self.current_device = index;
+    }
+
+    /// The default host's input devices. The list is empty when the host
+    /// can't list them.
+    pub fn get_input_devices() -> Vec<rodio::Device> {
+        match cpal::default_host().input_devices() {
+            Ok(devices) => devices.collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// A device that records what is being played, for the visualizer. WASAPI
+    /// can record any output device, so this is the default output device.
+    #[cfg(target_os = "windows")]
+    pub fn get_loopback_device() -> Option<rodio::Device> {
+        cpal::default_host().default_output_device()
+    }
+
+    /// A device that records what is being played, for the visualizer. This
+    /// is a monitor source, like PulseAudio's `*.monitor` sources. `None`
+    /// when the host has none.
+    #[cfg(not(target_os = "windows"))]
+    pub fn get_loopback_device() -> Option<rodio::Device> {
+        Devices::get_input_devices().into_iter().find(|device| {
+            device
+                .name()
+                .is_ok_and(|name| name.ends_with(".monitor") || name.starts_with("Monitor of"))
+        })
+    }
+
+    // records the loopback device into samples for the visualizer, in place of
+    // the samples tapped from the playing source. paused until the visualizer
+    // is shown
+    fn capture_loopback(
+        device: &rodio::Device,
+        samples: SampleBuffer,
+    ) -> Result<cpal::Stream, Box<dyn std::error::Error>> {
+        // wasapi loopback records an output device in its output format
+        let supported = device
+            .default_input_config()
+            .or_else(|_| device.default_output_config())?;
+        let config = supported.config();
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::I16 => build_capture_stream::<i16>(device, &config, samples)?,
+            cpal::SampleFormat::U16 => build_capture_stream::<u16>(device, &config, samples)?,
+            cpal::SampleFormat::I32 => build_capture_stream::<i32>(device, &config, samples)?,
+            cpal::SampleFormat::F64 => build_capture_stream::<f64>(device, &config, samples)?,
+            _ => build_capture_stream::<f32>(device, &config, samples)?,
+        };
+        // some hosts start streams as soon as they're built
+        stream.pause()?;
+        Ok(stream)
+    }
+
+    /// Opens `device` for playback and returns the stream with a sink on it.
+    ///
+    /// rodio always opens a device with its default buffer size. For a fixed
+    /// `buffer_size` in frames, the cpal stream is built here instead. It is
+    /// fed from a mixer in the device's channel count and sample rate. Keep
+    /// the stream alive for as long as the sink plays.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the device is gone or doesn't support the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use music_player::audio::Devices;
+    ///
+    /// let devices = Devices::new(0);
+    /// let device = devices.get_device_by_index_or_default(0);
+    /// let (_stream, sink) = Devices::open_stream(device, Some(1024))?;
+    /// sink.set_volume(0.5);
+    /// # Ok::<(), rodio::StreamError>(())
+    /// ```
+    pub fn open_stream(
+        device: &rodio::Device,
+        buffer_size: Option<u32>,
+    ) -> Result<(OutputStream, rodio::Sink), rodio::StreamError> {
+        let Some(buffer_size) = buffer_size else {
+            let (stream, stream_handle) = rodio::OutputStream::try_from_device(device)?;
+            let stream = OutputStream::Default(stream, stream_handle);
+            let sink = stream.new_sink().ok_or(rodio::StreamError::NoDevice)?;
+            return Ok((stream, sink));
+        };
+        let supported = device.default_output_config()?;
+        let mut config = supported.config();
+        config.buffer_size = cpal::BufferSize::Fixed(buffer_size);
+        // a mixer like rodio's own stream has, so sinks can play over each other
+        let (mixer, source) = dynamic_mixer::mixer::<f32>(config.channels, config.sample_rate.0);
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::I16 => build_stream::<i16>(device, &config, source)?,
+            cpal::SampleFormat::U16 => build_stream::<u16>(device, &config, source)?,
+            cpal::SampleFormat::I32 => build_stream::<i32>(device, &config, source)?,
+            cpal::SampleFormat::F64 => build_stream::<f64>(device, &config, source)?,
+            _ => build_stream::<f32>(device, &config, source)?,
+        };
+        stream.play()?;
+        let stream = OutputStream::Fixed(stream, mixer);
+        let sink = stream.new_sink().ok_or(rodio::StreamError::NoDevice)?;
+        Ok((stream, sink))
+    }
+}
+
+fn build_stream<T>(
+    device: &rodio::Device,
+    config: &cpal::StreamConfig,
+    mut source: impl Iterator<Item = f32> + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    device.build_output_stream::<T, _, _>(
+        config,
+        move |data, _| {
+            data.iter_mut()
+                .for_each(|d| *d = T::from_sample(source.next().unwrap_or(0.0)))
+        },
+        |err| log!(Level::Error, "an error occurred on output stream: {}", err),
+        None,
+    )
+}
+
+fn build_capture_stream<T>(
+    device: &rodio::Device,
+    config: &cpal::StreamConfig,
+    samples: SampleBuffer,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    device.build_input_stream::<T, _, _>(
+        config,
+        move |data: &[T], _| {
+            let mut samples = samples.lock().unwrap();
+            samples.extend(data.iter().map(|d| cpal::Sample::to_sample::<f32>(*d)));
+            while samples.len() > SAMPLE_BUFFER_SIZE {
+                samples.pop_front();
+            }
+        },
+        |err| {
+            log!(
+                Level::Error,
+                "an error occurred on loopback stream: {}",
+                err
+            )
+        },
+        None,
+    )
+}
+
+// held so the stream keeps playing, dropping it stops the output. the
+// handle or mixer is where new sinks are added
+pub enum OutputStream {
+    Default(
+        #[allow(dead_code)] rodio::OutputStream,
+        rodio::OutputStreamHandle,
+    ),
+    Fixed(
+        #[allow(dead_code)] cpal::Stream,
+        Arc<DynamicMixerController<f32>>,
+    ),
+    // no device at all. dropping the sender stops the thread pulling the mixer
+    Headless(
+        #[allow(dead_code)] mpsc::Sender<()>,
+        Arc<DynamicMixerController<f32>>,
+    ),
+}
+
+// how much the headless stream pulls at a time
+const HEADLESS_CHUNK: Duration = Duration::from_millis(10);
+
+impl OutputStream {
+    /// A stream that plays into nothing, with a sink on it. Samples are
+    /// pulled at `sample_rate` on a thread of its own, so tracks still take
+    /// their length to play and end like they would on a device. For tests
+    /// and machines without an output device.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use music_player::audio::{AudioInterface, Devices, OutputStream};
+    ///
+    /// let (stream, sink) = OutputStream::headless(2, 44_100);
+    /// let audio_interface = AudioInterface::new(stream, sink, Devices::new(0), None);
+    /// assert!(audio_interface.get_currently_playing().is_none());
+    /// ```
+    pub fn headless(channels: u16, sample_rate: u32) -> (Self, rodio::Sink) {
+        let (mixer, mut source) = dynamic_mixer::mixer::<f32>(channels, sample_rate);
+        let (alive, stopped) = mpsc::channel();
+        let chunk = (sample_rate as f64 * channels as f64 * HEADLESS_CHUNK.as_secs_f64()) as usize;
+        std::thread::spawn(move || {
+            while let Err(TryRecvError::Empty) = stopped.try_recv() {
+                source.by_ref().take(chunk).for_each(drop);
+                std::thread::sleep(HEADLESS_CHUNK);
+            }
+        });
+        let (sink, queue) = rodio::Sink::new_idle();
+        mixer.add(queue);
+        (OutputStream::Headless(alive, mixer), sink)
+    }
+
+    fn new_sink(&self) -> Option<rodio::Sink> {
+        match self {
+            OutputStream::Default(_, handle) => rodio::Sink::try_new(handle).ok(),
+            OutputStream::Fixed(_, mixer) | OutputStream::Headless(_, mixer) => {
+                let (sink, queue) = rodio::Sink::new_idle();
+                mixer.add(queue);
+                Some(sink)
+            }
+        }
+    }
+}
+
+// counts the samples pulled through a source by the output. unlike wall
+// clock time it stops while playback is paused or stalls under load
+struct PositionedSource<I> {
+    input: I,
+    samples: Arc<AtomicU64>,
+}
+
+impl<I: Source> PositionedSource<I>
+where
+    I::Item: Sample,
+{
+    fn new(input: I, samples: Arc<AtomicU64>) -> Self {
+        Self { input, samples }
+    }
+}
+
+impl<I: Source> Iterator for PositionedSource<I>
+where
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let sample = self.input.next()?;
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        Some(sample)
+    }
+}
+
+impl<I: Source> Source for PositionedSource<I>
+where
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+// the playing source's sample counter, along with what's needed to turn it into seconds
+struct Position {
+    samples: Arc<AtomicU64>,
+    channels: u16,
+    sample_rate: u32,
+    // where in the track the source started, after a seek or for a cue track
+    start: f64,
+}
+
+impl Position {
+    fn position_secs(&self) -> f64 {
+        let frames = self.samples.load(Ordering::Relaxed) as f64 / self.channels.max(1) as f64;
+        self.start + frames / self.sample_rate.max(1) as f64
+    }
+}
+
+// the most recently played samples, shared with the visualizer
+pub type SampleBuffer = Arc<Mutex<VecDeque<f32>>>;
+
+const SAMPLE_BUFFER_SIZE: usize = 4096;
+// samples are handed over in batches so the audio thread isn't locking for every one
+const TAP_BATCH_SIZE: usize = 256;
+
+// passes samples through unchanged while copying them into a SampleBuffer
+struct SampleTap<S> {
+    source: S,
+    samples: SampleBuffer,
+    pending: Vec<f32>,
+}
+
+impl<S> SampleTap<S> {
+    fn new(source: S, samples: SampleBuffer) -> Self {
+        Self {
+            source,
+            samples,
+            pending: Vec::with_capacity(TAP_BATCH_SIZE),
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut samples = self.samples.lock().unwrap();
+        samples.extend(self.pending.drain(..));
+        while samples.len() > SAMPLE_BUFFER_SIZE {
+            samples.pop_front();
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for SampleTap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.source.next()?;
+        self.pending.push(sample);
+        if self.pending.len() >= TAP_BATCH_SIZE {
+            self.flush();
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for SampleTap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
 }
 
-struct Track {
-    start_time: Instant,
-    pause_time: Option<Instant>,
-    pause_duration: f64,
+// plays through unchanged until triggered, then ramps the volume down to
+// nothing over the fade length and ends the source
+struct FadeOut<I> {
+    input: I,
+    trigger: Arc<AtomicBool>,
+    fade_samples: usize,
+    remaining: Option<usize>,
 }
 
-impl Track {
-    fn new() -> Self {
+impl<I: Source<Item = f32>> FadeOut<I> {
+    fn new(input: I, fade: Duration, trigger: Arc<AtomicBool>) -> Self {
+        let samples_per_sec = input.sample_rate() as f64 * input.channels() as f64;
         Self {
-            start_time: Instant::now(),
-            pause_time: None,
-            pause_duration: 0.0,
+            input,
+            trigger,
+            fade_samples: (fade.as_secs_f64() * samples_per_sec) as usize,
+            remaining: None,
         }
     }
+}
+
+impl<I: Source<Item = f32>> Iterator for FadeOut<I> {
+    type Item = f32;
 
-    fn toggle_pause(&mut self) {
-        match self.pause_time {
-            Some(time) => {
-                self.pause_duration += time.elapsed().as_secs_f64();
-                self.pause_time = None;
+    fn next(&mut self) -> Option<f32> {
+        if self.remaining.is_none() && self.trigger.load(Ordering::Relaxed) {
+            self.remaining = Some(self.fade_samples);
+        }
+        let sample = self.input.next()?;
+        match self.remaining {
+            Some(0) => None,
+            Some(remaining) => {
+                self.remaining = Some(remaining - 1);
+                Some(sample * remaining as f32 / self.fade_samples as f32)
             }
-            None => {
-                self.pause_time = Some(Instant::now());
+            None => Some(sample),
+        }
+    }
+}
+
+impl<I: Source<Item = f32>> Source for FadeOut<I> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+// how decoded audio is laid out before it reaches the device
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ChannelMapping {
+    // the channels as decoded
+    #[default]
+    Stereo,
+    Mono,
+    // front left and right from the stereo source, the other four silent
+    Upmix51,
+}
+
+impl ChannelMapping {
+    pub fn next(self) -> Self {
+        match self {
+            ChannelMapping::Stereo => ChannelMapping::Mono,
+            ChannelMapping::Mono => ChannelMapping::Upmix51,
+            ChannelMapping::Upmix51 => ChannelMapping::Stereo,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChannelMapping::Stereo => "Stereo",
+            ChannelMapping::Mono => "Mono",
+            ChannelMapping::Upmix51 => "5.1 upmix",
+        }
+    }
+
+    fn channels(&self, input_channels: u16) -> u16 {
+        match self {
+            ChannelMapping::Stereo => input_channels,
+            ChannelMapping::Mono => 1,
+            ChannelMapping::Upmix51 => 6,
+        }
+    }
+}
+
+// remixes the source a frame at a time: mono averages every channel, the 5.1
+// upmix copies left and right to the front pair (a mono source to both)
+struct ChannelMapper<I> {
+    input: I,
+    mapping: ChannelMapping,
+    frame: Vec<f32>,
+    // the rest of the current output frame, in reverse
+    pending: Vec<f32>,
+}
+
+impl<I: Source<Item = f32>> ChannelMapper<I> {
+    fn new(input: I, mapping: ChannelMapping) -> Self {
+        Self {
+            input,
+            mapping,
+            frame: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    // None once the source has run out
+    fn read_frame(&mut self) -> Option<()> {
+        let channels = self.input.channels().max(1) as usize;
+        self.frame.clear();
+        self.frame.extend(self.input.by_ref().take(channels));
+        (!self.frame.is_empty()).then_some(())
+    }
+}
+
+impl<I: Source<Item = f32>> Iterator for ChannelMapper<I> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        match self.mapping {
+            ChannelMapping::Stereo => self.input.next(),
+            ChannelMapping::Mono => {
+                self.read_frame()?;
+                Some(self.frame.iter().sum::<f32>() / self.frame.len() as f32)
             }
+            ChannelMapping::Upmix51 => {
+                if let Some(sample) = self.pending.pop() {
+                    return Some(sample);
+                }
+                self.read_frame()?;
+                let left = self.frame[0];
+                let right = self.frame.get(1).copied().unwrap_or(left);
+                // center, lfe and the surround pair stay silent
+                self.pending.extend([0.0, 0.0, 0.0, 0.0, right]);
+                Some(left)
+            }
+        }
+    }
+}
+
+impl<I: Source<Item = f32>> Source for ChannelMapper<I> {
+    fn current_frame_len(&self) -> Option<usize> {
+        let input_channels = self.input.channels().max(1) as usize;
+        let channels = self.mapping.channels(self.input.channels()) as usize;
+        self.input
+            .current_frame_len()
+            .map(|len| len / input_channels * channels + self.pending.len())
+    }
+
+    fn channels(&self) -> u16 {
+        self.mapping.channels(self.input.channels())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SilenceSettings {
+    // below this rms level, in dBFS, a stretch counts as silent
+    pub threshold_db: f32,
+    // how long it has to stay silent before the track is ended
+    pub duration_secs: f32,
+}
+
+// rms is taken over this much of the most recent audio
+const SILENCE_WINDOW_SECS: f32 = 0.5;
+
+// how long crossfade_to takes to switch tracks
+const CROSSFADE_DURATION: Duration = Duration::from_millis(500);
+
+// tracks kept for going back with skip_to_previous
+const HISTORY_SIZE: usize = 100;
+// going back from further into a track than this restarts it instead
+const PREVIOUS_RESTARTS_AFTER_SECS: usize = 3;
+
+// watches the level of a source and raises its flag once it has been silent
+// for long enough. leading silence is ignored, only a quiet tail ends a track
+struct SilenceSkipper<I> {
+    input: I,
+    settings: Option<SilenceSettings>,
+    reached: Arc<AtomicBool>,
+    window: VecDeque<f32>,
+    window_len: usize,
+    sum_of_squares: f64,
+    threshold: f64,
+    heard_audio: bool,
+    silent_samples: usize,
+    silent_samples_needed: usize,
+}
+
+impl<I: Source<Item = f32>> SilenceSkipper<I> {
+    fn new(input: I, settings: Option<SilenceSettings>, reached: Arc<AtomicBool>) -> Self {
+        let samples_per_sec = input.sample_rate() as f32 * input.channels() as f32;
+        let (threshold_db, duration_secs) = settings
+            .map(|settings| (settings.threshold_db, settings.duration_secs))
+            .unwrap_or_default();
+        let window_len = ((samples_per_sec * SILENCE_WINDOW_SECS) as usize).max(1);
+        Self {
+            input,
+            settings,
+            reached,
+            window: VecDeque::with_capacity(window_len),
+            window_len,
+            sum_of_squares: 0.0,
+            threshold: 10f64.powf(threshold_db as f64 / 20.0),
+            heard_audio: false,
+            silent_samples: 0,
+            silent_samples_needed: (samples_per_sec * duration_secs.max(0.0)) as usize,
         }
     }
 
-    fn time(&self) -> f64 {
-        match self.pause_time {
-            None => self.start_time.elapsed().as_secs_f64() - self.pause_duration,
-            Some(time) => {
-                self.start_time.elapsed().as_secs_f64()
-                    - time.elapsed().as_secs_f64()
-                    - self.pause_duration
+    fn track(&mut self, sample: f32) {
+        let square = sample as f64 * sample as f64;
+        self.window.push_back(square as f32);
+        self.sum_of_squares += square;
+        if self.window.len() > self.window_len {
+            let dropped = self.window.pop_front().unwrap_or(0.0) as f64;
+            // floating point drift could take the sum just below zero
+            self.sum_of_squares = (self.sum_of_squares - dropped).max(0.0);
+        }
+        if self.window.len() < self.window_len {
+            return;
+        }
+        let rms = (self.sum_of_squares / self.window_len as f64).sqrt();
+        if rms >= self.threshold {
+            self.heard_audio = true;
+            self.silent_samples = 0;
+        } else if self.heard_audio {
+            self.silent_samples += 1;
+            if self.silent_samples >= self.silent_samples_needed {
+                self.reached.store(true, Ordering::Relaxed);
             }
         }
     }
+}
+
+impl<I: Source<Item = f32>> Iterator for SilenceSkipper<I> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        if self.settings.is_some() {
+            self.track(sample);
+        }
+        Some(sample)
+    }
+}
+
+impl<I: Source<Item = f32>> Source for SilenceSkipper<I> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RepeatMode {
+    None,
+    All,
+    One,
+}
+
+impl RepeatMode {
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::None => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::None,
+        }
+    }
 
-    fn reset(&mut self) {
-        self.start_time = Instant::now();
-        self.pause_time = None;
-        self.pause_duration = 0.0;
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepeatMode::None => "Off",
+            RepeatMode::All => "All",
+            RepeatMode::One => "One",
+        }
     }
 }
 
+// the bytes of a stream fetched by AudioInterface::play_url
+type StreamDownload = Receiver<Result<Vec<u8>, Error>>;
+
+// what plays once the queue runs out with repeat off
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum AutoAdvance {
+    #[default]
+    Stop,
+    // the library track with the most in common with the one that finished
+    Similar,
+    Random,
+}
+
+/// Plays tracks and keeps the queue, shuffle, repeat and volume.
+///
+/// The track at the front of the queue starts when nothing is playing.
+/// [`handle_queue`](Self::handle_queue) has to be called regularly to move
+/// on when a track ends. The UI calls it on every tick.
+///
+/// # Examples
+///
+/// ```no_run
+/// use music_player::audio::{AudioFile, AudioInterface, Devices};
+///
+/// let devices = Devices::new(0);
+/// let (stream, sink) =
+///     Devices::open_stream(devices.get_device_by_index_or_default(0), None)?;
+/// let mut audio_interface = AudioInterface::new(stream, sink, devices, None);
+/// let track = AudioFile::new(&String::from("/music/song.flac"))?;
+/// audio_interface.append_to_queue(&mut vec![track]);
+/// assert!(audio_interface.get_currently_playing().is_some());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 pub struct AudioInterface {
     pub devices: Devices,
     queue: VecDeque<AudioFile>,
     // prevent the stream from being dropped
-    stream: rodio::OutputStream,
+    stream: OutputStream,
+    // in frames, None for the device's default
+    buffer_size: Option<u32>,
     currently_playing: Option<AudioFile>,
     pause: bool,
-    track: Track,
+    shuffle: bool,
+    // shuffles too, but skips tracks in recently_played_paths while it can
+    smart_shuffle: bool,
+    // capped at a quarter of the library
+    recently_played_paths: VecDeque<PathBuf>,
+    // tracks started so far, the playing one at the back
+    history: VecDeque<AudioFile>,
+    repeat: RepeatMode,
+    position: Option<Position>,
     sink: rodio::Sink,
+    samples: SampleBuffer,
+    // recording the loopback device into samples, None when there is no
+    // loopback device and samples are tapped from the playing source instead
+    loopback: Option<cpal::Stream>,
+    // None when silence skipping is off
+    skip_silence: Option<SilenceSettings>,
+    // set by the playing source once its tail has gone silent
+    silence_reached: Arc<AtomicBool>,
+    fade_in: Duration,
+    fade_out: Duration,
+    // tells the playing source to fade out and end
+    fading: Arc<AtomicBool>,
+    // like fading, but over CROSSFADE_DURATION whatever fade out is set
+    crossfading: Arc<AtomicBool>,
+    // the sink of the track being crossfaded away from, until it has faded out
+    crossfade_sink: Option<rodio::Sink>,
+    // the next source appended fades in over CROSSFADE_DURATION
+    crossfade_in: bool,
+    // spoken track announcements, played over the music on their own sink
+    announcement_sink: Option<rodio::Sink>,
+    channel_mapping: ChannelMapping,
+    resampler_quality: ResamplerQuality,
+    // rate of the playing source and of the device, when they differ
+    rate_conversion: Option<(u32, u32)>,
+    auto_advance: AutoAdvance,
+    // the library, where auto advance picks from
+    library: Vec<AudioFile>,
+    // a stream from play_url still downloading, and where to start it
+    download: Option<(StreamDownload, Duration)>,
 }
 
 impl AudioInterface {
-    pub fn new(stream: rodio::OutputStream, sink: rodio::Sink, devices: Devices) -> Self {
+    /// Plays on the stream and sink from [`Devices::open_stream`].
+    /// `buffer_size` is the size the stream was opened with, and is reused
+    /// when the device is switched.
+    ///
+    /// When the host has a loopback device, the visualizer records from it.
+    /// Recording is paused until
+    /// [`set_loopback_active`](Self::set_loopback_active) is called.
+    pub fn new(
+        stream: OutputStream,
+        sink: rodio::Sink,
+        devices: Devices,
+        buffer_size: Option<u32>,
+    ) -> Self {
+        let samples: SampleBuffer = Arc::new(Mutex::new(VecDeque::new()));
+        let loopback = Devices::get_loopback_device().and_then(|device| {
+            Devices::capture_loopback(&device, samples.clone())
+                .map_err(|e| log!(Level::Warn, "loopback capture unavailable: {}", e))
+                .ok()
+        });
         Self {
+            buffer_size,
             devices,
             stream,
             sink,
             pause: false,
-            track: Track::new(),
+            shuffle: false,
+            smart_shuffle: false,
+            recently_played_paths: VecDeque::new(),
+            history: VecDeque::new(),
+            repeat: RepeatMode::None,
+            position: None,
+            samples,
+            loopback,
+            skip_silence: None,
+            silence_reached: Arc::new(AtomicBool::new(false)),
+            fade_in: Duration::ZERO,
+            fade_out: Duration::ZERO,
+            fading: Arc::new(AtomicBool::new(false)),
+            crossfading: Arc::new(AtomicBool::new(false)),
+            crossfade_sink: None,
+            crossfade_in: false,
+            announcement_sink: None,
             currently_playing: None,
             queue: VecDeque::new(),
+            channel_mapping: ChannelMapping::Stereo,
+            resampler_quality: ResamplerQuality::Linear,
+            rate_conversion: None,
+            auto_advance: AutoAdvance::Stop,
+            library: Vec::new(),
+            download: None,
+        }
+    }
+
+    /// Moves playback to the output device at `index`. The current track
+    /// picks up where it left off. An out of range index picks the last
+    /// device.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the device can't be opened. Playback then stays on the old
+    /// device.
+    pub fn switch_device(&mut self, index: usize) -> Result<(), rodio::StreamError> {
+        let index = index.min(self.devices.get_device_names().len().saturating_sub(1));
+        let (stream, sink) =
+            Devices::open_stream(self.devices.get_device_by_index(index), self.buffer_size)?;
+        sink.set_volume(self.sink.volume());
+        if self.pause {
+            sink.pause();
+        }
+        let position = self
+            .position
+            .as_ref()
+            .map(|position| position.position_secs())
+            .unwrap_or(0.0);
+        self.sink.stop();
+        self.crossfade_sink = None;
+        self.sink = sink;
+        self.stream = stream;
+        self.devices.set_current_device(index);
+        if self.seek(position).is_err() {
+            // handle_queue moves on to the next track
+            self.currently_playing = None;
+        }
+        Ok(())
+    }
+
+    /// The stream's buffer size in frames, `None` for the device's default.
+    pub fn get_buffer_size(&self) -> Option<u32> {
+        self.buffer_size
+    }
+
+    /// Reopens the current device with a new buffer size.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`switch_device`](Self::switch_device).
+    pub fn set_buffer_size(&mut self, buffer_size: Option<u32>) -> Result<(), rodio::StreamError> {
+        let previous = std::mem::replace(&mut self.buffer_size, buffer_size);
+        let result = self.switch_device(self.devices.get_current_device());
+        if result.is_err() {
+            self.buffer_size = previous;
         }
+        result
     }
 
+    /// Whether playback is paused.
     pub fn get_paused(&self) -> bool {
         self.pause
     }
 
+    /// The track playing now, paused or not.
     pub fn get_currently_playing(&self) -> &Option<AudioFile> {
         &self.currently_playing
     }
 
+    /// Pauses or resumes playback. Pausing also stops a track that is still
+    /// fading out from a crossfade.
     pub fn toggle_pause(&mut self) {
-        self.track.toggle_pause();
         self.pause = !self.pause;
         if self.pause {
             self.sink.pause();
+            // a track still fading out from a crossfade would keep playing
+            self.crossfade_sink = None;
         } else {
             self.sink.play();
         }
     }
 
+    /// Starts the next track in the queue right away, so there is no gap.
+    /// Unlike [`hard_clear_queue`](Self::hard_clear_queue), the rest of the
+    /// queue stays.
+    pub fn skip_to_next(&mut self) {
+        if let Some(skipped) = self.currently_playing.take() {
+            if self.repeat == RepeatMode::All {
+                self.queue.push_back(skipped);
+            }
+        }
+        // a fading track is left to finish, the next one is appended behind it
+        self.fade_out_or_stop();
+        self.play_next();
+    }
+
+    /// The volume, from `0.0` to `1.0`.
+    pub fn get_volume(&self) -> f32 {
+        self.sink.volume()
+    }
+
+    /// Sets the volume, clamped to `0.0..=1.0`.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.sink.set_volume(volume.clamp(0.0, 1.0));
+    }
+
+    /// Sets how long tracks fade in when they start and fade out when they
+    /// are stopped. Takes effect from the next track that starts.
+    pub fn set_fades(&mut self, fade_in: Duration, fade_out: Duration) {
+        self.fade_in = fade_in;
+        self.fade_out = fade_out;
+    }
+
+    // fades the playing source out when a fade is set, so the sink empties
+    // once it's done. while paused there's nothing to hear, so stop right away
+    fn fade_out_or_stop(&mut self) {
+        if self.fade_out.is_zero() || self.pause || self.sink.empty() {
+            self.sink.stop();
+        } else {
+            self.fading.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Ends tracks early once their tail goes silent, or never with `None`.
+    /// Takes effect from the next track that starts.
+    pub fn set_skip_silence(&mut self, skip_silence: Option<SilenceSettings>) {
+        self.skip_silence = skip_silence;
+    }
+
+    /// How the channels of a track are mapped onto the output.
+    pub fn get_channel_mapping(&self) -> ChannelMapping {
+        self.channel_mapping
+    }
+
+    /// Sets the channel mapping. Takes effect from the next track that starts.
+    pub fn set_channel_mapping(&mut self, channel_mapping: ChannelMapping) {
+        self.channel_mapping = channel_mapping;
+    }
+
+    /// How tracks are resampled when their rate differs from the device's.
+    pub fn get_resampler_quality(&self) -> ResamplerQuality {
+        self.resampler_quality
+    }
+
+    /// Sets the resampler quality. Takes effect from the next track that
+    /// starts.
+    pub fn set_resampler_quality(&mut self, resampler_quality: ResamplerQuality) {
+        self.resampler_quality = resampler_quality;
+    }
+
+    /// The sample rates of the playing track and of the device, when they
+    /// differ.
+    pub fn get_rate_conversion(&self) -> Option<(u32, u32)> {
+        self.rate_conversion
+    }
+
+    // the rate the stream was opened with, the device's default
+    fn output_sample_rate(&self) -> Option<u32> {
+        // a headless stream has no devices to look at
+        self.devices
+            .devices
+            .get(self.devices.get_current_device())?
+            .default_output_config()
+            .ok()
+            .map(|config| config.sample_rate().0)
+    }
+
+    /// What plays when the queue runs out, picked from `library`.
+    pub fn set_auto_advance(&mut self, auto_advance: AutoAdvance, library: Vec<AudioFile>) {
+        self.auto_advance = auto_advance;
+        self.library = library;
+    }
+
+    /// Replaces the tracks auto advance picks from, once the library has
+    /// changed.
+    pub fn set_library(&mut self, library: Vec<AudioFile>) {
+        self.library = library;
+    }
+
+    /// Starts or stops recording from the loopback device. Nothing happens
+    /// when there is no loopback device.
+    pub fn set_loopback_active(&mut self, active: bool) {
+        let Some(loopback) = &self.loopback else {
+            return;
+        };
+        let result = match active {
+            true => loopback.play().map_err(|e| e.to_string()),
+            false => loopback.pause().map_err(|e| e.to_string()),
+        };
+        if let Err(e) = result {
+            log!(
+                Level::Warn,
+                "could not start or stop loopback capture: {}",
+                e
+            );
+        }
+    }
+
+    /// The most recently played samples, interleaved, for the visualizer.
+    pub fn get_samples(&self) -> Vec<f32> {
+        self.samples.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Whether the next track is picked at random, by plain or smart shuffle.
+    pub fn get_shuffle(&self) -> bool {
+        self.shuffle || self.smart_shuffle
+    }
+
+    /// Turns plain shuffle on or off.
+    pub fn toggle_shuffle(&mut self) {
+        self.shuffle = !self.shuffle;
+    }
+
+    /// Whether smart shuffle is on. Smart shuffle skips recently played
+    /// tracks while it can.
+    pub fn get_smart_shuffle(&self) -> bool {
+        self.smart_shuffle
+    }
+
+    /// Turns smart shuffle on or off.
+    pub fn toggle_smart_shuffle(&mut self) {
+        self.smart_shuffle = !self.smart_shuffle;
+    }
+
+    // smart shuffle leaves out recently played tracks, unless that's all the queue has
+    fn shuffled_index(&self) -> usize {
+        let fresh = (0..self.queue.len())
+            .filter(|&i| {
+                !self.smart_shuffle
+                    || !self
+                        .recently_played_paths
+                        .iter()
+                        .any(|path| path == self.queue[i].get_path())
+            })
+            .collect::<Vec<_>>();
+        match fresh.choose(&mut rand::thread_rng()) {
+            Some(&i) => i,
+            None => rand::thread_rng().gen_range(0..self.queue.len()),
+        }
+    }
+
+    fn remember_played(&mut self, file: &AudioFile) {
+        self.recently_played_paths
+            .push_back(file.get_path().to_path_buf());
+        while self.recently_played_paths.len() > self.library.len() / 4 {
+            self.recently_played_paths.pop_front();
+        }
+    }
+
+    /// The repeat mode.
+    pub fn get_repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    /// Moves on to the next repeat mode: off, all, then one.
+    pub fn cycle_repeat(&mut self) {
+        self.repeat = self.repeat.next();
+    }
+
+    /// Moves the tracks in `new_queue` to the end of the queue, leaving it
+    /// empty. The first of them starts when nothing is playing.
     pub fn append_to_queue(&mut self, new_queue: &mut Vec<AudioFile>) {
         // Vec to VecDeque
         let mut new_queue = new_queue.drain(..).collect::<VecDeque<_>>();
@@ -226,21 +1791,100 @@ impl AudioInterface {
         }
     }
 
+    /// Swaps two tracks in the queue. Out of range indices are ignored.
+    pub fn swap_queue_items(&mut self, i: usize, j: usize) {
+        if i < self.queue.len() && j < self.queue.len() {
+            self.queue.swap(i, j);
+        }
+    }
+
+    /// Drops everything after the current track, which keeps playing.
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Empties the queue and stops the current track, fading it out when a
+    /// fade out is set.
     pub fn hard_clear_queue(&mut self) {
         self.queue.clear();
-        self.sink.stop();
+        self.download = None;
+        self.fade_out_or_stop();
         self.currently_playing = None;
     }
 
+    /// Moves on once the current track has ended. The finished track is
+    /// requeued for repeat, or auto advance picks the next one when the queue
+    /// is empty. Call it regularly, like on every UI tick.
     pub fn handle_queue(&mut self) {
+        if self.poll_download() {
+            return;
+        }
+        if self.crossfade_sink.as_ref().is_some_and(rodio::Sink::empty) {
+            self.crossfade_sink = None;
+        }
+        // a silent tail ends the track just like the sink running out
+        if self.silence_reached.swap(false, Ordering::Relaxed) {
+            self.sink.stop();
+        }
         if self.sink.empty() && self.currently_playing.is_none() {
             self.currently_playing = self.get_next().cloned();
             self.play_next();
         } else if self.sink.empty() && self.currently_playing.is_some() {
-            self.currently_playing = None;
+            if let Some(finished) = self.currently_playing.take() {
+                match self.repeat {
+                    RepeatMode::None if self.queue.is_empty() => {
+                        // picked up and played on the next call
+                        let next =
+                            library::suggest_next(&self.library, &finished, self.auto_advance);
+                        self.queue.extend(next.cloned());
+                    }
+                    RepeatMode::None => {}
+                    RepeatMode::All => self.queue.push_back(finished),
+                    RepeatMode::One => self.queue.push_front(finished),
+                }
+            }
+        }
+    }
+
+    /// The tracks after the current one, next first.
+    pub fn get_queue(&self) -> &VecDeque<AudioFile> {
+        &self.queue
+    }
+
+    /// Changes the URL the next track in the queue streams from, like one
+    /// fetched ahead of time from Tidal.
+    pub fn set_next_stream_url(&mut self, stream_url: &str) {
+        if let Some(next) = self.queue.front_mut() {
+            if next.get_stream_url() != Some(stream_url) {
+                next.set_stream_url(stream_url.to_string());
+            }
         }
     }
 
+    /// Plays `wav` over the music on a sink of its own, at `volume`. An
+    /// announcement that is still speaking is cut off.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `wav` can't be decoded or the stream can't take another sink.
+    pub fn play_announcement(&mut self, wav: Vec<u8>, volume: f32) -> Result<(), Error> {
+        let source = rodio::Decoder::new(Cursor::new(wav)).map_err(Error::other)?;
+        let sink = self
+            .stream
+            .new_sink()
+            .ok_or_else(|| Error::other("no sink for the announcement"))?;
+        sink.set_volume(volume);
+        sink.append(source);
+        self.announcement_sink = Some(sink);
+        Ok(())
+    }
+
+    /// The next `n` tracks in the queue, or fewer when the queue is shorter.
+    pub fn get_upcoming(&self, n: usize) -> Vec<&AudioFile> {
+        self.queue.iter().take(n).collect()
+    }
+
+    /// The next track in the queue.
     pub fn get_next(&self) -> Option<&AudioFile> {
         if let Some(next) = self.queue.front() {
             Some(next)
@@ -249,41 +1893,367 @@ impl AudioInterface {
         }
     }
 
+    /// How far into the current track playback is, in whole seconds. `0`
+    /// when nothing is playing.
     pub fn get_sink_length(&self) -> usize {
         if self.sink.empty() && self.currently_playing.is_none() {
             0
         } else {
-            self.track.time() as usize
+            self.position
+                .as_ref()
+                .map(|position| position.position_secs() as usize)
+                .unwrap_or(0)
         }
     }
 
+    /// How far through the current track playback is, from `0.0` to `1.0`,
+    /// for progress bars. `0.0` when nothing is playing or the length isn't
+    /// known.
+    pub fn get_position_ratio(&self) -> f64 {
+        let Some(playing) = &self.currently_playing else {
+            return 0.0;
+        };
+        let duration = playing.get_playing_duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        (self.get_sink_length() as f64 / duration).clamp(0.0, 1.0)
+    }
+
     fn play_next(&mut self) {
-        if let Some(next) = self.queue.pop_front() {
-            self.currently_playing = Some(next);
-            self.track.reset();
-            if self.pause {
-                self.pause = false;
-                self.sink.play();
+        // repeating a single track always replays the front of the queue
+        let next = if self.get_shuffle() && self.repeat != RepeatMode::One && !self.queue.is_empty()
+        {
+            let i = self.shuffled_index();
+            self.queue.remove(i)
+        } else {
+            self.queue.pop_front()
+        };
+        if let Some(next) = next {
+            self.start(next);
+        }
+    }
+
+    fn start(&mut self, next: AudioFile) {
+        self.remember_played(&next);
+        self.history.push_back(next.clone());
+        if self.history.len() > HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.currently_playing = Some(next);
+        if self.pause {
+            self.pause = false;
+            self.sink.play();
+        }
+        let next = self.currently_playing.clone().unwrap();
+        match next.get_stream_url() {
+            Some(url) => self.play_url(url, Duration::ZERO),
+            None => {
+                if let Err(e) = self.play(&next, Duration::ZERO) {
+                    log!(
+                        Level::Error,
+                        "can't play {}: {}",
+                        next.get_path().display(),
+                        e
+                    );
+                    // handle_queue moves on to the next track
+                    self.currently_playing = None;
+                }
             }
-            self.play(self.currently_playing.as_ref().unwrap().get_path())
-                .unwrap();
         }
     }
 
-    fn play(&self, file: &Path) -> Result<(), std::io::Error> {
-        self.sink.stop();
-        let file = BufReader::new(std::fs::File::open(file)?);
+    /// Switches to `audio_file` straight away. The playing track fades out
+    /// while the new one fades in.
+    pub fn crossfade_to(&mut self, audio_file: &AudioFile) {
+        let playing = self.currently_playing.is_some() && !self.pause && !self.sink.empty();
+        if let Some(sink) = self.stream.new_sink().filter(|_| playing) {
+            sink.set_volume(self.sink.volume());
+            self.crossfading.store(true, Ordering::Relaxed);
+            self.crossfade_sink = Some(std::mem::replace(&mut self.sink, sink));
+            self.crossfade_in = true;
+        }
+        self.start(audio_file.clone());
+    }
+
+    /// Restarts the current track, or goes back to the one before it when the
+    /// current track has only just started.
+    pub fn skip_to_previous(&mut self) {
+        if self.get_sink_length() > PREVIOUS_RESTARTS_AFTER_SECS {
+            let _ = self.seek(0.0);
+            return;
+        }
+        // the back of the history is the playing track, unless playback stopped
+        if let Some(current) = self.currently_playing.take() {
+            if self.history.back() == Some(&current) {
+                self.history.pop_back();
+            }
+            self.queue.push_front(current);
+        }
+        match self.history.pop_back() {
+            Some(previous) => {
+                self.fade_out_or_stop();
+                self.start(previous);
+            }
+            // nothing to go back to, start the first track over
+            None => {
+                self.currently_playing = self.queue.pop_front();
+                let _ = self.seek(0.0);
+            }
+        }
+    }
+
+    /// Restarts the current track `seconds` in, clamped to its length. Does
+    /// nothing when nothing is playing.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be opened or decoded. Streams are fetched
+    /// in the background, see [`play_url`](Self::play_url).
+    pub fn seek(&mut self, seconds: f64) -> Result<(), std::io::Error> {
+        let Some(current) = self.currently_playing.clone() else {
+            return Ok(());
+        };
+        let seconds = seconds.clamp(0.0, current.get_playing_duration());
+        let start = Duration::from_secs_f64(seconds);
+        match current.get_stream_url() {
+            Some(url) => self.play_url(url, start),
+            None => self.play(&current, start)?,
+        }
+        Ok(())
+    }
+
+    /// Plays `audio_file` right away from `seconds` in, leaving the queue
+    /// alone. Playback resumes if it was paused.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`seek`](Self::seek).
+    pub fn seek_to(&mut self, audio_file: &AudioFile, seconds: f64) -> Result<(), std::io::Error> {
+        self.currently_playing = Some(audio_file.clone());
+        if self.pause {
+            self.pause = false;
+            self.sink.play();
+        }
+        self.seek(seconds)
+    }
+
+    // every source goes through here so the visualizer, the position and
+    // silence skipping can follow it
+    fn append<S>(&mut self, source: S, start: Duration)
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let input_rate = source.sample_rate();
+        let output_rate = self.output_sample_rate().unwrap_or(input_rate);
+        self.rate_conversion = (input_rate != output_rate).then_some((input_rate, output_rate));
+        let source = resample::resample(source, self.resampler_quality, output_rate);
+        let source = ChannelMapper::new(source, self.channel_mapping);
+        let samples = Arc::new(AtomicU64::new(0));
+        self.position = Some(Position {
+            samples: samples.clone(),
+            channels: source.channels(),
+            sample_rate: source.sample_rate(),
+            start: start.as_secs_f64(),
+        });
+        // fresh flags, so a source that was replaced can't end the new one
+        self.silence_reached = Arc::new(AtomicBool::new(false));
+        self.fading = Arc::new(AtomicBool::new(false));
+        self.crossfading = Arc::new(AtomicBool::new(false));
+        let fade_in = match std::mem::take(&mut self.crossfade_in) {
+            true => CROSSFADE_DURATION,
+            false => self.fade_in,
+        };
+        // tracks played from the top fade in, seeking doesn't
+        let source: Box<dyn Source<Item = f32> + Send> = if start.is_zero() && !fade_in.is_zero() {
+            Box::new(source.fade_in(fade_in))
+        } else {
+            Box::new(source)
+        };
+        let source = FadeOut::new(source, self.fade_out, self.fading.clone());
+        let source = FadeOut::new(source, CROSSFADE_DURATION, self.crossfading.clone());
+        let source = SilenceSkipper::new(source, self.skip_silence, self.silence_reached.clone());
+        let source = PositionedSource::new(source, samples);
+        match self.loopback {
+            Some(_) => self.sink.append(source),
+            None => self
+                .sink
+                .append(SampleTap::new(source, self.samples.clone())),
+        }
+    }
+
+    // the sink plays its sources in order, so a source that is fading out
+    // is left to finish before the new one starts
+    fn stop_unless_fading(&mut self) {
+        if !self.fading.load(Ordering::Relaxed) {
+            self.sink.stop();
+        }
+    }
+
+    fn play(&mut self, audio_file: &AudioFile, start: Duration) -> Result<(), std::io::Error> {
+        self.stop_unless_fading();
+        self.download = None;
+        let file = BufReader::new(std::fs::File::open(audio_file.get_path())?);
         match rodio::Decoder::new(file) {
             Ok(source) => {
-                self.sink.append(source);
+                let source = source.convert_samples::<f32>();
+                match audio_file.get_cue_offset_frames() {
+                    Some(offset) => {
+                        let offset = Duration::from_secs_f64(offset as f64 / FRAMES_PER_SECOND);
+                        self.append(
+                            source.skip_duration(offset + start).take_duration(
+                                Duration::from_secs_f64(audio_file.get_raw_duration())
+                                    .saturating_sub(start),
+                            ),
+                            start,
+                        );
+                    }
+                    // positions stay relative to where the music starts
+                    None => {
+                        let offset = Duration::from_secs_f64(audio_file.get_start_offset_secs());
+                        self.append(source.skip_duration(offset + start), start)
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => Err(Error::new(ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Downloads `url` on a thread of its own and plays it from `start`, in
+    /// place of the current source. [`handle_queue`](Self::handle_queue)
+    /// starts it once the download is done. When the request fails or the
+    /// response isn't audio rodio can decode, the error is logged and the
+    /// next track plays.
+    pub fn play_url(&mut self, url: &str, start: Duration) {
+        self.stop_unless_fading();
+        let (sender, receiver) = mpsc::channel();
+        let url = url.to_string();
+        std::thread::spawn(move || {
+            let bytes = reqwest::blocking::get(url)
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.bytes())
+                .map(|bytes| bytes.to_vec())
+                .map_err(Error::other);
+            let _ = sender.send(bytes);
+        });
+        // replacing an earlier download drops it, its thread's send just fails
+        self.download = Some((receiver, start));
+    }
+
+    fn play_bytes(&mut self, bytes: Vec<u8>, start: Duration) -> Result<(), std::io::Error> {
+        match rodio::Decoder::new(Cursor::new(bytes)) {
+            Ok(source) => {
+                self.append(source.convert_samples::<f32>().skip_duration(start), start);
                 Ok(())
             }
-            Err(e) => {
-                Err(Error::new(
-                    ErrorKind::InvalidData,
-                    e,
-                )) 
+            Err(e) => Err(Error::new(ErrorKind::InvalidData, e)),
+        }
+    }
+
+    // plays the stream from play_url once it has downloaded. true while it
+    // is still downloading, so the empty sink isn't taken for the track ending
+    fn poll_download(&mut self) -> bool {
+        let Some((receiver, start)) = &self.download else {
+            return false;
+        };
+        let start = *start;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => Err(Error::other("the download stopped")),
+        };
+        self.download = None;
+        if let Err(e) = result.and_then(|bytes| self.play_bytes(bytes, start)) {
+            log!(Level::Error, "can't play stream: {}", e);
+            self.currently_playing = None;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn decodes(path: &str) -> bool {
+        let file = BufReader::new(std::fs::File::open(path).unwrap());
+        rodio::Decoder::new(file).unwrap().count() > 0
+    }
+
+    #[test]
+    fn reads_aiff_without_tags() {
+        let file = AudioFile::new(&fixture("tone.aiff")).unwrap();
+        assert_eq!(file.get_title(), "Unknown");
+        assert!((file.get_raw_duration() - 0.25).abs() < 0.01);
+        assert!(decodes(&fixture("tone.aiff")));
+    }
+
+    #[test]
+    fn reads_wav_info_tags() {
+        let file = AudioFile::new(&fixture("tone.wav")).unwrap();
+        assert_eq!(file.get_title(), "Tone");
+        assert_eq!(file.get_artist(), "rmus");
+        assert_eq!(file.get_album_artist(), "rmus");
+        assert_eq!(file.get_album(), "Fixtures");
+        assert!((file.get_raw_duration() - 0.25).abs() < 0.01);
+        assert!(decodes(&fixture("tone.wav")));
+    }
+
+    // random runs of play and pause, checked against the frames played. the
+    // source is wrapped in rodio's Pausable like the sink does, and the output
+    // keeps pulling samples while paused, getting silence back
+    #[test]
+    fn position_follows_played_frames() {
+        use rand::{rngs::StdRng, SeedableRng};
+        const CHANNELS: u16 = 2;
+        const SAMPLE_RATE: u32 = 44100;
+        let mut rng = StdRng::seed_from_u64(1142);
+        for _ in 0..100 {
+            let start = rng.gen_range(0.0..600.0);
+            let samples = Arc::new(AtomicU64::new(0));
+            let position = Position {
+                samples: samples.clone(),
+                channels: CHANNELS,
+                sample_rate: SAMPLE_RATE,
+                start,
+            };
+            let buffer = rodio::buffer::SamplesBuffer::new(
+                CHANNELS,
+                SAMPLE_RATE,
+                vec![0.5f32; SAMPLE_RATE as usize * CHANNELS as usize * 10],
+            );
+            let mut output = PositionedSource::new(buffer, samples).pausable(false);
+            let mut played_frames = 0usize;
+            let mut last = position.position_secs();
+            for _ in 0..rng.gen_range(1..20) {
+                let paused = rng.gen_bool(0.3);
+                output.set_paused(paused);
+                // up to a quarter second at a time
+                let frames = rng.gen_range(0..SAMPLE_RATE as usize / 4);
+                let pulled = output
+                    .by_ref()
+                    .take(frames * CHANNELS as usize)
+                    .collect::<Vec<_>>();
+                // the output got every sample it asked for, paused or not
+                assert_eq!(pulled.len(), frames * CHANNELS as usize);
+                let now = position.position_secs();
+                assert!(now >= 0.0);
+                if paused {
+                    assert!(pulled.iter().all(|&sample| sample == 0.0));
+                    assert_eq!(now, last);
+                } else {
+                    played_frames += frames;
+                    assert!(now >= last);
+                }
+                last = now;
             }
+            let expected = start + played_frames as f64 / SAMPLE_RATE as f64;
+            assert!((position.position_secs() - expected).abs() < 0.005);
         }
     }
 }
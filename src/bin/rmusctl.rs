@@ -0,0 +1,56 @@
+use std::{io, process::ExitCode};
+#[cfg(unix)]
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+};
+
+// must match the path the player listens on in src/ipc.rs
+#[cfg(unix)]
+fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("rmus.sock"),
+        None => std::env::temp_dir().join("rmus.sock"),
+    }
+}
+
+#[cfg(unix)]
+fn send(command: &str) -> Result<String, io::Error> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    writeln!(stream, "{}", command)?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(response.trim_end().to_string())
+}
+
+// rmus only listens on a unix socket
+#[cfg(not(unix))]
+fn send(_command: &str) -> Result<String, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "rmusctl needs unix domain sockets",
+    ))
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        eprintln!("usage: rmusctl <play|pause|next|prev|volume <0.0-1.0>|status>");
+        return ExitCode::FAILURE;
+    }
+    match send(&args.join(" ")) {
+        Ok(response) if response.starts_with("ERR") => {
+            eprintln!("{}", response);
+            ExitCode::FAILURE
+        }
+        Ok(response) => {
+            println!("{}", response);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("could not reach rmus: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::acoustid::USER_AGENT;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{thread, time::Duration};
+use url::Url;
+
+const ARTIST_URL: &str = "https://musicbrainz.org/ws/2/artist";
+const WIKIDATA_URL: &str = "https://www.wikidata.org/wiki/Special:EntityData";
+const WIKIPEDIA_SUMMARY_URL: &str = "https://en.wikipedia.org/api/rest_v1/page/summary/";
+// musicbrainz allows one request a second
+const MUSICBRAINZ_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize)]
+struct ArtistSearch {
+    #[serde(default)]
+    artists: Vec<Artist>,
+}
+
+#[derive(Deserialize)]
+struct Artist {
+    id: String,
+    #[serde(default)]
+    disambiguation: String,
+}
+
+#[derive(Deserialize)]
+struct ArtistLookup {
+    #[serde(default)]
+    relations: Vec<Relation>,
+}
+
+#[derive(Deserialize)]
+struct Relation {
+    #[serde(rename = "type")]
+    kind: String,
+    url: Option<RelationUrl>,
+}
+
+#[derive(Deserialize)]
+struct RelationUrl {
+    resource: String,
+}
+
+#[derive(Deserialize)]
+struct Summary {
+    #[serde(default)]
+    extract: String,
+}
+
+fn get<T: DeserializeOwned>(url: &str, query: &[(&str, &str)]) -> Result<T, reqwest::Error> {
+    reqwest::blocking::Client::new()
+        .get(url)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .query(query)
+        .send()?
+        .error_for_status()?
+        .json()
+}
+
+// the musicbrainz disambiguation followed by the english wikipedia summary
+// the artist's wikidata entry links to. empty when musicbrainz doesn't
+// know the artist
+pub fn fetch_artist_bio(artist_name: &str) -> Result<String, reqwest::Error> {
+    let query = format!("artist:\"{}\"", artist_name.replace('"', "\\\""));
+    let search: ArtistSearch = get(
+        ARTIST_URL,
+        &[("query", query.as_str()), ("limit", "1"), ("fmt", "json")],
+    )?;
+    let Some(artist) = search.artists.into_iter().next() else {
+        return Ok(String::new());
+    };
+    thread::sleep(MUSICBRAINZ_INTERVAL);
+    let lookup: ArtistLookup = get(
+        &format!("{}/{}", ARTIST_URL, artist.id),
+        &[("inc", "url-rels"), ("fmt", "json")],
+    )?;
+    let wikidata_id = lookup
+        .relations
+        .into_iter()
+        .filter(|relation| relation.kind == "wikidata")
+        .find_map(|relation| {
+            let resource = relation.url?.resource;
+            resource.rsplit('/').next().map(String::from)
+        });
+    let extract = match wikidata_id {
+        Some(id) => wikipedia_extract(&id)?,
+        None => String::new(),
+    };
+    Ok([artist.disambiguation, extract]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+fn wikipedia_extract(wikidata_id: &str) -> Result<String, reqwest::Error> {
+    let entity: serde_json::Value = get(&format!("{}/{}.json", WIKIDATA_URL, wikidata_id), &[])?;
+    let Some(title) = entity["entities"][wikidata_id]["sitelinks"]["enwiki"]["title"].as_str()
+    else {
+        return Ok(String::new());
+    };
+    let Ok(mut url) = Url::parse(WIKIPEDIA_SUMMARY_URL) else {
+        return Ok(String::new());
+    };
+    // the title can contain slashes, it has to be a single path segment
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().push(title);
+    }
+    let summary: Summary = get(url.as_str(), &[])?;
+    Ok(summary.extract)
+}
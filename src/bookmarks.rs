@@ -0,0 +1,265 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    cache::LibraryCache,
+    library::seconds_to_formatted_time,
+    log,
+    log::Level,
+    ui::{centered_rect, render_popup, Window},
+};
+use chrono::{DateTime, Local, Utc};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    io::{self, Stdout},
+    path::PathBuf,
+    rc::Rc,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub track_path: PathBuf,
+    pub position_secs: f64,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// asks for a label for the position the current track was at when B was pressed
+pub struct BookmarkPrompt {
+    track: AudioFile,
+    position_secs: f64,
+    label: String,
+}
+
+impl BookmarkPrompt {
+    pub fn new(track: AudioFile, position_secs: f64) -> Self {
+        Self {
+            track,
+            position_secs,
+            label: String::new(),
+        }
+    }
+
+    // returns true once the prompt is done with, saved or not
+    pub fn handle_input(&mut self, key: KeyCode, cache: &mut LibraryCache) -> bool {
+        match key {
+            KeyCode::Esc => return true,
+            KeyCode::Char(c) => self.label.push(c),
+            KeyCode::Backspace => {
+                self.label.pop();
+            }
+            KeyCode::Enter => {
+                let label = match self.label.trim() {
+                    "" => seconds_to_formatted_time(self.position_secs as usize),
+                    label => label.to_string(),
+                };
+                cache.add_bookmark(Bookmark {
+                    track_path: self.track.get_path().to_path_buf(),
+                    position_secs: self.position_secs,
+                    label,
+                    created_at: Utc::now(),
+                });
+                if let Err(e) = cache.save() {
+                    log!(Level::Warn, "saving the library cache failed: {}", e);
+                }
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub fn draw(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let title = format!(
+            "Bookmark {} at {} - Enter to save, Esc to cancel",
+            self.track.get_title(),
+            seconds_to_formatted_time(self.position_secs as usize)
+        );
+        let prompt =
+            Paragraph::new(format!("{}_", self.label)).style(Style::default().fg(Color::Yellow));
+        render_popup(f, centered_rect(60, 20, area), &title, |inner, f| {
+            f.render_widget(prompt, inner)
+        });
+    }
+}
+
+pub struct BookmarksWindow {
+    title: String,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    cache: Rc<RefCell<LibraryCache>>,
+    // used to find the track a bookmark points at, with its tags from the cache
+    music_list: Vec<AudioFile>,
+    state: ListState,
+    status: Option<String>,
+}
+
+impl BookmarksWindow {
+    pub fn new(
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        cache: Rc<RefCell<LibraryCache>>,
+        music_list: &[AudioFile],
+    ) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            title: String::from("Bookmarks"),
+            audio_interface,
+            cache,
+            music_list: music_list.to_vec(),
+            state,
+            status: None,
+        }
+    }
+
+    // grouped by track, in the order they come in the track
+    fn sorted_bookmarks(&self) -> Vec<Bookmark> {
+        let mut bookmarks = self.cache.borrow().get_bookmarks().to_vec();
+        bookmarks.sort_by(|a, b| {
+            a.track_path
+                .cmp(&b.track_path)
+                .then(a.position_secs.total_cmp(&b.position_secs))
+        });
+        bookmarks
+    }
+
+    fn find_track(&self, bookmark: &Bookmark) -> Option<AudioFile> {
+        self.music_list
+            .iter()
+            .find(|file| file.get_path() == bookmark.track_path)
+            .cloned()
+            .or_else(|| AudioFile::new(&bookmark.track_path.to_string_lossy().to_string()).ok())
+    }
+
+    fn track_name(&self, bookmark: &Bookmark) -> String {
+        match self
+            .music_list
+            .iter()
+            .find(|file| file.get_path() == bookmark.track_path)
+        {
+            Some(file) => format!("{} - {}", file.get_title(), file.get_artist()),
+            None => bookmark.track_path.display().to_string(),
+        }
+    }
+
+    fn selected_bookmark(&self) -> Option<Bookmark> {
+        self.sorted_bookmarks()
+            .get(self.state.selected().unwrap_or(0))
+            .cloned()
+    }
+
+    fn play_selected(&mut self) {
+        let Some(bookmark) = self.selected_bookmark() else {
+            return;
+        };
+        let Some(track) = self.find_track(&bookmark) else {
+            self.status = Some(format!("can't read {}", bookmark.track_path.display()));
+            return;
+        };
+        self.status = Some(
+            match self
+                .audio_interface
+                .borrow_mut()
+                .seek_to(&track, bookmark.position_secs)
+            {
+                Ok(()) => format!("playing from {}", bookmark.label),
+                Err(e) => format!("failed to play: {}", e),
+            },
+        );
+    }
+
+    fn delete_selected(&mut self) {
+        let Some(bookmark) = self.selected_bookmark() else {
+            return;
+        };
+        let mut cache = self.cache.borrow_mut();
+        cache.remove_bookmark(&bookmark);
+        if let Err(e) = cache.save() {
+            log!(Level::Warn, "saving the library cache failed: {}", e);
+        }
+        self.status = Some(format!("deleted {}", bookmark.label));
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.cache.borrow().get_bookmarks().len();
+        if len == 0 {
+            return;
+        }
+        let i = self.state.selected().unwrap_or(0) as isize + offset;
+        self.state.select(Some(i.rem_euclid(len as isize) as usize));
+    }
+}
+
+impl Window for BookmarksWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let bookmarks = self.sorted_bookmarks();
+        if self.state.selected().unwrap_or(0) >= bookmarks.len() {
+            self.state.select(Some(bookmarks.len().saturating_sub(1)));
+        }
+        // the first bookmark of each track carries the track's name above it
+        let items = bookmarks
+            .iter()
+            .enumerate()
+            .map(|(i, bookmark)| {
+                let mut lines = Vec::new();
+                if i == 0 || bookmarks[i - 1].track_path != bookmark.track_path {
+                    lines.push(Line::from(Span::styled(
+                        self.track_name(bookmark),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    )));
+                }
+                lines.push(Line::from(format!(
+                    "  {} {} ({})",
+                    seconds_to_formatted_time(bookmark.position_secs as usize),
+                    bookmark.label,
+                    bookmark
+                        .created_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                )));
+                ListItem::new(Text::from(lines))
+            })
+            .collect::<Vec<_>>();
+        let title = match &self.status {
+            Some(status) => format!("{} - {}", self.get_title(), status),
+            None => format!("{} - Enter to play, d to delete", self.get_title()),
+        };
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut self.state);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        match key {
+            KeyCode::Up => self.select(-1),
+            KeyCode::Down => self.select(1),
+            KeyCode::Enter => self.play_selected(),
+            KeyCode::Char('d') => self.delete_selected(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn on_library_changed(&mut self, music_list: &[AudioFile]) {
+        self.music_list = music_list.to_vec();
+    }
+}
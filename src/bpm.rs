@@ -0,0 +1,120 @@
+use crate::{audio::AudioFile, cue::FRAMES_PER_SECOND};
+use id3::TagLike;
+use rodio::Source;
+use std::{
+    io::{self, BufReader},
+    path::Path,
+    time::Duration,
+};
+
+// how much of the track is analysed
+const SAMPLE_SECS: u64 = 30;
+// mono samples per energy measurement, around 12 ms at 44.1 kHz
+const HOP_SIZE: usize = 512;
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 180.0;
+
+// estimates the tempo from the first 30 seconds of the track. the energy of
+// short windows is turned into an onset envelope (how sharply the energy
+// rises), and the beat period is the lag at which that envelope best lines
+// up with itself
+pub fn detect(file: &AudioFile) -> Result<f32, io::Error> {
+    let reader = BufReader::new(std::fs::File::open(file.get_path())?);
+    let source = rodio::Decoder::new(reader)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .convert_samples::<f32>();
+    let offset = file
+        .get_cue_offset_frames()
+        .map(|frames| Duration::from_secs_f64(frames as f64 / FRAMES_PER_SECOND))
+        .unwrap_or_default();
+    let source = source
+        .skip_duration(offset)
+        .take_duration(Duration::from_secs(SAMPLE_SECS));
+    let channels = source.channels().max(1) as usize;
+    let sample_rate = source.sample_rate();
+    let samples = source.collect::<Vec<f32>>();
+    let mono = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect::<Vec<_>>();
+    estimate(&mono, sample_rate).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not enough audio to find a tempo",
+        )
+    })
+}
+
+fn estimate(mono: &[f32], sample_rate: u32) -> Option<f32> {
+    let energies = mono
+        .chunks_exact(HOP_SIZE)
+        .map(|hop| hop.iter().map(|sample| sample * sample).sum::<f32>())
+        .collect::<Vec<_>>();
+    let onsets = energies
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect::<Vec<_>>();
+    let hops_per_sec = sample_rate as f32 / HOP_SIZE as f32;
+    let min_lag = (60.0 * hops_per_sec / MAX_BPM).floor().max(1.0) as usize;
+    let max_lag = (60.0 * hops_per_sec / MIN_BPM).ceil() as usize;
+    if onsets.len() <= max_lag * 2 {
+        return None;
+    }
+    let (lag, score) = (min_lag..=max_lag)
+        .map(|lag| {
+            let score = onsets
+                .iter()
+                .zip(&onsets[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / (onsets.len() - lag) as f32;
+            (lag, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if score <= 0.0 {
+        return None;
+    }
+    let bpm = 60.0 * hops_per_sec / lag as f32;
+    Some((bpm * 10.0).round() / 10.0)
+}
+
+// the BPM tag lives in a TBPM frame for mp3 and a BPM comment for flac
+pub fn read_tag(path: &Path) -> Option<f32> {
+    match path.extension()?.to_str()? {
+        "mp3" => id3::Tag::read_from_path(path)
+            .ok()?
+            .get("TBPM")?
+            .content()
+            .text()?
+            .trim()
+            .parse()
+            .ok(),
+        "flac" => metaflac::Tag::read_from_path(path)
+            .ok()?
+            .get_vorbis("BPM")?
+            .next()?
+            .trim()
+            .parse()
+            .ok(),
+        _ => None,
+    }
+}
+
+// formats without a supported BPM tag are left alone, the cache still has it
+pub fn write_tag(path: &Path, bpm: f32) -> Result<(), io::Error> {
+    let bpm = format!("{}", bpm.round());
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("mp3") => {
+            let mut tag = id3::Tag::read_from_path(path).unwrap_or_default();
+            tag.set_text("TBPM", bpm);
+            tag.write_to_path(path, id3::Version::Id3v24)
+                .map_err(io::Error::other)
+        }
+        Some("flac") => {
+            let mut tag = metaflac::Tag::read_from_path(path).map_err(io::Error::other)?;
+            tag.set_vorbis("BPM", vec![bpm]);
+            tag.save().map_err(io::Error::other)
+        }
+        _ => Ok(()),
+    }
+}
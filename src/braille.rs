@@ -0,0 +1,34 @@
+use image::imageops::FilterType;
+
+// bit of the braille dot at [y][x] inside a 2x4 pixel cell
+const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+// renders an encoded image as `height` lines of `width` braille characters,
+// lighting every dot that is brighter than the image's mean luminance
+pub fn render(image_data: &[u8], width: u32, height: u32) -> Option<Vec<String>> {
+    let image = image::load_from_memory(image_data).ok()?;
+    let pixels = image
+        .resize_exact(width * 2, height * 4, FilterType::Triangle)
+        .into_luma8();
+    let mean = pixels.pixels().map(|p| p.0[0] as u32).sum::<u32>()
+        / (pixels.width() * pixels.height()).max(1);
+    let lines = (0..height)
+        .map(|row| {
+            (0..width)
+                .map(|col| {
+                    let mut bits = 0u8;
+                    for (dy, dots) in DOTS.iter().enumerate() {
+                        for (dx, dot) in dots.iter().enumerate() {
+                            let pixel = pixels.get_pixel(col * 2 + dx as u32, row * 4 + dy as u32);
+                            if pixel.0[0] as u32 > mean {
+                                bits |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits as u32).unwrap_or(' ')
+                })
+                .collect::<String>()
+        })
+        .collect();
+    Some(lines)
+}
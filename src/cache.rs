@@ -0,0 +1,132 @@
+use crate::{audio::AudioFile, bookmarks::Bookmark};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, io};
+
+// per-track data that is not stored in the file's tags and has to
+// survive a rescan of the library
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TrackData {
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub play_count: u64,
+    // tags found by an acoustid lookup, used in place of the file's own
+    #[serde(default)]
+    pub tags: Option<FixedTags>,
+    // removed from the library as a duplicate, the file itself may still exist
+    #[serde(default)]
+    pub hidden: bool,
+    // detected with the b key, for files whose format has no BPM tag
+    #[serde(default)]
+    pub bpm: Option<f32>,
+    // seconds of silence to skip at the start, set in the tag editor
+    #[serde(default)]
+    pub start_offset_secs: f64,
+    // result of the last flac verification, None for files never verified
+    #[serde(default)]
+    pub integrity_ok: Option<bool>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FixedTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct LibraryCache {
+    tracks: HashMap<String, TrackData>,
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+    // tracks found by the last library scan, to estimate the next one's progress
+    #[serde(default)]
+    last_scan_total: usize,
+    // fetched from musicbrainz and wikipedia, by artist name
+    #[serde(default)]
+    artist_bios: HashMap<String, String>,
+}
+
+impl LibraryCache {
+    pub fn load() -> Self {
+        let cwd = std::env::current_dir().unwrap();
+        let cache_path = cwd.join("library_cache.json");
+        // a missing or unreadable cache only loses user data, so start over
+        std::fs::read_to_string(cache_path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(contents.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), io::Error> {
+        let cwd = std::env::current_dir()?;
+        let cache_path = cwd.join("library_cache.json");
+        let cache_contents = serde_json::to_string(&self)?;
+        std::fs::write(cache_path, cache_contents)
+    }
+
+    fn key(file: &AudioFile) -> String {
+        match file.get_cue_offset_frames() {
+            Some(offset) => format!("{}#{}", file.get_path().display(), offset),
+            None => file.get_path().display().to_string(),
+        }
+    }
+
+    pub fn get(&self, file: &AudioFile) -> TrackData {
+        self.tracks
+            .get(&LibraryCache::key(file))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // copies the cached data onto a freshly scanned file
+    pub fn apply(&self, file: &mut AudioFile) {
+        let data = self.get(file);
+        file.set_rating(data.rating);
+        file.set_play_count(data.play_count);
+        file.set_start_offset_secs(data.start_offset_secs);
+        file.set_integrity_ok(data.integrity_ok);
+        if data.bpm.is_some() {
+            file.set_bpm(data.bpm);
+        }
+        if let Some(tags) = data.tags {
+            file.set_tags(tags.title, tags.artist, tags.album);
+        }
+    }
+
+    pub fn update(&mut self, file: &AudioFile, update: impl FnOnce(&mut TrackData)) {
+        update(self.tracks.entry(LibraryCache::key(file)).or_default());
+    }
+
+    pub fn get_last_scan_total(&self) -> usize {
+        self.last_scan_total
+    }
+
+    pub fn set_last_scan_total(&mut self, total: usize) {
+        self.last_scan_total = total;
+    }
+
+    pub fn get_artist_bio(&self, artist: &str) -> Option<&String> {
+        self.artist_bios.get(artist)
+    }
+
+    pub fn set_artist_bio(&mut self, artist: &str, bio: String) {
+        self.artist_bios.insert(artist.to_string(), bio);
+    }
+
+    pub fn get_bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn add_bookmark(&mut self, bookmark: Bookmark) {
+        self.bookmarks.push(bookmark);
+    }
+
+    pub fn remove_bookmark(&mut self, bookmark: &Bookmark) {
+        self.bookmarks.retain(|b| {
+            b.track_path != bookmark.track_path
+                || b.position_secs != bookmark.position_secs
+                || b.created_at != bookmark.created_at
+        });
+    }
+}
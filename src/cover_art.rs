@@ -0,0 +1,107 @@
+use crate::acoustid::USER_AGENT;
+use audiotags::{MimeType, Picture, Tag};
+use serde::Deserialize;
+use std::{fmt, path::Path};
+
+const RELEASE_GROUP_URL: &str = "https://musicbrainz.org/ws/2/release-group";
+const COVER_ART_URL: &str = "https://coverartarchive.org/release-group";
+
+#[derive(Debug)]
+pub enum CoverArtError {
+    Request(reqwest::Error),
+    // musicbrainz has no release group by that name and artist
+    NoMatch,
+    // the cover isn't a png, jpeg, tiff or bmp
+    UnknownFormat,
+    Tag(audiotags::Error),
+}
+
+impl fmt::Display for CoverArtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoverArtError::Request(e) => write!(f, "request failed: {}", e),
+            CoverArtError::NoMatch => write!(f, "album not found on musicbrainz"),
+            CoverArtError::UnknownFormat => write!(f, "cover art is in an unknown format"),
+            CoverArtError::Tag(e) => write!(f, "couldn't write the tags: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for CoverArtError {
+    fn from(e: reqwest::Error) -> Self {
+        CoverArtError::Request(e)
+    }
+}
+
+impl From<audiotags::Error> for CoverArtError {
+    fn from(e: audiotags::Error) -> Self {
+        CoverArtError::Tag(e)
+    }
+}
+
+pub struct CoverArt {
+    pub data: Vec<u8>,
+    pub mime_type: MimeType,
+}
+
+#[derive(Deserialize)]
+struct ReleaseGroupSearch {
+    #[serde(rename = "release-groups", default)]
+    release_groups: Vec<ReleaseGroup>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseGroup {
+    id: String,
+}
+
+// the best matching musicbrainz release group id
+fn lookup_release_group(album_artist: &str, album: &str) -> Result<String, CoverArtError> {
+    let quote = |value: &str| value.replace('"', "\\\"");
+    let query = format!(
+        "releasegroup:\"{}\" AND artist:\"{}\"",
+        quote(album),
+        quote(album_artist)
+    );
+    let search: ReleaseGroupSearch = reqwest::blocking::Client::new()
+        .get(RELEASE_GROUP_URL)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .query(&[("query", query.as_str()), ("limit", "1"), ("fmt", "json")])
+        .send()?
+        .error_for_status()?
+        .json()?;
+    search
+        .release_groups
+        .into_iter()
+        .next()
+        .map(|release_group| release_group.id)
+        .ok_or(CoverArtError::NoMatch)
+}
+
+// looks the album up on musicbrainz, then downloads its front cover from the
+// cover art archive
+pub fn fetch(album_artist: &str, album: &str) -> Result<CoverArt, CoverArtError> {
+    let mbid = lookup_release_group(album_artist, album)?;
+    let response = reqwest::blocking::Client::new()
+        .get(format!("{}/{}/front", COVER_ART_URL, mbid))
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .send()?
+        .error_for_status()?;
+    let mime_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| MimeType::try_from(value).ok())
+        .ok_or(CoverArtError::UnknownFormat)?;
+    Ok(CoverArt {
+        data: response.bytes()?.to_vec(),
+        mime_type,
+    })
+}
+
+pub fn embed(path: &Path, cover: &CoverArt) -> Result<(), CoverArtError> {
+    let mut tag = Tag::new().read_from_path(path)?;
+    tag.set_album_cover(Picture::new(&cover.data, cover.mime_type));
+    tag.write_to_path(&path.to_string_lossy())?;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+// cue sheet positions are given in frames, 75 of which make up a second
+pub const FRAMES_PER_SECOND: f64 = 75.0;
+
+#[derive(Clone, Debug)]
+pub struct CueTrack {
+    pub title: String,
+    pub artist: String,
+    // the PERFORMER given before the first track
+    pub album_artist: String,
+    pub album: String,
+    // TRACK nn AUDIO
+    pub number: Option<u16>,
+    pub start_frame: u64,
+    pub end_frame: Option<u64>,
+    pub file: PathBuf,
+}
+
+pub fn parse(cue_path: &Path) -> Result<Vec<CueTrack>, io::Error> {
+    let contents = std::fs::read_to_string(cue_path)?;
+    parse_contents(&contents, cue_path.parent().unwrap_or(Path::new("")), None)
+}
+
+// some single file flac releases carry their cue sheet in a CUESHEET vorbis
+// comment. its FILE lines name the original rip, so every track points back
+// at the flac itself instead
+pub fn read_embedded(flac_path: &Path) -> Option<Vec<CueTrack>> {
+    let tag = metaflac::Tag::read_from_path(flac_path).ok()?;
+    let contents = tag.get_vorbis("CUESHEET")?.next()?.to_owned();
+    let dir = flac_path.parent().unwrap_or(Path::new(""));
+    parse_contents(&contents, dir, Some(flac_path))
+        .ok()
+        .filter(|tracks| !tracks.is_empty())
+}
+
+fn parse_contents(
+    contents: &str,
+    dir: &Path,
+    file_override: Option<&Path>,
+) -> Result<Vec<CueTrack>, io::Error> {
+    let mut album = String::from("Unknown");
+    let mut album_artist = String::from("Unknown");
+    let mut file: Option<PathBuf> = None;
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    // whether TITLE/PERFORMER lines currently describe a track or the album
+    let mut in_track = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        let (command, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        match command {
+            "FILE" => {
+                // FILE "name.flac" WAVE
+                let name = match value.rsplit_once(char::is_whitespace) {
+                    Some((name, _file_type)) => name,
+                    None => value,
+                };
+                file = Some(match file_override {
+                    Some(path) => path.to_path_buf(),
+                    None => dir.join(unquote(name)),
+                });
+                in_track = false;
+            }
+            "TRACK" => {
+                let Some(file) = &file else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "TRACK before FILE in cue sheet",
+                    ));
+                };
+                tracks.push(CueTrack {
+                    title: String::from("Unknown"),
+                    artist: album_artist.clone(),
+                    album_artist: album_artist.clone(),
+                    album: album.clone(),
+                    number: value.split_whitespace().next().and_then(|n| n.parse().ok()),
+                    start_frame: 0,
+                    end_frame: None,
+                    file: file.clone(),
+                });
+                in_track = true;
+            }
+            "TITLE" => match tracks.last_mut() {
+                Some(track) if in_track => track.title = unquote(value),
+                _ => album = unquote(value),
+            },
+            "PERFORMER" => match tracks.last_mut() {
+                Some(track) if in_track => track.artist = unquote(value),
+                _ => album_artist = unquote(value),
+            },
+            "INDEX" => {
+                // INDEX 01 mm:ss:ff marks the start of the track
+                if let (Some(track), Some(("01", time))) =
+                    (tracks.last_mut(), value.split_once(char::is_whitespace))
+                {
+                    track.start_frame = parse_frames(time.trim())?;
+                }
+            }
+            _ => {}
+        }
+    }
+    // every track ends where the next one in the same file starts
+    for i in 1..tracks.len() {
+        if tracks[i].file == tracks[i - 1].file {
+            tracks[i - 1].end_frame = Some(tracks[i].start_frame);
+        }
+    }
+    Ok(tracks)
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').to_string()
+}
+
+fn parse_frames(time: &str) -> Result<u64, io::Error> {
+    let parts = time
+        .split(':')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    match parts[..] {
+        [minutes, seconds, frames] => Ok((minutes * 60 + seconds) * 75 + frames),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid cue sheet time: {}", time),
+        )),
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{audio::AudioFile, ui::Window};
+use crossterm::event::KeyCode;
+use std::io::{self, Stdout};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+// what to do with the copies the user didn't keep
+pub struct Resolution {
+    pub keep: AudioFile,
+    pub remove: Vec<AudioFile>,
+    pub delete_from_disk: bool,
+}
+
+// lists groups of identical files so the user can pick the copy to keep.
+// the library window owns it and applies the resolution
+pub struct DuplicatesWindow {
+    groups: Vec<Vec<AudioFile>>,
+    group: usize,
+    file: usize,
+    // set once a copy has been picked, while asking whether to delete the rest from disk
+    confirming: bool,
+    resolution: Option<Resolution>,
+}
+
+impl DuplicatesWindow {
+    pub fn new(groups: Vec<Vec<AudioFile>>) -> Self {
+        Self {
+            groups,
+            group: 0,
+            file: 0,
+            confirming: false,
+            resolution: None,
+        }
+    }
+
+    pub fn is_confirming(&self) -> bool {
+        self.confirming
+    }
+
+    pub fn take_resolution(&mut self) -> Option<Resolution> {
+        self.resolution.take()
+    }
+
+    fn resolve(&mut self, delete_from_disk: bool) {
+        let mut remove = self.groups.remove(self.group);
+        let keep = remove.remove(self.file);
+        self.resolution = Some(Resolution {
+            keep,
+            remove,
+            delete_from_disk,
+        });
+        self.group = self.group.min(self.groups.len().saturating_sub(1));
+        self.file = 0;
+        self.confirming = false;
+    }
+}
+
+impl Window for DuplicatesWindow {
+    fn get_title(&self) -> String {
+        if self.confirming {
+            String::from("Duplicates - also delete the other copies from disk? y/n, Esc to cancel")
+        } else {
+            format!(
+                "Duplicates - {} groups, Enter keeps the selected copy, Esc to close",
+                self.groups.len()
+            )
+        }
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let mut items = Vec::new();
+        let mut selected = None;
+        for (i, group) in self.groups.iter().enumerate() {
+            items.push(
+                ListItem::new(format!(
+                    "{} - {} ({} copies)",
+                    group[0].get_title(),
+                    group[0].get_artist(),
+                    group.len()
+                ))
+                .style(Style::default().fg(Color::Yellow)),
+            );
+            for (j, file) in group.iter().enumerate() {
+                if i == self.group && j == self.file {
+                    selected = Some(items.len());
+                }
+                items.push(ListItem::new(format!("    {}", file.get_path().display())));
+            }
+        }
+        if items.is_empty() {
+            items.push(ListItem::new("No duplicates found"));
+        }
+        let mut state = ListState::default();
+        state.select(selected);
+        let duplicates_list = List::new(items)
+            .block(
+                Block::default()
+                    .title(self.get_title())
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(duplicates_list, area, &mut state);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if self.groups.is_empty() {
+            return Ok(());
+        }
+        if self.confirming {
+            match key {
+                KeyCode::Char('y') => self.resolve(true),
+                KeyCode::Char('n') => self.resolve(false),
+                KeyCode::Esc => self.confirming = false,
+                _ => {}
+            }
+            return Ok(());
+        }
+        let group_len = self.groups[self.group].len();
+        match key {
+            KeyCode::Up if self.file > 0 => self.file -= 1,
+            KeyCode::Up if self.group > 0 => {
+                self.group -= 1;
+                self.file = self.groups[self.group].len() - 1;
+            }
+            KeyCode::Down if self.file + 1 < group_len => self.file += 1,
+            KeyCode::Down if self.group + 1 < self.groups.len() => {
+                self.group += 1;
+                self.file = 0;
+            }
+            KeyCode::Enter => self.confirming = true,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,50 @@
+use crate::library::{recursive_file_walk, with_thousands_separators};
+use std::{collections::HashSet, path::Path};
+
+#[derive(Clone, Copy, Default)]
+pub struct FolderStats {
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+impl FolderStats {
+    // like "1,234 tracks, 45.2 GB"
+    pub fn describe(&self) -> String {
+        format!(
+            "{} tracks, {}",
+            with_thousands_separators(self.file_count),
+            format_bytes(self.total_bytes)
+        )
+    }
+}
+
+// every audio file under the folder, cue sheets aside since they only point
+// at audio that is counted already
+pub fn compute(path: &Path) -> FolderStats {
+    let mut stats = FolderStats::default();
+    if !path.is_dir() {
+        return stats;
+    }
+    for file in recursive_file_walk(path, usize::MAX, &[], &mut HashSet::new(), &mut Vec::new()) {
+        if file.ends_with(".cue") {
+            continue;
+        }
+        stats.file_count += 1;
+        stats.total_bytes += std::fs::metadata(&file).map_or(0, |metadata| metadata.len());
+    }
+    stats
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
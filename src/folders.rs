@@ -1,19 +1,33 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     io::{self, Stdout},
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
 };
 
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent};
 use tui::{
+    layout::{Constraint, Direction, Layout},
     prelude::{CrosstermBackend, Rect},
     style::{Color, Style},
     widgets::{Block, Borders, List, ListItem, ListState},
     Frame,
 };
 
-use crate::{settings::Settings, ui::Window};
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    folder_stats::{self, FolderStats},
+    input::InputWidget,
+    library::LibraryWindow,
+    settings::Settings,
+    ui::Window,
+};
 
 enum ExplorerState {
     Explore(String),
@@ -26,6 +40,18 @@ pub struct FoldersWindow {
     explorer_window: FileExplorerWindow,
     estate: ExplorerState,
     settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    library_window: Rc<RefCell<LibraryWindow>>,
+    // shared with the library window, set when the folder list changes
+    needs_refresh: Arc<AtomicBool>,
+    // result of the last folder queued or rescanned, shown in the title
+    status: Option<String>,
+    // track counts and sizes, worked out in the background the first time
+    // each folder is shown
+    stats: HashMap<PathBuf, FolderStats>,
+    stats_receiver: Option<Receiver<(PathBuf, FolderStats)>>,
+    // query typed after /, the list only shows folders containing it
+    search: Option<InputWidget>,
 }
 
 impl Window for FoldersWindow {
@@ -40,20 +66,51 @@ impl Window for FoldersWindow {
     ) -> std::result::Result<(), io::Error> {
         match &self.estate {
             ExplorerState::None => {
-                let ref_settings = self.settings.borrow();
-                let mut lib_folders = ref_settings
-                    .lib_folders
+                self.poll_stats();
+                self.poll_rescan();
+                let mut lib_folders = self
+                    .visible_folders()
                     .iter()
-                    .map(|folder| ListItem::new(folder.as_str()))
+                    .map(|folder| match self.stats.get(folder) {
+                        Some(stats) => {
+                            ListItem::new(format!("{}  ({})", folder.display(), stats.describe()))
+                        }
+                        None => ListItem::new(format!("{}  (counting…)", folder.display())),
+                    })
                     .collect::<Vec<_>>();
-                lib_folders
-                    .push(ListItem::new("Add a Folder").style(Style::default().fg(Color::Yellow)));
+                if self.search.is_none() {
+                    lib_folders.push(
+                        ListItem::new("Add a Folder").style(Style::default().fg(Color::Yellow)),
+                    );
+                }
+                let title = match &self.status {
+                    Some(status) => format!("Folders - {}", status),
+                    None => String::from(
+                        "Folders - Enter to queue a folder, r to rescan it, d to remove it, / to search",
+                    ),
+                };
                 let folder_list_widget = List::new(lib_folders)
-                    .block(Block::default().title("Folders").borders(Borders::ALL))
+                    .block(Block::default().title(title).borders(Borders::ALL))
                     .style(Style::default().fg(Color::Green))
                     .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
                     .highlight_symbol(">> ");
-                f.render_stateful_widget(folder_list_widget, area, &mut self.state);
+                let Some(search) = &self.search else {
+                    f.render_stateful_widget(folder_list_widget, area, &mut self.state);
+                    return Ok(());
+                };
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(3)])
+                    .split(area);
+                f.render_stateful_widget(folder_list_widget, chunks[0], &mut self.state);
+                search.draw(
+                    Block::default()
+                        .title("Search - Enter to open, Esc to clear")
+                        .borders(Borders::ALL),
+                    Style::default().fg(Color::Yellow),
+                    chunks[1],
+                    f,
+                );
                 Ok(())
             }
             ExplorerState::Explore(s) => {
@@ -65,71 +122,306 @@ impl Window for FoldersWindow {
 
     fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
         match &self.estate {
+            ExplorerState::None if self.search.is_some() => {
+                self.handle_search_input(KeyEvent::from(key));
+                Ok(())
+            }
             ExplorerState::None => {
                 match key {
+                    KeyCode::Char('/') => {
+                        self.search = Some(InputWidget::new());
+                        self.state.select(Some(0));
+                    }
                     KeyCode::Up => self.previous(),
                     KeyCode::Down => self.next(),
-                    KeyCode::Enter => self.file_explorer(if let Some(i) = self.state.selected() {
-                        self.settings.borrow().lib_folders[i].clone()
-                    } else {
-                        format!("{}", home::home_dir().unwrap().display())
-                    }),
+                    KeyCode::Enter => match self.selected_folder() {
+                        Some(folder) => self.queue_folder(&folder),
+                        None => self.file_explorer(home::home_dir().unwrap()),
+                    },
+                    KeyCode::Char('r') => {
+                        if let Some(folder) = self.selected_folder() {
+                            self.rescan_folder(&folder);
+                        }
+                    }
+                    KeyCode::Char('d') => {
+                        if let Some(folder) = self.selected_folder() {
+                            self.remove_folder(&folder);
+                        }
+                    }
+                    _ => {}
+                }
+                Ok(())
+            }
+            ExplorerState::Explore(path) => {
+                let path = PathBuf::from(path);
+                match key {
+                    KeyCode::Up | KeyCode::Down => self.explorer_window.handle_input(key)?,
+                    KeyCode::Enter => match self.explorer_window.selected_entry() {
+                        Some(entry) if entry.is_dir() => self.file_explorer(entry),
+                        Some(entry) => self.queue_file(&entry),
+                        None => {}
+                    },
+                    KeyCode::Backspace => {
+                        if let Some(parent) = path.parent() {
+                            self.file_explorer(parent.to_path_buf());
+                        }
+                    }
+                    KeyCode::Char('a') => self.add_folder(path),
+                    KeyCode::Esc => self.estate = ExplorerState::None,
                     _ => {}
                 }
                 Ok(())
             }
-            ExplorerState::Explore(s) => Ok(()),
         }
     }
+
+    fn is_typing(&self) -> bool {
+        self.search.is_some()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> std::result::Result<(), io::Error> {
+        if self.search.is_none() {
+            return self.handle_input(key.code);
+        }
+        self.handle_search_input(key);
+        Ok(())
+    }
 }
 
 impl FoldersWindow {
-    pub fn new(settings: Rc<RefCell<Settings>>) -> Self {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        library_window: Rc<RefCell<LibraryWindow>>,
+    ) -> Self {
         let mut state = ListState::default();
         state.select(Some(0));
         let file_explorer_window: FileExplorerWindow = FileExplorerWindow::new();
+        let needs_refresh = library_window.borrow().refresh_flag();
         Self {
             settings: settings.clone(),
             title: "Folders".to_string(),
             state,
             estate: ExplorerState::None,
             explorer_window: file_explorer_window,
+            audio_interface,
+            library_window,
+            needs_refresh,
+            status: None,
+            stats: HashMap::new(),
+            stats_receiver: None,
+            search: None,
         }
     }
-    pub fn next(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.settings.borrow().lib_folders.len() {
-                    0
-                } else {
-                    i + 1
+
+    // picks up finished counts, and starts counting folders that have none
+    fn poll_stats(&mut self) {
+        if let Some(receiver) = &self.stats_receiver {
+            loop {
+                match receiver.try_recv() {
+                    Ok((folder, stats)) => {
+                        self.stats.insert(folder, stats);
+                    }
+                    Err(mpsc::TryRecvError::Empty) => return,
+                    Err(mpsc::TryRecvError::Disconnected) => break,
                 }
             }
-            None => 0,
+            self.stats_receiver = None;
+        }
+        let missing = self
+            .settings
+            .borrow()
+            .get_lib_folders_as_paths()
+            .into_iter()
+            .filter(|folder| !self.stats.contains_key(folder))
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for folder in missing {
+                let stats = folder_stats::compute(&folder);
+                if sender.send((folder, stats)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.stats_receiver = Some(receiver);
+    }
+
+    // plays the folder as an album, without going through the explorer
+    fn queue_folder(&mut self, folder: &Path) {
+        let mut tracks = AudioFile::from_dir(folder);
+        self.status = Some(match tracks.len() {
+            0 => format!("no tracks in {}", folder.display()),
+            count => format!("queued {} tracks from {}", count, folder.display()),
+        });
+        self.audio_interface
+            .borrow_mut()
+            .append_to_queue(&mut tracks);
+    }
+
+    fn rescan_folder(&mut self, folder: &Path) {
+        self.status = Some(if self.library_window.borrow_mut().rescan_folder(folder) {
+            format!("rescanning {}…", folder.display())
+        } else {
+            String::from("wait for the current scan to finish")
+        });
+    }
+
+    // the library window merges the rescan, this only reports it
+    fn poll_rescan(&mut self) {
+        let Some((folder, added, removed)) = self.library_window.borrow_mut().take_rescan_result()
+        else {
+            return;
         };
-        self.state.select(Some(i));
+        // counted again on the next draw
+        self.stats.remove(&folder);
+        self.status = Some(format!(
+            "rescanned {}, {} added, {} removed",
+            folder.display(),
+            added,
+            removed
+        ));
     }
 
-    pub fn previous(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.settings.borrow().lib_folders.len() - 1
-                } else {
-                    i - 1
+    fn handle_search_input(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => return self.previous(),
+            KeyCode::Down => return self.next(),
+            KeyCode::Esc => self.search = None,
+            KeyCode::Enter => {
+                if let Some(folder) = self.selected_folder() {
+                    self.search = None;
+                    self.file_explorer(folder);
                 }
             }
-            None => 0,
-        };
+            code => {
+                if !search.handle_key(code, key.modifiers) {
+                    return;
+                }
+            }
+        }
+        // the first match is selected whenever the query changes
+        self.state.select(Some(0));
+    }
+
+    // the folders matching the search, or all of them
+    fn visible_folders(&self) -> Vec<PathBuf> {
+        let folders = self.settings.borrow().get_lib_folders_as_paths();
+        match &self.search {
+            Some(search) => {
+                let search = search.text().to_lowercase();
+                folders
+                    .into_iter()
+                    .filter(|folder| {
+                        folder
+                            .display()
+                            .to_string()
+                            .to_lowercase()
+                            .contains(&search)
+                    })
+                    .collect()
+            }
+            None => folders,
+        }
+    }
+
+    // the folders plus "Add a Folder", which is hidden while searching
+    fn list_len(&self) -> usize {
+        self.visible_folders().len() + usize::from(self.search.is_none())
+    }
+
+    // None while "Add a Folder" is selected
+    fn selected_folder(&self) -> Option<PathBuf> {
+        self.state
+            .selected()
+            .and_then(|i| self.visible_folders().get(i).cloned())
+    }
+
+    pub fn next(&mut self) {
+        let len = self.list_len();
+        if len == 0 {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| (i + 1) % len);
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let len = self.list_len();
+        if len == 0 {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| (i + len - 1) % len);
         self.state.select(Some(i));
     }
 
-    fn file_explorer(&self, path: String) {}
+    fn file_explorer(&mut self, path: PathBuf) {
+        self.estate = ExplorerState::Explore(path.display().to_string());
+    }
+
+    fn queue_file(&mut self, path: &Path) {
+        self.status = Some(match AudioFile::new(&path.display().to_string()) {
+            Ok(file) => {
+                self.audio_interface
+                    .borrow_mut()
+                    .append_to_queue(&mut vec![file]);
+                format!("queued {}", path.display())
+            }
+            Err(e) => format!("can't play {}: {}", path.display(), e),
+        });
+    }
+
+    // adds the folder being explored to the library and scans it straight away
+    fn add_folder(&mut self, path: PathBuf) {
+        self.estate = ExplorerState::None;
+        let added = self
+            .settings
+            .borrow_mut()
+            .add_lib_folder(&path.display().to_string());
+        self.status = Some(match added {
+            Ok(()) => {
+                self.needs_refresh.store(true, Ordering::Relaxed);
+                format!("added {}, rescanning the library", path.display())
+            }
+            Err(e) => format!("can't add {}: {}", path.display(), e),
+        });
+    }
+
+    fn remove_folder(&mut self, folder: &Path) {
+        self.settings
+            .borrow_mut()
+            .lib_folders
+            .retain(|lib_folder| Path::new(lib_folder) != folder);
+        self.stats.remove(folder);
+        if self.state.selected().unwrap_or(0) >= self.list_len() {
+            self.state.select(Some(self.list_len().saturating_sub(1)));
+        }
+        self.folders_changed(format!("removed {}", folder.display()));
+    }
+
+    // saves the new folder list and has the library scan it again the
+    // next time it is drawn
+    fn folders_changed(&mut self, status: String) {
+        self.status = Some(match self.settings.borrow().save() {
+            Ok(()) => format!("{}, rescanning the library", status),
+            Err(e) => format!("{}, but could not save settings: {}", status, e),
+        });
+        self.needs_refresh.store(true, Ordering::Relaxed);
+    }
 }
 
 pub struct FileExplorerWindow {
     title: String,
     path: String,
+    // directories first, then files, each sorted by name
+    entries: Vec<PathBuf>,
+    state: ListState,
 }
 
 impl Window for FileExplorerWindow {
@@ -142,15 +434,46 @@ impl Window for FileExplorerWindow {
         area: Rect,
         f: &mut Frame<CrosstermBackend<Stdout>>,
     ) -> Result<(), io::Error> {
-        // let new_path = Path::new(&self.path);
-        // let mut Files = new_path.read_dir().unwrap();
-        // let mut file_vec = Vec::new();
-        // Files.map(|x| {
-        //     // let y = x.unwrap().path().file_name().unwrap().to_str().unwrap();
-        //     ListItem::new(y.clone());
-        // });
-        // let mut FileWindow = List::new(file_vec);
-        // f.render_widget(FileWindow, area);
+        let items = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let name = entry
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if entry.is_dir() {
+                    ListItem::new(format!("{}/", name))
+                } else {
+                    ListItem::new(name)
+                }
+            })
+            .collect::<Vec<_>>();
+        let title = format!(
+            "{} - Enter to open or queue, Backspace to go up, a to add this folder, Esc to close",
+            self.title
+        );
+        let file_list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(file_list, area, &mut self.state);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => self.state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => self
+                .state
+                .select(Some((selected + 1).min(self.entries.len() - 1))),
+            _ => {}
+        }
         Ok(())
     }
 }
@@ -158,13 +481,44 @@ impl Window for FileExplorerWindow {
 impl FileExplorerWindow {
     fn new() -> Self {
         let path = format!("{}", home::home_dir().unwrap().display());
-        Self {
+        let mut explorer = Self {
             title: path.clone(),
             path: path.clone(),
-        }
+            entries: Vec::new(),
+            state: ListState::default(),
+        };
+        explorer.read_dir();
+        explorer
     }
 
+    // rereads the directory only when it changes, so the selection survives redraws
     fn set_cwd(&mut self, s: &str) {
-        self.path = s.to_string();
+        if self.path != s {
+            self.path = s.to_string();
+            self.title = s.to_string();
+            self.read_dir();
+        }
+    }
+
+    // hidden files are left out, an unreadable directory shows up empty
+    fn read_dir(&mut self) {
+        let mut entries = std::fs::read_dir(&self.path)
+            .map(|dir| {
+                dir.filter_map(Result::ok)
+                    .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                    .map(|entry| entry.path())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        entries.sort_by_key(|entry| (!entry.is_dir(), entry.file_name().map(|n| n.to_os_string())));
+        self.entries = entries;
+        self.state.select(Some(0));
+    }
+
+    fn selected_entry(&self) -> Option<PathBuf> {
+        self.state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .cloned()
     }
 }
@@ -0,0 +1,127 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::io::Stdout;
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+// a single line of text with a cursor, edited like a shell prompt
+#[derive(Default)]
+pub struct InputWidget {
+    text: String,
+    // in characters, not bytes
+    cursor: usize,
+}
+
+impl InputWidget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    // start of the word before the cursor, skipping the spaces in between
+    fn previous_word(&self) -> usize {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut i = self.cursor;
+        while i > 0 && chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        while i > 0 && !chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        i
+    }
+
+    // end of the word after the cursor
+    fn next_word(&self) -> usize {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut i = self.cursor;
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    }
+
+    // returns true if the text changed, keys it doesn't know are left to the caller
+    pub fn handle_key(&mut self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        match key {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.len(),
+            KeyCode::Char('k') if ctrl => {
+                let i = self.byte_index(self.cursor);
+                self.text.truncate(i);
+                return true;
+            }
+            KeyCode::Char('u') if ctrl => {
+                let i = self.byte_index(self.cursor);
+                self.text.replace_range(..i, "");
+                self.cursor = 0;
+                return true;
+            }
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => {
+                let i = self.byte_index(self.cursor);
+                self.text.insert(i, c);
+                self.cursor += 1;
+                return true;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let i = self.byte_index(self.cursor);
+                self.text.remove(i);
+                return true;
+            }
+            KeyCode::Delete if self.cursor < self.len() => {
+                let i = self.byte_index(self.cursor);
+                self.text.remove(i);
+                return true;
+            }
+            KeyCode::Left if ctrl => self.cursor = self.previous_word(),
+            KeyCode::Right if ctrl => self.cursor = self.next_word(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.len(),
+            _ => {}
+        }
+        false
+    }
+
+    // the text inside the block, with the terminal cursor placed where typing goes
+    pub fn draw(
+        &self,
+        block: Block,
+        style: Style,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) {
+        let inner = block.inner(area);
+        let width = inner.width as usize;
+        // scrolled so the cursor stays inside the box
+        let scroll = (self.cursor + 1).saturating_sub(width);
+        let visible = self.text.chars().skip(scroll).collect::<String>();
+        f.render_widget(Paragraph::new(visible).block(block).style(style), area);
+        f.set_cursor(inner.x + (self.cursor - scroll) as u16, inner.y);
+    }
+}
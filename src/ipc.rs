@@ -0,0 +1,123 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+pub enum Command {
+    Play,
+    Pause,
+    Next,
+    Prev,
+    Volume(f32),
+    Status,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (command, argument) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match command.to_uppercase().as_str() {
+            "PLAY" => Ok(Command::Play),
+            "PAUSE" => Ok(Command::Pause),
+            "NEXT" => Ok(Command::Next),
+            "PREV" => Ok(Command::Prev),
+            "VOLUME" => argument
+                .trim()
+                .parse::<f32>()
+                .map(Command::Volume)
+                .map_err(|_| format!("invalid volume: {}", argument.trim())),
+            "STATUS" => Ok(Command::Status),
+            _ => Err(format!("unknown command: {}", command)),
+        }
+    }
+}
+
+// a command read from the socket, waiting for the ui thread to answer it
+pub struct Request {
+    pub command: Command,
+    reply: Sender<String>,
+}
+
+impl Request {
+    pub fn reply(self, response: String) {
+        // the client may have hung up already, there's nobody left to tell
+        let _ = self.reply.send(response);
+    }
+}
+
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("rmus.sock"),
+        None => std::env::temp_dir().join("rmus.sock"),
+    }
+}
+
+// the audio interface can't leave the ui thread, so the listener thread
+// only parses commands and hands them over to be run on the next tick
+pub struct IpcServer {
+    path: PathBuf,
+    requests: Receiver<Request>,
+}
+
+impl IpcServer {
+    pub fn start() -> Result<Self, io::Error> {
+        let path = socket_path();
+        // left behind by a previous run that didn't shut down cleanly
+        if UnixStream::connect(&path).is_err() {
+            let _ = std::fs::remove_file(&path);
+        }
+        let listener = UnixListener::bind(&path)?;
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || handle_client(stream, sender));
+            }
+        });
+        Ok(Self { path, requests })
+    }
+
+    pub fn pending(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn handle_client(stream: UnixStream, sender: Sender<Request>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match line.parse::<Command>() {
+            Ok(command) => {
+                let (reply, response) = mpsc::channel();
+                if sender.send(Request { command, reply }).is_err() {
+                    return;
+                }
+                response
+                    .recv()
+                    .unwrap_or_else(|_| String::from("ERR shutting down"))
+            }
+            Err(e) => format!("ERR {}", e),
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+pub mod acoustid;
+pub mod album;
+pub mod audio;
+pub mod bio;
+pub mod bookmarks;
+pub mod bpm;
+pub mod braille;
+pub mod cache;
+pub mod cover_art;
+pub mod cue;
+pub mod duplicates;
+pub mod folder_stats;
+pub mod folders;
+pub mod input;
+// the socket rmusctl talks to, unix only
+#[cfg(unix)]
+pub mod ipc;
+pub mod library;
+pub mod listenbrainz;
+pub mod log;
+pub mod lrc;
+pub mod lyrics;
+pub mod missing_art;
+pub mod mpd_compat;
+pub mod mpris;
+pub mod notification;
+pub mod playlist;
+pub mod playlist_manager;
+pub mod podcast;
+pub mod queue;
+pub mod resample;
+pub mod server;
+pub mod settings;
+pub mod smart_playlist;
+pub mod subsonic;
+pub mod tag_editor;
+pub mod tidal;
+pub mod tts;
+pub mod ui;
+pub mod update_checker;
+pub mod visualizer;
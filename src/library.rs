@@ -1,66 +1,753 @@
 use crate::{
-    audio::{AudioFile, AudioInterface},
+    acoustid::{self, UnmatchedFingerprint},
+    audio::{AudioFile, AudioInterface, AutoAdvance},
+    bio, bpm, braille,
+    cache::{FixedTags, LibraryCache},
+    cue,
+    duplicates::{DuplicatesWindow, Resolution},
+    log,
+    log::Level,
+    missing_art::MissingArtWindow,
     settings::Settings,
-    ui::Window,
+    tag_editor::EditTagsWindow,
+    ui::{centered_rect, focus_border_style, render_popup, Window},
 };
 use crossterm::event::KeyCode;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
-    io::{self, Stdout},
-    path::Path,
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, Read, Stdout},
+    path::{Path, PathBuf},
     rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    time::{Duration, SystemTime},
 };
 use tui::{
     backend::CrosstermBackend,
     layout::{Constraint, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Row, Table, TableState},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Borders, Cell, Gauge, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+        Wrap,
+    },
     Frame,
 };
 
+#[derive(Clone, Copy)]
+enum GroupBy {
+    Genre,
+    Artist,
+}
+
+impl GroupBy {
+    fn title(&self) -> &'static str {
+        match self {
+            GroupBy::Genre => "Genres",
+            GroupBy::Artist => "Artists",
+        }
+    }
+
+    fn key<'a>(&self, file: &'a AudioFile) -> &'a String {
+        match self {
+            GroupBy::Genre => file.get_genre(),
+            GroupBy::Artist => file.get_artist(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortColumn {
+    Title,
+    Artist,
+    Album,
+    Year,
+    Duration,
+    Rating,
+}
+
+impl SortColumn {
+    fn next(self) -> Self {
+        match self {
+            SortColumn::Title => SortColumn::Artist,
+            SortColumn::Artist => SortColumn::Album,
+            SortColumn::Album => SortColumn::Year,
+            SortColumn::Year => SortColumn::Duration,
+            SortColumn::Duration => SortColumn::Rating,
+            SortColumn::Rating => SortColumn::Title,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SortColumn::Title => "Title",
+            SortColumn::Artist => "Artist",
+            SortColumn::Album => "Album",
+            SortColumn::Year => "Year",
+            SortColumn::Duration => "Length",
+            SortColumn::Rating => "Rating",
+        }
+    }
+
+    fn compare(&self, a: &AudioFile, b: &AudioFile) -> std::cmp::Ordering {
+        match self {
+            SortColumn::Title => a.get_title().cmp(b.get_title()),
+            SortColumn::Artist => a.get_artist().cmp(b.get_artist()),
+            // whole albums together, in the order they play
+            SortColumn::Album => a
+                .get_album_artist()
+                .cmp(b.get_album_artist())
+                .then_with(|| a.get_album().cmp(b.get_album()))
+                .then_with(|| a.album_position().cmp(&b.album_position())),
+            SortColumn::Year => a.get_year().cmp(&b.get_year()),
+            SortColumn::Duration => a.get_raw_duration().total_cmp(&b.get_raw_duration()),
+            // best rated first
+            SortColumn::Rating => b.get_rating().cmp(&a.get_rating()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum RatingFilter {
+    All,
+    AtLeast(u8),
+}
+
+impl RatingFilter {
+    fn next(self) -> Self {
+        match self {
+            RatingFilter::All => RatingFilter::AtLeast(3),
+            RatingFilter::AtLeast(stars) if stars < 5 => RatingFilter::AtLeast(stars + 1),
+            RatingFilter::AtLeast(_) => RatingFilter::All,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            RatingFilter::All => String::from("All"),
+            RatingFilter::AtLeast(5) => String::from("5 stars only"),
+            RatingFilter::AtLeast(stars) => format!("≥{} stars", stars),
+        }
+    }
+
+    fn matches(&self, file: &AudioFile) -> bool {
+        match self {
+            RatingFilter::All => true,
+            RatingFilter::AtLeast(stars) => file.get_rating().unwrap_or(0) >= *stars,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum QualityFilter {
+    All,
+    Lossless,
+    Lossy,
+}
+
+impl QualityFilter {
+    fn next(self) -> Self {
+        match self {
+            QualityFilter::All => QualityFilter::Lossless,
+            QualityFilter::Lossless => QualityFilter::Lossy,
+            QualityFilter::Lossy => QualityFilter::All,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            QualityFilter::All => "All",
+            QualityFilter::Lossless => "Lossless only",
+            QualityFilter::Lossy => "Lossy only",
+        }
+    }
+
+    fn matches(&self, file: &AudioFile) -> bool {
+        match self {
+            QualityFilter::All => true,
+            QualityFilter::Lossless => file.is_lossless(),
+            QualityFilter::Lossy => !file.is_lossless(),
+        }
+    }
+}
+
+// opened with b on an artist in the artist browser
+struct ArtistBio {
+    artist: String,
+    // None while it is being fetched
+    bio: Option<String>,
+    receiver: Option<Receiver<Result<String, reqwest::Error>>>,
+}
+
+// the album opened in the album browser, the track list only shows it
+struct AlbumContext {
+    album: String,
+    artist: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+        }
+    }
+
+    fn default_path(&self) -> String {
+        let file_name = match self {
+            ExportFormat::Csv => "rmus_library.csv",
+            ExportFormat::Json => "rmus_library.json",
+        };
+        format!("{}", home::home_dir().unwrap().join(file_name).display())
+    }
+}
+
+// the export popup first asks for a format, then for where to write the file
+enum ExportPrompt {
+    Format(ExportFormat),
+    Path(ExportFormat, String),
+}
+
+// size in braille characters of the cover art in the info panel
+const INFO_ART_WIDTH: u32 = 16;
+const INFO_ART_HEIGHT: u32 = 8;
+
+#[derive(Clone, Copy)]
+enum LibraryView {
+    Tracks,
+    Browse(GroupBy),
+}
+
+// a row of the tracks table, the separator heads the recently added tracks
+enum TableRow {
+    Separator(String),
+    Track(usize),
+}
+
+// sent from the scan thread, the files that couldn't be read come with Done
+pub enum ScanProgress {
+    Scanning { total_found: usize },
+    Done(Vec<AudioFile>, Vec<(PathBuf, String)>),
+}
+
+// tracks read between progress updates, so the channel isn't flooded
+const SCAN_PROGRESS_INTERVAL: usize = 25;
+
+struct Verification {
+    results: Receiver<(PathBuf, Option<bool>)>,
+    done: usize,
+    total: usize,
+}
+
 pub struct LibraryWindow {
     title: String,
     settings: Rc<RefCell<Settings>>,
     audio_interface: Rc<RefCell<AudioInterface>>,
+    cache: Rc<RefCell<LibraryCache>>,
     music_list: Vec<AudioFile>,
     state: TableState,
+    // index into the visible tracks of the first row drawn
+    visible_window_start: usize,
+    sort_column: Option<SortColumn>,
+    rating_filter: RatingFilter,
+    quality_filter: QualityFilter,
+    // while set, next and previous stay within one album in track order
+    album_context: Option<AlbumContext>,
+    view: LibraryView,
+    // 0 while the group list has focus, 1 for the track table next to it
+    browse_pane: usize,
+    group_state: ListState,
+    group_track_state: TableState,
+    // result of the last tag lookup or edit, shown in the table title
+    status: Option<String>,
+    // open tag editor and the index in music_list of the file it edits
+    editor: Option<(usize, EditTagsWindow)>,
+    export: Option<ExportPrompt>,
+    duplicates: Option<DuplicatesWindow>,
+    // the background search for duplicates, until the window opens
+    duplicate_scan: Option<Receiver<Vec<Vec<AudioFile>>>>,
+    missing_art: Option<MissingArtWindow>,
+    // the background verification of every flac
+    verify: Option<Verification>,
+    // files modified within settings.recently_added_days when the library was loaded
+    recently_added: HashSet<PathBuf>,
+    show_info: bool,
+    // cover art of the file last shown in the info panel, decoding it every draw is slow
+    info_art: Option<(PathBuf, Option<Vec<String>>)>,
+    // the background scan while the library is loading
+    scan: Option<Receiver<ScanProgress>>,
+    // a single folder scanned again in the background, the rest of the
+    // library stays usable meanwhile
+    rescan: Option<(PathBuf, Receiver<ScanProgress>)>,
+    // the folder of the last rescan with how many tracks it added and
+    // removed, until the folders window takes it
+    rescan_result: Option<(PathBuf, usize, usize)>,
+    // set when music_list changes, until the ui passes it on to the other windows
+    library_changed: bool,
+    // tracks read so far by the background scan
+    scan_found: usize,
+    // files the scan found but couldn't read, with the reason
+    scan_errors: Vec<(PathBuf, String)>,
+    // selection in the scan errors popup while it is open
+    scan_errors_state: Option<ListState>,
+    artist_bio: Option<ArtistBio>,
+    // set by the folders window when library folders are added or removed
+    needs_refresh: Arc<AtomicBool>,
+    // a recording id from the tag editor whose file acoustid couldn't match,
+    // waiting for y/n to submit the fingerprint
+    submission: Option<(String, UnmatchedFingerprint)>,
 }
 
 impl LibraryWindow {
     pub fn new(
         settings: Rc<RefCell<Settings>>,
         audio_interface: Rc<RefCell<AudioInterface>>,
+        cache: Rc<RefCell<LibraryCache>>,
     ) -> Self {
-        let music_list = LibraryWindow::load_music_list(settings.clone());
+        let scan = LibraryWindow::start_scan(&settings.borrow());
         let mut state = TableState::default();
         state.select(Some(0));
         Self {
             title: String::from("Library"),
-            music_list,
+            music_list: Vec::new(),
             state,
+            visible_window_start: 0,
+            scan: Some(scan),
+            rescan: None,
+            rescan_result: None,
+            library_changed: false,
+            scan_found: 0,
             settings,
             audio_interface,
+            cache,
+            sort_column: None,
+            rating_filter: RatingFilter::All,
+            quality_filter: QualityFilter::All,
+            album_context: None,
+            view: LibraryView::Tracks,
+            browse_pane: 0,
+            group_state: ListState::default(),
+            group_track_state: TableState::default(),
+            status: None,
+            editor: None,
+            export: None,
+            duplicates: None,
+            duplicate_scan: None,
+            missing_art: None,
+            verify: None,
+            recently_added: HashSet::new(),
+            show_info: false,
+            info_art: None,
+            scan_errors: Vec::new(),
+            scan_errors_state: None,
+            artist_bio: None,
+            needs_refresh: Arc::new(AtomicBool::new(false)),
+            submission: None,
         }
     }
 
-    fn load_music_list(settings: Rc<RefCell<Settings>>) -> Vec<AudioFile> {
-        let music_list = recursive_vec_file_walk(settings.borrow().get_lib_folders());
-        music_list
+    pub fn refresh_flag(&self) -> Arc<AtomicBool> {
+        self.needs_refresh.clone()
+    }
+
+    // scans the library folders again from scratch, the list is empty
+    // until the new scan is done
+    pub fn reload_from_settings(&mut self) {
+        self.music_list.clear();
+        self.state.select(Some(0));
+        self.visible_window_start = 0;
+        self.scan_found = 0;
+        // the full scan covers the folder as well
+        self.rescan = None;
+        self.scan = Some(LibraryWindow::start_scan(&self.settings.borrow()));
+    }
+
+    // reading the tags of a large library takes a while, so it happens on
+    // its own thread and the window picks the result up in poll_scan
+    fn start_scan(settings: &Settings) -> Receiver<ScanProgress> {
+        LibraryWindow::scan_folders(settings, settings.get_lib_folders_as_paths())
+    }
+
+    fn scan_folders(settings: &Settings, folders: Vec<PathBuf>) -> Receiver<ScanProgress> {
+        let exclude = exclude_patterns(settings);
+        let max_depth = settings.scan_max_depth;
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || scan_library(folders, max_depth, &exclude, &sender));
+        receiver
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    // checks each flac against its md5 on a thread, files found in a cue sheet
+    // more than once are only decoded once
+    fn start_verify(&mut self) {
+        if self.verify.is_some() {
+            return;
+        }
+        let mut seen = HashSet::new();
+        let files = self
+            .music_list
             .iter()
-            .filter_map(|path| {
-                if let Ok(audiofile) = AudioFile::new(path) {
-                    Some(audiofile)
-                } else {
-                    None
+            .filter(|file| file.get_path().extension().is_some_and(|ext| ext == "flac"))
+            .filter(|file| seen.insert(file.get_path().to_path_buf()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let total = files.len();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for file in files {
+                let integrity_ok = match file.verify_flac() {
+                    Ok(ok) => Some(ok),
+                    Err(e) if e.kind() == io::ErrorKind::InvalidData => Some(false),
+                    Err(e) => {
+                        log!(
+                            Level::Warn,
+                            "couldn't verify {}: {}",
+                            file.get_path().display(),
+                            e
+                        );
+                        None
+                    }
+                };
+                if sender
+                    .send((file.get_path().to_path_buf(), integrity_ok))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+        self.verify = Some(Verification {
+            results: receiver,
+            done: 0,
+            total,
+        });
+        self.status = Some(format!("verifying {} FLAC files", total));
+    }
+
+    fn poll_verify(&mut self) {
+        let Some(verify) = &mut self.verify else {
+            return;
+        };
+        let mut finished = false;
+        let mut results = Vec::new();
+        loop {
+            match verify.results.try_recv() {
+                Ok(result) => {
+                    verify.done += 1;
+                    results.push(result);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        let (done, total) = (verify.done, verify.total);
+        if results.is_empty() && !finished {
+            return;
+        }
+        let mut cache = self.cache.borrow_mut();
+        for (path, integrity_ok) in results {
+            for file in self
+                .music_list
+                .iter_mut()
+                .filter(|file| file.get_path() == path)
+            {
+                file.set_integrity_ok(integrity_ok);
+                cache.update(file, |data| data.integrity_ok = integrity_ok);
+            }
+        }
+        if !finished {
+            self.status = Some(format!("verifying FLAC files {}/{}", done, total));
+            return;
+        }
+        if let Err(e) = cache.save() {
+            log!(Level::Warn, "saving the library cache failed: {}", e);
+        }
+        drop(cache);
+        self.verify = None;
+        let corrupt = self
+            .music_list
+            .iter()
+            .filter(|file| file.get_integrity_ok() == Some(false))
+            .count();
+        self.status = Some(format!(
+            "verified {} FLAC files, {} corrupt",
+            total, corrupt
+        ));
+    }
+
+    // takes in whatever the scan thread has sent since the last call
+    pub fn poll_scan(&mut self) {
+        let Some(scan) = &self.scan else {
+            return;
+        };
+        let mut done = None;
+        loop {
+            match scan.try_recv() {
+                Ok(ScanProgress::Scanning { total_found }) => self.scan_found = total_found,
+                Ok(ScanProgress::Done(music_list, scan_errors)) => {
+                    done = Some((music_list, scan_errors));
+                    break;
+                }
+                Err(TryRecvError::Empty) => return,
+                // the thread died without finishing, keep the empty library
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        self.scan = None;
+        if let Some((music_list, scan_errors)) = done {
+            self.finish_scan(music_list, scan_errors);
+        }
+    }
+
+    fn finish_scan(&mut self, music_list: Vec<AudioFile>, scan_errors: Vec<(PathBuf, String)>) {
+        let mut cache = self.cache.borrow_mut();
+        let mut music_list = music_list
+            .into_iter()
+            .filter(|audio_file| !cache.get(audio_file).hidden)
+            .map(|mut audio_file| {
+                cache.apply(&mut audio_file);
+                audio_file
+            })
+            .collect::<Vec<_>>();
+        cache.set_last_scan_total(music_list.len());
+        if let Err(e) = cache.save() {
+            log!(Level::Warn, "saving the library cache failed: {}", e);
+        }
+        drop(cache);
+        log!(
+            Level::Info,
+            "library scan found {} tracks",
+            music_list.len()
+        );
+        for (path, error) in &scan_errors {
+            log!(Level::Warn, "could not read {}: {}", path.display(), error);
+        }
+        let recently_added = if self.settings.borrow().show_recently_added {
+            recently_added(&music_list, self.settings.borrow().recently_added_days)
+        } else {
+            HashSet::new()
+        };
+        // stable, so the scan order is kept within both parts
+        music_list.sort_by_key(|file| !recently_added.contains(file.get_path()));
+        self.music_list = music_list;
+        self.recently_added = recently_added;
+        self.scan_errors = scan_errors;
+        self.library_changed = true;
+    }
+
+    fn draw_scan_progress(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        // the size of the last scan, there is nothing to go on the first time
+        let estimated_total = self.cache.borrow().get_last_scan_total();
+        let ratio = match estimated_total {
+            0 => 0.0,
+            total => (self.scan_found as f64 / total as f64).min(1.0),
+        };
+        let gauge = Gauge::default()
+            .block(
+                Block::default()
+                    .title(self.get_title())
+                    .borders(Borders::ALL),
+            )
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+            .label(format!(
+                "Scanning… {} tracks found",
+                with_thousands_separators(self.scan_found)
+            ))
+            .ratio(ratio);
+        f.render_widget(
+            gauge,
+            Rect {
+                height: area.height.min(3),
+                ..area
+            },
+        );
+    }
+
+    // reads the tags of every file, so only done when asked for
+    pub fn find_missing_artwork(&self) -> Vec<&AudioFile> {
+        self.music_list
+            .iter()
+            .filter(|file| file.get_album_art().is_none())
+            .collect()
+    }
+
+    // reading every file takes a while, so duplicates are looked for on a
+    // thread and the window opens once poll_duplicate_scan has the groups
+    fn start_duplicate_scan(&mut self) {
+        if self.duplicate_scan.is_some() {
+            return;
+        }
+        let files = self.music_list.clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(scan_duplicates(&files));
+        });
+        self.duplicate_scan = Some(receiver);
+        self.status = Some(String::from("looking for duplicates"));
+    }
+
+    fn poll_duplicate_scan(&mut self) {
+        let Some(duplicate_scan) = &self.duplicate_scan else {
+            return;
+        };
+        let groups = match duplicate_scan.try_recv() {
+            Ok(groups) => groups,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.duplicate_scan = None;
+                self.status = Some(String::from("looking for duplicates failed"));
+                return;
+            }
+        };
+        self.duplicate_scan = None;
+        self.status = None;
+        self.duplicates = Some(DuplicatesWindow::new(groups));
+    }
+
+    fn remove_duplicates(&mut self, resolution: Resolution) {
+        let mut cache = self.cache.borrow_mut();
+        let mut failed = 0;
+        for file in &resolution.remove {
+            self.music_list.retain(|other| other != file);
+            cache.update(file, |data| data.hidden = true);
+            // a cue track's file holds the rest of the album as well
+            let deletable = file.get_cue_offset_frames().is_none();
+            if resolution.delete_from_disk
+                && deletable
+                && (!is_separate_copy(resolution.keep.get_path(), file.get_path())
+                    || std::fs::remove_file(file.get_path()).is_err())
+            {
+                failed += 1;
+            }
+        }
+        if let Err(e) = cache.save() {
+            log!(Level::Warn, "saving the library cache failed: {}", e);
+        }
+        drop(cache);
+        self.library_changed = true;
+        let visible = self.visible_indices().len();
+        if self.state.selected().unwrap_or(0) >= visible {
+            self.state.select(Some(visible.saturating_sub(1)));
+        }
+        self.status = Some(if failed > 0 {
+            format!(
+                "removed {} copies, {} could not be deleted",
+                resolution.remove.len(),
+                failed
+            )
+        } else {
+            format!("removed {} copies", resolution.remove.len())
+        });
+    }
+
+    // indices into music_list of the tracks that pass the rating and quality filters
+    fn visible_indices(&self) -> Vec<usize> {
+        let mut visible = self
+            .music_list
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| self.rating_filter.matches(file))
+            .filter(|(_, file)| self.quality_filter.matches(file))
+            .filter(|(_, file)| match &self.album_context {
+                Some(context) => {
+                    *file.get_album() == context.album && *file.get_album_artist() == context.artist
                 }
+                None => true,
             })
-            .collect::<Vec<_>>()
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if self.album_context.is_some() {
+            visible.sort_by_key(|&i| self.music_list[i].album_position());
+        }
+        visible
+    }
+
+    pub fn selected_track(&self) -> Option<AudioFile> {
+        let visible = self.visible_indices();
+        let &i = self.state.selected().and_then(|i| visible.get(i))?;
+        Some(self.music_list[i].clone())
+    }
+
+    pub fn set_album_context(&mut self, album: &str, artist: &str) {
+        self.album_context = Some(AlbumContext {
+            album: album.to_string(),
+            artist: artist.to_string(),
+        });
+        self.view = LibraryView::Tracks;
+        self.state.select(Some(0));
+        self.visible_window_start = 0;
+    }
+
+    pub fn clear_album_context(&mut self) {
+        if self.album_context.take().is_some() {
+            self.state.select(Some(0));
+            self.visible_window_start = 0;
+        }
+    }
+
+    // "Track N of M in <Album>" for the selected track, while an album is open
+    pub fn album_context_label(&self) -> Option<String> {
+        let context = self.album_context.as_ref()?;
+        let len = self.visible_indices().len();
+        let selected = self
+            .state
+            .selected()
+            .unwrap_or(0)
+            .min(len.saturating_sub(1));
+        Some(format!(
+            "Track {} of {} in {}",
+            selected + 1,
+            len,
+            context.album
+        ))
+    }
+
+    fn visible_tracks(&self) -> Vec<AudioFile> {
+        self.visible_indices()
+            .into_iter()
+            .map(|i| self.music_list[i].clone())
+            .collect()
+    }
+
+    // the visible tracks with a separator in front of the recently added ones,
+    // which are always at the start of music_list
+    fn table_rows(&self, visible: &[usize]) -> Vec<TableRow> {
+        let mut rows = Vec::with_capacity(visible.len() + 1);
+        if visible
+            .first()
+            .is_some_and(|&i| self.recently_added.contains(self.music_list[i].get_path()))
+        {
+            rows.push(TableRow::Separator(format!(
+                "── Added in the last {} days ──",
+                self.settings.borrow().recently_added_days
+            )));
+        }
+        rows.extend(visible.iter().map(|&i| TableRow::Track(i)));
+        rows
     }
 
     pub fn next(&mut self) {
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.music_list.len() - 1 {
+                if i + 1 >= self.visible_indices().len() {
                     0
                 } else {
                     i + 1
@@ -71,8 +758,8 @@ impl LibraryWindow {
         self.state.select(Some(i));
     }
 
-    fn get_wrapped_music_list(&self, i: usize) -> Vec<AudioFile> {
-        let get_music_list = self.music_list.clone();
+    fn get_wrapped_music_list(music_list: &[AudioFile], i: usize) -> Vec<AudioFile> {
+        let get_music_list = music_list.to_vec();
         // split the list in two at the index i given:
         let first_half = get_music_list.get(0..i).unwrap_or(&[]);
         let second_half = get_music_list.get(i..).unwrap_or(&[]);
@@ -87,7 +774,7 @@ impl LibraryWindow {
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.music_list.len() - 1
+                    self.visible_indices().len().saturating_sub(1)
                 } else {
                     i - 1
                 }
@@ -96,6 +783,743 @@ impl LibraryWindow {
         };
         self.state.select(Some(i));
     }
+
+    // scans the folder again on a thread, to pick up files added since the
+    // library was scanned and drop the ones that were deleted. returns false
+    // while another scan is still running
+    pub fn rescan_folder(&mut self, folder: &Path) -> bool {
+        if self.scan.is_some() || self.rescan.is_some() {
+            return false;
+        }
+        let scan = LibraryWindow::scan_folders(&self.settings.borrow(), vec![folder.to_path_buf()]);
+        self.rescan = Some((folder.to_path_buf(), scan));
+        true
+    }
+
+    // called every tick by the ui, so scans are picked up whichever tab is shown
+    pub fn poll_scans(&mut self) {
+        if self.needs_refresh.swap(false, Ordering::Relaxed) {
+            self.reload_from_settings();
+        }
+        self.poll_scan();
+        self.poll_rescan();
+    }
+
+    pub fn take_library_changed(&mut self) -> bool {
+        std::mem::take(&mut self.library_changed)
+    }
+
+    fn poll_rescan(&mut self) {
+        let Some((folder, scan)) = &self.rescan else {
+            return;
+        };
+        let done = loop {
+            match scan.try_recv() {
+                Ok(ScanProgress::Scanning { .. }) => {}
+                Ok(ScanProgress::Done(music_list, scan_errors)) => {
+                    break Some((music_list, scan_errors))
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break None,
+            }
+        };
+        let folder = folder.clone();
+        self.rescan = None;
+        match done {
+            Some((music_list, scan_errors)) => self.finish_rescan(folder, music_list, scan_errors),
+            None => log!(Level::Error, "rescanning {} failed", folder.display()),
+        }
+    }
+
+    fn finish_rescan(
+        &mut self,
+        folder: PathBuf,
+        music_list: Vec<AudioFile>,
+        scan_errors: Vec<(PathBuf, String)>,
+    ) {
+        let track_key =
+            |file: &AudioFile| (file.get_path().to_path_buf(), file.get_cue_offset_frames());
+        let cache = self.cache.borrow();
+        let scanned = music_list
+            .into_iter()
+            .filter(|audio_file| !cache.get(audio_file).hidden)
+            .map(|mut audio_file| {
+                cache.apply(&mut audio_file);
+                audio_file
+            })
+            .collect::<Vec<_>>();
+        drop(cache);
+        let scanned_keys = scanned.iter().map(track_key).collect::<HashSet<_>>();
+        let before = self.music_list.len();
+        self.music_list.retain(|file| {
+            !file.get_path().starts_with(&folder) || scanned_keys.contains(&track_key(file))
+        });
+        let removed = before - self.music_list.len();
+        let known = self
+            .music_list
+            .iter()
+            .map(track_key)
+            .collect::<HashSet<_>>();
+        let added = scanned
+            .into_iter()
+            .filter(|file| !known.contains(&track_key(file)))
+            .collect::<Vec<_>>();
+        let added_count = added.len();
+        self.music_list.extend(added);
+        for (path, error) in &scan_errors {
+            log!(Level::Warn, "could not read {}: {}", path.display(), error);
+        }
+        self.scan_errors
+            .retain(|(path, _)| !path.starts_with(&folder));
+        self.scan_errors.extend(scan_errors);
+        let visible = self.visible_indices().len();
+        if self.state.selected().unwrap_or(0) >= visible {
+            self.state.select(Some(visible.saturating_sub(1)));
+        }
+        log!(
+            Level::Info,
+            "rescanned {}, {} added, {} removed",
+            folder.display(),
+            added_count,
+            removed
+        );
+        self.rescan_result = Some((folder, added_count, removed));
+        self.library_changed = true;
+    }
+
+    pub fn take_rescan_result(&mut self) -> Option<(PathBuf, usize, usize)> {
+        self.rescan_result.take()
+    }
+
+    pub fn get_music_list(&self) -> &Vec<AudioFile> {
+        &self.music_list
+    }
+
+    // crossfades from whatever is playing into the selected track, the rest
+    // of the list follows it in the queue
+    fn play_from(&self, music_list: &[AudioFile], i: usize) {
+        let mut wrapped_music_list = LibraryWindow::get_wrapped_music_list(music_list, i);
+        if wrapped_music_list.is_empty() {
+            return;
+        }
+        let first = wrapped_music_list.remove(0);
+        let mut audio_interface = self.audio_interface.borrow_mut();
+        audio_interface.clear_queue();
+        audio_interface.crossfade_to(&first);
+        audio_interface.append_to_queue(&mut wrapped_music_list);
+    }
+
+    fn handle_tracks_input(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Up => self.previous(),
+            KeyCode::Down => self.next(),
+            KeyCode::Enter => {
+                if let Some(i) = self.state.selected() {
+                    self.play_from(&self.visible_tracks(), i);
+                }
+            }
+            KeyCode::Char(c @ '0'..='5') => {
+                let stars = c.to_digit(10).unwrap() as u8;
+                self.rate_selected(if stars == 0 { None } else { Some(stars) });
+            }
+            KeyCode::Char('o') => {
+                let sort_column = match self.sort_column {
+                    Some(sort_column) => sort_column.next(),
+                    None => SortColumn::Title,
+                };
+                self.sort_column = Some(sort_column);
+                let recently_added = &self.recently_added;
+                self.music_list.sort_by(|a, b| {
+                    // recently added tracks stay on top
+                    let a_recent = recently_added.contains(a.get_path());
+                    let b_recent = recently_added.contains(b.get_path());
+                    b_recent
+                        .cmp(&a_recent)
+                        .then_with(|| sort_column.compare(a, b))
+                });
+            }
+            KeyCode::Char('f') => {
+                self.rating_filter = self.rating_filter.next();
+                self.state.select(Some(0));
+            }
+            KeyCode::Char('Q') => {
+                self.quality_filter = self.quality_filter.next();
+                self.state.select(Some(0));
+            }
+            KeyCode::Char('F') => self.fix_selected_tags(),
+            KeyCode::Char('b') => self.detect_selected_bpm(),
+            KeyCode::Char('e') => self.edit_selected_tags(),
+            KeyCode::Char('E') => self.export = Some(ExportPrompt::Format(ExportFormat::Csv)),
+            KeyCode::Char('D') => self.start_duplicate_scan(),
+            KeyCode::Char('V') => self.start_verify(),
+            KeyCode::Char('A') => {
+                self.missing_art = Some(MissingArtWindow::new(&self.find_missing_artwork()));
+            }
+            KeyCode::Tab => self.show_info = !self.show_info,
+            KeyCode::Char('w') => {
+                let mut settings = self.settings.borrow_mut();
+                settings.word_wrap = !settings.word_wrap;
+            }
+            KeyCode::Char('U') if !self.scan_errors.is_empty() => {
+                let mut state = ListState::default();
+                state.select(Some(0));
+                self.scan_errors_state = Some(state);
+            }
+            _ => {}
+        }
+    }
+
+    fn rate_selected(&mut self, rating: Option<u8>) {
+        let visible = self.visible_indices();
+        if let Some(&i) = self.state.selected().and_then(|i| visible.get(i)) {
+            self.music_list[i].set_rating(rating);
+            let mut cache = self.cache.borrow_mut();
+            cache.update(&self.music_list[i], |data| data.rating = rating);
+            if let Err(e) = cache.save() {
+                log!(Level::Warn, "saving the library cache failed: {}", e);
+            }
+        }
+    }
+
+    fn fix_selected_tags(&mut self) {
+        let visible = self.visible_indices();
+        let Some(&i) = self.state.selected().and_then(|i| visible.get(i)) else {
+            return;
+        };
+        let file = &mut self.music_list[i];
+        if file.get_stream_url().is_some() || file.get_cue_offset_frames().is_some() {
+            self.status = Some(String::from("can only look up whole local files"));
+            return;
+        }
+        self.status = Some(match file.lookup_and_fill_tags() {
+            Ok(()) => {
+                let tags = FixedTags {
+                    title: file.get_title().clone(),
+                    artist: file.get_artist().clone(),
+                    album: file.get_album().clone(),
+                };
+                let mut cache = self.cache.borrow_mut();
+                cache.update(file, |data| data.tags = Some(tags));
+                if let Err(e) = cache.save() {
+                    log!(Level::Warn, "saving the library cache failed: {}", e);
+                }
+                format!("tagged {}", file.get_title())
+            }
+            Err(e) => e.to_string(),
+        });
+    }
+
+    // analysing takes a moment, the ui waits for it like it does for tag lookups
+    fn detect_selected_bpm(&mut self) {
+        let visible = self.visible_indices();
+        let Some(&i) = self.state.selected().and_then(|i| visible.get(i)) else {
+            return;
+        };
+        let file = &mut self.music_list[i];
+        if file.get_stream_url().is_some() {
+            return;
+        }
+        match bpm::detect(file) {
+            Ok(bpm) => {
+                file.set_bpm(Some(bpm));
+                let mut cache = self.cache.borrow_mut();
+                cache.update(file, |data| data.bpm = Some(bpm));
+                if let Err(e) = cache.save() {
+                    log!(Level::Warn, "saving the library cache failed: {}", e);
+                }
+                // a cue track's file holds other tracks with their own tempo
+                let written = match file.get_cue_offset_frames() {
+                    Some(_) => Ok(()),
+                    None => bpm::write_tag(file.get_path(), bpm),
+                };
+                self.status = Some(match written {
+                    Ok(()) => format!("{:.0} BPM", bpm),
+                    Err(e) => format!("{:.0} BPM, tag not written: {}", bpm, e),
+                });
+            }
+            Err(e) => self.status = Some(format!("BPM detection failed: {}", e)),
+        }
+    }
+
+    fn edit_selected_tags(&mut self) {
+        let visible = self.visible_indices();
+        let Some(&i) = self.state.selected().and_then(|i| visible.get(i)) else {
+            return;
+        };
+        let file = &self.music_list[i];
+        if file.get_stream_url().is_some() || file.get_cue_offset_frames().is_some() {
+            self.status = Some(String::from("can only edit whole local files"));
+            return;
+        }
+        match EditTagsWindow::new(file) {
+            Ok(editor) => self.editor = Some((i, editor)),
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    fn handle_editor_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        let Some((i, editor)) = &mut self.editor else {
+            return Ok(());
+        };
+        match key {
+            KeyCode::Enter => {
+                let recording = editor
+                    .recording_id()
+                    .map(|id| (editor.get_path().clone(), id.to_string()));
+                let saved = editor.save();
+                self.status = Some(match &saved {
+                    Ok(()) => {
+                        let file = &mut self.music_list[*i];
+                        editor.apply(file);
+                        // the file's own tags are right now, drop any looked up ones
+                        let mut cache = self.cache.borrow_mut();
+                        cache.update(file, |data| {
+                            data.tags = None;
+                            data.start_offset_secs = file.get_start_offset_secs();
+                        });
+                        if let Err(e) = cache.save() {
+                            log!(Level::Warn, "saving the library cache failed: {}", e);
+                        }
+                        format!("saved tags for {}", file.get_title())
+                    }
+                    Err(e) => format!("failed to save tags: {}", e),
+                });
+                self.editor = None;
+                if let (Ok(()), Some((path, id))) = (saved, recording) {
+                    self.offer_submission(&path, id);
+                }
+            }
+            KeyCode::Esc => self.editor = None,
+            _ => editor.handle_input(key)?,
+        }
+        Ok(())
+    }
+
+    // a recording id entered by hand is only worth sending if acoustid
+    // doesn't already know the file
+    fn offer_submission(&mut self, path: &Path, recording_id: String) {
+        let saved = self.status.take().unwrap_or_default();
+        match acoustid::unmatched(path) {
+            Ok(Some(fingerprint)) => {
+                self.status = Some(saved);
+                self.submission = Some((recording_id, fingerprint));
+            }
+            Ok(None) => self.status = Some(format!("{}, already on AcoustID", saved)),
+            Err(e) => self.status = Some(format!("{}, {}", saved, e)),
+        }
+    }
+
+    fn handle_submission_input(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char('y') => {
+                let Some((recording_id, fingerprint)) = self.submission.take() else {
+                    return;
+                };
+                let user_key = self
+                    .settings
+                    .borrow()
+                    .acoustid_user_key
+                    .clone()
+                    .unwrap_or_default();
+                self.status = Some(
+                    match acoustid::submit(
+                        &fingerprint.fingerprint,
+                        fingerprint.duration,
+                        &recording_id,
+                        &user_key,
+                    ) {
+                        Ok(()) => String::from("fingerprint submitted to AcoustID"),
+                        Err(e) => e.to_string(),
+                    },
+                );
+            }
+            KeyCode::Char('n') | KeyCode::Esc => self.submission = None,
+            _ => {}
+        }
+    }
+
+    // cached bios show straight away, others are fetched in the background
+    fn open_artist_bio(&mut self, artist: String) {
+        if let Some(bio) = self.cache.borrow().get_artist_bio(&artist) {
+            self.artist_bio = Some(ArtistBio {
+                artist,
+                bio: Some(bio.clone()),
+                receiver: None,
+            });
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let name = artist.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(bio::fetch_artist_bio(&name));
+        });
+        self.artist_bio = Some(ArtistBio {
+            artist,
+            bio: None,
+            receiver: Some(receiver),
+        });
+    }
+
+    fn poll_artist_bio(&mut self) {
+        let Some(artist_bio) = &mut self.artist_bio else {
+            return;
+        };
+        let Some(receiver) = &artist_bio.receiver else {
+            return;
+        };
+        let fetched = match receiver.try_recv() {
+            Ok(fetched) => fetched,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                artist_bio.receiver = None;
+                return;
+            }
+        };
+        artist_bio.receiver = None;
+        artist_bio.bio = Some(match fetched {
+            Ok(bio) => {
+                let mut cache = self.cache.borrow_mut();
+                cache.set_artist_bio(&artist_bio.artist, bio.clone());
+                if let Err(e) = cache.save() {
+                    log!(Level::Warn, "saving the library cache failed: {}", e);
+                }
+                bio
+            }
+            // not cached, so it is tried again the next time
+            Err(e) => format!("Couldn't fetch the biography: {}", e),
+        });
+    }
+
+    fn draw_artist_bio(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let Some(artist_bio) = &self.artist_bio else {
+            return;
+        };
+        let text = match artist_bio.bio.as_deref() {
+            None => "Fetching from MusicBrainz…",
+            Some("") => "No biography found",
+            Some(bio) => bio,
+        };
+        let title = format!("{} - b or Esc to close", artist_bio.artist);
+        let bio = Paragraph::new(text)
+            .style(Style::default().fg(Color::Yellow))
+            .wrap(Wrap { trim: true });
+        render_popup(f, centered_rect(70, 60, area), &title, |inner, f| {
+            f.render_widget(bio, inner)
+        });
+    }
+
+    fn draw_submission_prompt(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let Some((recording_id, _)) = &self.submission else {
+            return;
+        };
+        let prompt = Paragraph::new(vec![
+            Line::from("AcoustID has no match for this file."),
+            Line::from(format!(
+                "Submit its fingerprint for recording {}? (y/n)",
+                recording_id
+            )),
+        ])
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true });
+        render_popup(
+            f,
+            centered_rect(60, 20, area),
+            "Submit Fingerprint",
+            |inner, f| f.render_widget(prompt, inner),
+        );
+    }
+
+    pub fn export_to_csv(&self, path: &Path) -> Result<(), io::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record([
+            "Title",
+            "Artist",
+            "Album",
+            "Year",
+            "Duration",
+            "Path",
+            "PlayCount",
+            "Rating",
+        ])?;
+        for file in &self.music_list {
+            writer.write_record([
+                file.get_title().clone(),
+                file.get_artist().clone(),
+                file.get_album().clone(),
+                file.get_year().to_string(),
+                file.get_raw_duration().to_string(),
+                file.get_path().display().to_string(),
+                file.get_play_count().to_string(),
+                file.get_rating()
+                    .map(|rating| rating.to_string())
+                    .unwrap_or_default(),
+            ])?;
+        }
+        writer.flush()
+    }
+
+    pub fn export_to_json(&self, path: &Path) -> Result<(), io::Error> {
+        let contents = serde_json::to_string_pretty(&self.music_list)?;
+        std::fs::write(path, contents)
+    }
+
+    fn handle_export_input(&mut self, key: KeyCode) {
+        let Some(prompt) = &mut self.export else {
+            return;
+        };
+        match (prompt, key) {
+            (_, KeyCode::Esc) => self.export = None,
+            (ExportPrompt::Format(format), KeyCode::Up | KeyCode::Down) => {
+                *format = match format {
+                    ExportFormat::Csv => ExportFormat::Json,
+                    ExportFormat::Json => ExportFormat::Csv,
+                };
+            }
+            (ExportPrompt::Format(format), KeyCode::Enter) => {
+                let format = *format;
+                self.export = Some(ExportPrompt::Path(format, format.default_path()));
+            }
+            (ExportPrompt::Path(_, path), KeyCode::Char(c)) => path.push(c),
+            (ExportPrompt::Path(_, path), KeyCode::Backspace) => {
+                path.pop();
+            }
+            (ExportPrompt::Path(format, path), KeyCode::Enter) => {
+                let path = PathBuf::from(path.as_str());
+                let result = match format {
+                    ExportFormat::Csv => self.export_to_csv(&path),
+                    ExportFormat::Json => self.export_to_json(&path),
+                };
+                self.status = Some(match result {
+                    Ok(()) => format!("exported to {}", path.display()),
+                    Err(e) => format!("export failed: {}", e),
+                });
+                self.export = None;
+            }
+            _ => {}
+        }
+    }
+
+    // metadata of the selected track, next to the table
+    fn draw_info(&mut self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let block = Block::default().title("Track Info").borders(Borders::ALL);
+        let visible = self.visible_indices();
+        let Some(&i) = self.state.selected().and_then(|i| visible.get(i)) else {
+            let placeholder = Paragraph::new("No track selected")
+                .block(block)
+                .style(Style::default().fg(Color::Green));
+            f.render_widget(placeholder, area);
+            return;
+        };
+        let file = &self.music_list[i];
+        let art = match &self.info_art {
+            Some((path, art)) if path == file.get_path() => art.clone(),
+            _ => {
+                let art = file
+                    .cached_album_art()
+                    .and_then(|art| braille::render(&art, INFO_ART_WIDTH, INFO_ART_HEIGHT));
+                self.info_art = Some((file.get_path().to_path_buf(), art.clone()));
+                art
+            }
+        };
+        let play_count = self.cache.borrow().get(file).play_count;
+        let mut lines = vec![
+            Line::from(format!("Title: {}", file.get_title())),
+            Line::from(format!("Artist: {}", file.get_artist())),
+            Line::from(format!("Album Artist: {}", file.get_album_artist())),
+            Line::from(format!("Album: {}", file.get_album())),
+            Line::from(format!("Year: {}", file.get_year())),
+            Line::from(format!("Genre: {}", file.get_genre())),
+            Line::from(format!("Length: {}", file.get_duration())),
+            Line::from(format!("Rating: {}", rating_stars(file.get_rating()))),
+            Line::from(format!("Play Count: {}", play_count)),
+            Line::from(format!(
+                "Lyrics: {}",
+                if file.get_lyrics().is_some() {
+                    "Yes"
+                } else {
+                    "No"
+                }
+            )),
+            Line::from(format!("Path: {}", file.get_path().display())),
+        ];
+        if let Some(offset) = file.get_cue_offset_frames() {
+            lines.push(Line::from(format!(
+                "Cue Offset: {}",
+                seconds_to_formatted_time((offset as f64 / cue::FRAMES_PER_SECOND) as usize)
+            )));
+        }
+        if let Some(art) = art {
+            lines.push(Line::from(""));
+            lines.extend(art.into_iter().map(Line::from));
+        }
+        let info = Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().fg(Color::Green))
+            .wrap(Wrap { trim: false });
+        f.render_widget(info, area);
+    }
+
+    fn draw_scan_errors(&mut self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let Some(state) = &mut self.scan_errors_state else {
+            return;
+        };
+        let errors = self
+            .scan_errors
+            .iter()
+            .map(|(path, error)| {
+                ListItem::new(vec![
+                    Line::from(path.display().to_string()),
+                    Line::from(Span::styled(
+                        format!("  {}", error),
+                        Style::default().fg(Color::Red),
+                    )),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let title = format!(
+            "{} files could not be read - Esc to close",
+            self.scan_errors.len()
+        );
+        let errors_widget = List::new(errors)
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        render_popup(f, centered_rect(80, 60, area), &title, |inner, f| {
+            f.render_stateful_widget(errors_widget, inner, state)
+        });
+    }
+
+    fn handle_scan_errors_input(&mut self, key: KeyCode) {
+        let len = self.scan_errors.len();
+        let Some(state) = &mut self.scan_errors_state else {
+            return;
+        };
+        let selected = state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => state.select(Some(if selected == 0 { len - 1 } else { selected - 1 })),
+            KeyCode::Down => state.select(Some((selected + 1) % len)),
+            KeyCode::Esc | KeyCode::Char('U') => self.scan_errors_state = None,
+            _ => {}
+        }
+    }
+
+    fn draw_export_prompt(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let Some(prompt) = &self.export else {
+            return;
+        };
+        let (title, lines) = match prompt {
+            ExportPrompt::Format(selected) => (
+                "Export Library - Up/Down to choose, Enter to continue",
+                [ExportFormat::Csv, ExportFormat::Json]
+                    .iter()
+                    .map(|format| {
+                        let style = if format == selected {
+                            Style::default().fg(Color::Yellow)
+                        } else {
+                            Style::default()
+                        };
+                        Line::from(Span::styled(format.name(), style))
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            ExportPrompt::Path(_, path) => (
+                "Export Library - Enter to save, Esc to cancel",
+                vec![Line::from(Span::styled(
+                    format!("{}_", path),
+                    Style::default().fg(Color::Yellow),
+                ))],
+            ),
+        };
+        let prompt_widget = Paragraph::new(lines).style(Style::default().fg(Color::Green));
+        render_popup(f, centered_rect(60, 20, area), title, |inner, f| {
+            f.render_widget(prompt_widget, inner)
+        });
+    }
+
+    fn browse(&mut self, group_by: GroupBy) {
+        self.view = LibraryView::Browse(group_by);
+        self.browse_pane = 0;
+        self.group_state.select(Some(0));
+        self.group_track_state.select(Some(0));
+    }
+
+    fn get_groups(&self, group_by: GroupBy) -> Vec<String> {
+        let mut groups = self
+            .music_list
+            .iter()
+            .map(|file| group_by.key(file).clone())
+            .collect::<Vec<_>>();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+
+    fn get_group_tracks(&self) -> Vec<AudioFile> {
+        let LibraryView::Browse(group_by) = self.view else {
+            return Vec::new();
+        };
+        let groups = self.get_groups(group_by);
+        match self.group_state.selected().and_then(|i| groups.get(i)) {
+            Some(group) => self
+                .music_list
+                .iter()
+                .filter(|file| group_by.key(file) == group)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn handle_browse_input(&mut self, key: KeyCode) {
+        let len = match (self.browse_pane, self.view) {
+            (0, LibraryView::Browse(group_by)) => self.get_groups(group_by).len(),
+            _ => self.get_group_tracks().len(),
+        };
+        let selected = match self.browse_pane {
+            0 => self.group_state.selected(),
+            _ => self.group_track_state.selected(),
+        }
+        .unwrap_or(0);
+        let select = match key {
+            KeyCode::Up if selected == 0 => len.saturating_sub(1),
+            KeyCode::Up => selected - 1,
+            KeyCode::Down if selected + 1 >= len => 0,
+            KeyCode::Down => selected + 1,
+            KeyCode::Left => {
+                self.browse_pane = 0;
+                return;
+            }
+            KeyCode::Right => {
+                self.browse_pane = 1;
+                return;
+            }
+            KeyCode::Enter if self.browse_pane == 0 => {
+                self.browse_pane = 1;
+                return;
+            }
+            KeyCode::Enter => {
+                let tracks = self.get_group_tracks();
+                if selected < tracks.len() {
+                    self.play_from(&tracks, selected);
+                }
+                return;
+            }
+            KeyCode::Char('b') if matches!(self.view, LibraryView::Browse(GroupBy::Artist)) => {
+                let groups = self.get_groups(GroupBy::Artist);
+                if let Some(artist) = self.group_state.selected().and_then(|i| groups.get(i)) {
+                    self.open_artist_bio(artist.clone());
+                }
+                return;
+            }
+            _ => return,
+        };
+        if self.browse_pane == 0 {
+            self.group_state.select(Some(select));
+            self.group_track_state.select(Some(0));
+        } else {
+            self.group_track_state.select(Some(select));
+        }
+    }
 }
 
 impl Window for LibraryWindow {
@@ -108,162 +1532,719 @@ impl Window for LibraryWindow {
         area: Rect,
         f: &mut Frame<CrosstermBackend<Stdout>>,
     ) -> Result<(), io::Error> {
-        let mut table_widget_vec = Vec::new();
-        for file in self.music_list.iter() {
-            table_widget_vec.push(Row::new(vec![
-                file.get_title().clone(),
-                file.get_artist().clone(),
-                file.get_album().clone(),
-                file.get_year().to_string(),
-                file.get_duration(),
-            ]))
+        self.poll_scans();
+        self.poll_verify();
+        self.poll_duplicate_scan();
+        self.poll_artist_bio();
+        if self.is_scanning() {
+            self.draw_scan_progress(area, f);
+            return Ok(());
+        }
+        if let Some(duplicates) = &mut self.duplicates {
+            return duplicates.draw(area, f);
         }
-        match self.audio_interface.borrow().get_currently_playing() {
-            Some(track) => {
-                let index = self
-                    .music_list
+        if let Some(missing_art) = &mut self.missing_art {
+            return missing_art.draw(area, f);
+        }
+        match self.view {
+            LibraryView::Tracks => {
+                let (area, info_area) = if self.show_info {
+                    let panes = tui::layout::Layout::default()
+                        .direction(tui::layout::Direction::Horizontal)
+                        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+                        .split(area);
+                    (panes[0], Some(panes[1]))
+                } else {
+                    (area, None)
+                };
+                // only the rows that fit on screen are built, minus the border and header
+                let available_rows = area.height.saturating_sub(3).max(1) as usize;
+                let visible = self.visible_indices();
+                let rows = self.table_rows(&visible);
+                // the selection counts tracks only, skip over the separator
+                let separators = rows.len() - visible.len();
+                let selected = self.state.selected().unwrap_or(0) + separators;
+                if selected < self.visible_window_start {
+                    self.visible_window_start = selected;
+                } else if selected >= self.visible_window_start + available_rows {
+                    self.visible_window_start = selected + 1 - available_rows;
+                }
+                // keep the separator in view while the first track is selected
+                if selected == separators {
+                    self.visible_window_start = 0;
+                }
+                let start = self.visible_window_start.min(rows.len());
+                // one extra row of overdraw
+                let end = (start + available_rows + 1).min(rows.len());
+                let mut columns = self.settings.borrow().library_columns.clone();
+                if self.sort_column == Some(SortColumn::Album) {
+                    columns = with_track_number_column(&columns);
+                }
+                let widths = column_widths(&columns);
+                let word_wrap = self.settings.borrow().word_wrap;
+                let audio_interface = self.audio_interface.borrow();
+                let table_widget_vec = rows[start..end]
                     .iter()
-                    .position(|x| x.get_path() == track.get_path())
-                    .unwrap();
-                table_widget_vec[index] = Row::new(vec![
-                    track.get_title().clone(),
-                    track.get_artist().clone(),
-                    track.get_album().clone(),
-                    track.get_year().to_string(),
-                    track.get_duration(),
-                ])
-                .style(
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
+                    .map(|row| match row {
+                        TableRow::Separator(label) => Row::new(vec![label.clone()])
+                            .style(Style::default().fg(Color::LightGreen)),
+                        TableRow::Track(i) if word_wrap => wrapped_track_row(
+                            &self.music_list[*i],
+                            audio_interface.get_currently_playing(),
+                            &columns,
+                            area.width.saturating_sub(2),
+                        ),
+                        TableRow::Track(i) => track_row(
+                            &self.music_list[*i],
+                            audio_interface.get_currently_playing(),
+                            &columns,
+                        ),
+                    })
+                    .collect::<Vec<_>>();
+                let mut title = String::from("Music Found");
+                if let Some(sort_column) = self.sort_column {
+                    title = format!("{} - sorted by {}", title, sort_column.name());
+                }
+                if self.rating_filter != RatingFilter::All {
+                    title = format!("{} - {}", title, self.rating_filter.name());
+                }
+                if self.quality_filter != QualityFilter::All {
+                    title = format!("{} - {}", title, self.quality_filter.name());
+                }
+                if let Some(context) = &self.album_context {
+                    title = format!("{} - {} (Esc for all)", title, context.album);
+                }
+                if let Some(status) = &self.status {
+                    title = format!("{} - {}", title, status);
+                }
+                if !self.scan_errors.is_empty() {
+                    title = format!("{} [U: {} errors]", title, self.scan_errors.len());
+                }
+                let table_widget = track_table(table_widget_vec, title.as_str(), &columns, &widths);
+                let mut window_state = TableState::default();
+                window_state.select(Some(selected - start));
+                f.render_stateful_widget(table_widget, area, &mut window_state);
+                drop(audio_interface);
+                if let Some(info_area) = info_area {
+                    self.draw_info(info_area, f);
+                }
+            }
+            LibraryView::Browse(group_by) => {
+                let panes = tui::layout::Layout::default()
+                    .direction(tui::layout::Direction::Horizontal)
+                    .constraints(
+                        [
+                            tui::layout::Constraint::Percentage(25),
+                            tui::layout::Constraint::Percentage(75),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(area);
+                let groups = self
+                    .get_groups(group_by)
+                    .into_iter()
+                    .map(ListItem::new)
+                    .collect::<Vec<_>>();
+                let group_list = List::new(groups)
+                    .block(
+                        Block::default()
+                            .title(group_by.title())
+                            .borders(Borders::ALL)
+                            .border_style(focus_border_style(self.browse_pane == 0)),
+                    )
+                    .style(Style::default().fg(Color::Green))
+                    .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+                    .highlight_symbol(">> ");
+                let columns = self.settings.borrow().library_columns.clone();
+                let widths = column_widths(&columns);
+                let audio_interface = self.audio_interface.borrow();
+                let tracks = self
+                    .get_group_tracks()
+                    .iter()
+                    .map(|file| track_row(file, audio_interface.get_currently_playing(), &columns))
+                    .collect::<Vec<_>>();
+                let track_widget = track_table(tracks, "Tracks", &columns, &widths).block(
+                    Block::default()
+                        .title("Tracks")
+                        .borders(Borders::ALL)
+                        .border_style(focus_border_style(self.browse_pane == 1)),
                 );
+                f.render_stateful_widget(group_list, panes[0], &mut self.group_state);
+                f.render_stateful_widget(track_widget, panes[1], &mut self.group_track_state);
             }
-            None => {}
         }
-        let chunks = tui::layout::Layout::default()
-            .direction(tui::layout::Direction::Vertical)
-            .constraints(
-                [
-                    tui::layout::Constraint::Percentage(95),
-                    tui::layout::Constraint::Percentage(5),
-                ]
-                .as_ref(),
-            )
-            .split(area);
-        let table_widget = Table::new(table_widget_vec)
-            .block(Block::default().title("Music Found").borders(Borders::ALL))
-            .style(Style::default().fg(Color::Green))
-            .highlight_style(
-                Style::default()
-                    .add_modifier(Modifier::BOLD)
-                    .bg(Color::Green)
-                    .fg(Color::White),
-            )
-            .header(
-                Row::new(vec!["Title", "Artist", "Album", "Year", "Length"])
-                    .style(Style::default().fg(Color::Yellow)),
-            )
-            .widths(&[
-                Constraint::Percentage(30),
-                Constraint::Percentage(30),
-                Constraint::Percentage(30),
-                Constraint::Percentage(5),
-                Constraint::Percentage(5),
-            ]);
-        let progress_bar = tui::widgets::Gauge::default()
-            .block(Block::default().borders(Borders::ALL))
-            .style(Style::default().fg(Color::Green).bg(Color::Black))
-            .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
-            .label(
-                match self.audio_interface.borrow().get_currently_playing() {
-                    Some(audiofile) => match self.audio_interface.borrow().get_paused() {
-                        true => {
-                            format!(
-                                "⋫ {} - {} - {} / {} ⋪",
-                                audiofile.get_artist(),
-                                audiofile.get_title(),
-                                seconds_to_formatted_time(
-                                    self.audio_interface.borrow().get_sink_length()
-                                ),
-                                audiofile.get_duration()
-                            )
-                        }
-                        false => {
-                            format!(
-                                "► {} - {} - {} / {} ◄",
-                                audiofile.get_artist(),
-                                audiofile.get_title(),
-                                seconds_to_formatted_time(
-                                    self.audio_interface.borrow().get_sink_length()
-                                ),
-                                audiofile.get_duration()
-                            )
-                        }
-                    },
-                    None => "Nothing Playing".to_string(),
-                },
-            )
-            .ratio(
-                match self.audio_interface.borrow().get_currently_playing() {
-                    Some(audiofile) => {
-                        let ratio = self.audio_interface.borrow().get_sink_length() as f64
-                            / audiofile.get_raw_duration();
-                        if ratio < 1.0 && ratio > 0.0 {
-                            ratio
-                        } else {
-                            0.0
-                        }
-                    }
-                    None => 0.0,
-                },
-            );
-        f.render_stateful_widget(table_widget, chunks[0], &mut self.state);
-        f.render_widget(progress_bar, chunks[1]);
+        if let Some((_, editor)) = &mut self.editor {
+            editor.draw(centered_rect(60, 40, area), f)?;
+        }
+        self.draw_export_prompt(area, f);
+        self.draw_scan_errors(area, f);
+        self.draw_submission_prompt(area, f);
+        self.draw_artist_bio(area, f);
         Ok(())
     }
 
+    fn is_typing(&self) -> bool {
+        self.editor.is_some()
+            || self.export.is_some()
+            || self.duplicates.is_some()
+            || self.missing_art.is_some()
+            || self.scan_errors_state.is_some()
+            || self.submission.is_some()
+            || self.artist_bio.is_some()
+    }
+
+    // keeps the progress bar moving smoothly
+    fn tick_rate(&self) -> Duration {
+        Duration::from_millis(100)
+    }
+
     fn handle_input(&mut self, key: crossterm::event::KeyCode) -> Result<(), io::Error> {
-        match key {
-            KeyCode::Up => self.previous(),
-            KeyCode::Down => self.next(),
-            KeyCode::Enter => {
-                if let Some(i) = self.state.selected() {
-                    self.audio_interface.borrow_mut().hard_clear_queue();
-                    let mut wrapped_music_list = self.get_wrapped_music_list(i);
-                    self.audio_interface
-                        .borrow_mut()
-                        .append_to_queue(&mut wrapped_music_list);
-                }
+        if self.submission.is_some() {
+            self.handle_submission_input(key);
+            return Ok(());
+        }
+        if self.artist_bio.is_some() {
+            if matches!(key, KeyCode::Esc | KeyCode::Char('b')) {
+                self.artist_bio = None;
             }
-            _ => {}
+            return Ok(());
+        }
+        if self.editor.is_some() {
+            return self.handle_editor_input(key);
+        }
+        if self.export.is_some() {
+            self.handle_export_input(key);
+            return Ok(());
+        }
+        if self.scan_errors_state.is_some() {
+            self.handle_scan_errors_input(key);
+            return Ok(());
+        }
+        if let Some(duplicates) = &mut self.duplicates {
+            if key == KeyCode::Esc && !duplicates.is_confirming() {
+                self.duplicates = None;
+                return Ok(());
+            }
+            duplicates.handle_input(key)?;
+            if let Some(resolution) = duplicates.take_resolution() {
+                self.remove_duplicates(resolution);
+            }
+            return Ok(());
+        }
+        if let Some(missing_art) = &mut self.missing_art {
+            // stays open until the fetch is done, so its results can be seen
+            if key == KeyCode::Esc && !missing_art.is_fetching() {
+                self.missing_art = None;
+                return Ok(());
+            }
+            return missing_art.handle_input(key);
+        }
+        match key {
+            KeyCode::Char('g') => self.browse(GroupBy::Genre),
+            KeyCode::Char('a') => self.browse(GroupBy::Artist),
+            KeyCode::Esc if self.album_context.is_some() => self.clear_album_context(),
+            KeyCode::Esc => self.view = LibraryView::Tracks,
+            _ => match self.view {
+                LibraryView::Tracks => self.handle_tracks_input(key),
+                LibraryView::Browse(_) => self.handle_browse_input(key),
+            },
         }
         Ok(())
     }
 }
 
-fn recursive_vec_file_walk(lib_folders: Vec<String>) -> Vec<String> {
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ColumnConfig {
+    pub name: String,
+    pub visible: bool,
+    pub width_pct: u8,
+}
+
+pub fn default_columns() -> Vec<ColumnConfig> {
+    [
+        ("Title", 30),
+        ("Artist", 20),
+        ("Album", 25),
+        ("Year", 5),
+        ("Length", 5),
+        ("Rating", 10),
+        ("BPM", 5),
+    ]
+    .into_iter()
+    .map(|(name, width_pct)| ColumnConfig {
+        name: String::from(name),
+        visible: true,
+        width_pct,
+    })
+    .collect()
+}
+
+fn column_value(file: &AudioFile, column: &str) -> String {
+    match column {
+        "#" => file
+            .get_track_number()
+            .map(|track| format!("{:>3}", track))
+            .unwrap_or_default(),
+        "Title" => match file.get_integrity_ok() {
+            Some(false) => format!("⚠ {}", file.get_title()),
+            _ => file.get_title().clone(),
+        },
+        "Artist" => match featured_artist(file.get_title()) {
+            Some(featured) => format!("{} (feat. {})", file.get_album_artist(), featured),
+            None => file.get_album_artist().clone(),
+        },
+        "Album" => file.get_album().clone(),
+        "Year" => file.get_year().to_string(),
+        "Length" => file.get_duration(),
+        "Rating" => rating_stars(file.get_rating()),
+        // tables can't align a column, so pad it to the right instead
+        "BPM" => match file.get_bpm() {
+            Some(bpm) => format!("{:>5.0}", bpm),
+            None => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+// the guest in titles like "Song (feat. Someone)" or "Song ft. Someone"
+fn featured_artist(title: &str) -> Option<&str> {
+    let lowercase = title.to_lowercase();
+    // lowercasing can change byte lengths outside ascii, only trust it when it didn't
+    if lowercase.len() != title.len() {
+        return None;
+    }
+    ["feat. ", "ft. ", "featuring "]
+        .iter()
+        .filter_map(|marker| {
+            let start = lowercase.find(marker)?;
+            let preceded = title[..start].ends_with(' ') || title[..start].ends_with('(');
+            preceded.then_some(start + marker.len())
+        })
+        .min()
+        .map(|start| {
+            let rest = &title[start..];
+            rest[..rest.find([')', ']']).unwrap_or(rest.len())].trim()
+        })
+        .filter(|featured| !featured.is_empty())
+}
+
+// shown in front of the configured columns wherever tracks are listed album by album
+pub fn with_track_number_column(columns: &[ColumnConfig]) -> Vec<ColumnConfig> {
+    let mut columns = columns.to_vec();
+    columns.insert(
+        0,
+        ColumnConfig {
+            name: String::from("#"),
+            visible: true,
+            width_pct: 4,
+        },
+    );
+    columns
+}
+
+pub fn column_widths(columns: &[ColumnConfig]) -> Vec<Constraint> {
+    columns
+        .iter()
+        .filter(|column| column.visible)
+        .map(|column| Constraint::Percentage(column.width_pct as u16))
+        .collect()
+}
+
+pub fn rating_stars(rating: Option<u8>) -> String {
+    match rating {
+        Some(stars) => format!(
+            "{}{}",
+            "★".repeat(stars as usize),
+            "☆".repeat(5 - stars as usize)
+        ),
+        None => String::new(),
+    }
+}
+
+pub fn track_row(
+    file: &AudioFile,
+    currently_playing: &Option<AudioFile>,
+    columns: &[ColumnConfig],
+) -> Row<'static> {
+    let row = Row::new(
+        columns
+            .iter()
+            .filter(|column| column.visible)
+            .map(|column| match column.name.as_str() {
+                "Title" => Cell::from(Line::from(vec![
+                    quality_badge(file),
+                    Span::raw(column_value(file, &column.name)),
+                ])),
+                _ => Cell::from(column_value(file, &column.name)),
+            })
+            .collect::<Vec<_>>(),
+    );
+    playing_style(row, file, currently_playing)
+}
+
+// [L] for lossless files, [C] for compressed ones, in front of the title
+fn quality_badge(file: &AudioFile) -> Span<'static> {
+    match file.is_lossless() {
+        true => Span::styled("[L] ", Style::default().fg(Color::Cyan)),
+        false => Span::styled("[C] ", Style::default().fg(Color::DarkGray)),
+    }
+}
+
+// like track_row, but long values wrap onto more lines instead of being cut
+// off. width is the space inside the table's borders
+pub fn wrapped_track_row(
+    file: &AudioFile,
+    currently_playing: &Option<AudioFile>,
+    columns: &[ColumnConfig],
+    width: u16,
+) -> Row<'static> {
+    let cells = columns
+        .iter()
+        .filter(|column| column.visible)
+        .map(|column| {
+            // less the space between columns
+            let column_width = (width as usize * column.width_pct as usize / 100)
+                .saturating_sub(1)
+                .max(1);
+            let badge = (column.name == "Title").then(|| quality_badge(file));
+            // the badge sits on the first line, the title wraps around it
+            let badge_width = badge.as_ref().map_or(0, Span::width);
+            let mut lines = textwrap::wrap(
+                &column_value(file, &column.name),
+                column_width.saturating_sub(badge_width).max(1),
+            )
+            .into_iter()
+            .map(|line| Line::from(line.into_owned()))
+            .collect::<Vec<_>>();
+            if let (Some(badge), Some(first)) = (badge, lines.first_mut()) {
+                first.spans.insert(0, badge);
+            }
+            lines
+        })
+        .collect::<Vec<_>>();
+    let height = cells.iter().map(Vec::len).max().unwrap_or(1).max(1);
+    let row = Row::new(cells.into_iter().map(|lines| Cell::from(Text::from(lines))))
+        .height(height as u16);
+    playing_style(row, file, currently_playing)
+}
+
+fn playing_style(
+    row: Row<'static>,
+    file: &AudioFile,
+    currently_playing: &Option<AudioFile>,
+) -> Row<'static> {
+    match currently_playing {
+        Some(track) if track.get_path() == file.get_path() => row.style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        _ => row,
+    }
+}
+
+// widths come from column_widths, the table only borrows them
+pub fn track_table<'a>(
+    rows: Vec<Row<'a>>,
+    title: &'a str,
+    columns: &[ColumnConfig],
+    widths: &'a [Constraint],
+) -> Table<'a> {
+    Table::new(rows)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green))
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::Green)
+                .fg(Color::White),
+        )
+        .header(
+            Row::new(
+                columns
+                    .iter()
+                    .filter(|column| column.visible)
+                    .map(|column| column.name.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .style(Style::default().fg(Color::Yellow)),
+        )
+        .widths(widths)
+}
+
+fn recently_added(music_list: &[AudioFile], days: u32) -> HashSet<PathBuf> {
+    let Some(threshold) = SystemTime::now().checked_sub(Duration::from_secs(days as u64 * 86400))
+    else {
+        return HashSet::new();
+    };
+    music_list
+        .iter()
+        .map(|file| file.get_path())
+        .filter(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified > threshold)
+        })
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+// groups tracks whose files have the same size and contents. only the first
+// 64 KB is hashed at first, the files that still match are then read in full.
+// cue tracks are only compared with tracks at the same offset of another file
+pub fn scan_duplicates(music_list: &[AudioFile]) -> Vec<Vec<AudioFile>> {
+    let mut candidates: HashMap<(u64, blake3::Hash, Option<u64>), Vec<&AudioFile>> = HashMap::new();
+    for file in music_list {
+        if file.get_stream_url().is_some() {
+            continue;
+        }
+        let Ok(metadata) = std::fs::metadata(file.get_path()) else {
+            continue;
+        };
+        if let Some(hash) = head_hash(file.get_path()) {
+            candidates
+                .entry((metadata.len(), hash, file.get_cue_offset_frames()))
+                .or_default()
+                .push(file);
+        }
+    }
+    // the tracks of a cue sheet share one file, which is only read once
+    let mut full_hashes: HashMap<&Path, Option<blake3::Hash>> = HashMap::new();
+    let mut groups: HashMap<(u64, blake3::Hash, Option<u64>), Vec<AudioFile>> = HashMap::new();
+    for ((size, _, offset), files) in candidates {
+        if files.len() < 2 {
+            continue;
+        }
+        for file in files {
+            let hash = *full_hashes
+                .entry(file.get_path())
+                .or_insert_with(|| full_hash(file.get_path()));
+            if let Some(hash) = hash {
+                groups
+                    .entry((size, hash, offset))
+                    .or_default()
+                    .push(file.clone());
+            }
+        }
+    }
+    let mut duplicates = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect::<Vec<_>>();
+    duplicates.sort_by(|a, b| a[0].get_title().cmp(b[0].get_title()));
+    duplicates
+}
+
+// hashing only the start of each file keeps the scan fast on large libraries
+fn head_hash(path: &Path) -> Option<blake3::Hash> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(64 * 1024)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(blake3::hash(&head))
+}
+
+fn full_hash(path: &Path) -> Option<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut std::fs::File::open(path).ok()?, &mut hasher).ok()?;
+    Some(hasher.finalize())
+}
+
+// checked again right before a copy is deleted, the files may have changed
+// since the scan. a second path to the kept file is never a copy of it
+fn is_separate_copy(keep: &Path, copy: &Path) -> bool {
+    let same_file = match (keep.canonicalize(), copy.canonicalize()) {
+        (Ok(keep), Ok(copy)) => keep == copy,
+        _ => return false,
+    };
+    !same_file && same_contents(keep, copy).unwrap_or(false)
+}
+
+fn same_contents(a: &Path, b: &Path) -> Result<bool, io::Error> {
+    if std::fs::metadata(a)?.len() != std::fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let mut a = io::BufReader::new(std::fs::File::open(a)?);
+    let mut b = io::BufReader::new(std::fs::File::open(b)?);
+    loop {
+        let (chunk_a, chunk_b) = (a.fill_buf()?, b.fill_buf()?);
+        if chunk_a.is_empty() || chunk_b.is_empty() {
+            return Ok(chunk_a.is_empty() && chunk_b.is_empty());
+        }
+        let n = chunk_a.len().min(chunk_b.len());
+        if chunk_a[..n] != chunk_b[..n] {
+            return Ok(false);
+        }
+        a.consume(n);
+        b.consume(n);
+    }
+}
+
+// patterns that don't compile were rejected when they were entered
+fn exclude_patterns(settings: &Settings) -> Vec<glob::Pattern> {
+    settings
+        .lib_exclude_patterns
+        .iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect()
+}
+
+// also sends the files that couldn't be read, with the reason. the cache is
+// applied by the window once the list arrives
+fn scan_library(
+    lib_folders: Vec<PathBuf>,
+    max_depth: usize,
+    exclude: &[glob::Pattern],
+    progress: &Sender<ScanProgress>,
+) {
+    let mut scan_errors = Vec::new();
+    let music_list = recursive_vec_file_walk(lib_folders, max_depth, exclude, &mut scan_errors);
+    let mut cue_tracks = Vec::new();
+    for path in music_list.iter().filter(|path| path.ends_with(".cue")) {
+        match cue::parse(Path::new(path)) {
+            Ok(tracks) => cue_tracks.extend(tracks),
+            Err(e) => scan_errors.push((PathBuf::from(path), e.to_string())),
+        }
+    }
+    // a separate cue sheet wins over one embedded in the flac it describes
+    let external = cue_tracks
+        .iter()
+        .map(|track| track.file.clone())
+        .collect::<HashSet<_>>();
+    cue_tracks.extend(
+        music_list
+            .iter()
+            .filter(|path| path.ends_with(".flac") && !external.contains(Path::new(path)))
+            .filter_map(|path| cue::read_embedded(Path::new(path)))
+            .flatten(),
+    );
+    // files split by a cue sheet are listed as their tracks instead
+    let split_files = cue_tracks
+        .iter()
+        .map(|track| track.file.clone())
+        .collect::<HashSet<_>>();
+    let mut audio_files = Vec::new();
+    for path in music_list
+        .iter()
+        .filter(|path| !path.ends_with(".cue") && !split_files.contains(Path::new(path)))
+    {
+        match AudioFile::new(path) {
+            Ok(audio_file) => audio_files.push(audio_file),
+            Err(e) => scan_errors.push((PathBuf::from(path), e.to_string())),
+        }
+        if audio_files.len().is_multiple_of(SCAN_PROGRESS_INTERVAL) {
+            let _ = progress.send(ScanProgress::Scanning {
+                total_found: audio_files.len(),
+            });
+        }
+    }
+    for track in &cue_tracks {
+        match AudioFile::from_cue_track(track) {
+            Ok(audio_file) => audio_files.push(audio_file),
+            Err(e) => scan_errors.push((track.file.clone(), e.to_string())),
+        }
+    }
+    // every track of a broken cue file fails the same way, list it once
+    scan_errors.dedup();
+    // the window may be gone if rmus quit mid scan
+    let _ = progress.send(ScanProgress::Done(audio_files, scan_errors));
+}
+
+// 1234567 -> 1,234,567
+pub fn with_thousands_separators(n: usize) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+fn recursive_vec_file_walk(
+    lib_folders: Vec<PathBuf>,
+    max_depth: usize,
+    exclude: &[glob::Pattern],
+    errors: &mut Vec<(PathBuf, String)>,
+) -> Vec<String> {
     let mut files = Vec::new();
-    for str_path in lib_folders {
-        let path = Path::new(str_path.as_str());
-        recursive_file_walk(path)
+    let mut visited = HashSet::new();
+    for path in lib_folders {
+        recursive_file_walk(&path, max_depth, exclude, &mut visited, errors)
             .into_iter()
             .for_each(|x| files.push(x))
     }
     files
 }
 
-fn recursive_file_walk(path: &Path) -> Vec<String> {
+// depth counts down to 0 as the walk descends. visited holds the canonical path
+// of every directory scanned so far, so symlink cycles are only entered once.
+// directories and files whose name matches an exclude pattern are skipped.
+// directories and entries that can't be read are skipped and added to errors
+pub fn recursive_file_walk(
+    path: &Path,
+    depth: usize,
+    exclude: &[glob::Pattern],
+    visited: &mut HashSet<PathBuf>,
+    errors: &mut Vec<(PathBuf, String)>,
+) -> Vec<String> {
     let mut files = Vec::new();
-    for entry in path.read_dir().expect("read_dir call failed") {
-        let entry = entry.expect("Error reading entry");
+    if depth == 0 {
+        return files;
+    }
+    let entries = match std::fs::canonicalize(path).and_then(|canonical| {
+        let entries = path.read_dir()?;
+        Ok((canonical, entries))
+    }) {
+        Ok((canonical, entries)) => {
+            if !visited.insert(canonical) {
+                return files;
+            }
+            entries
+        }
+        Err(e) => {
+            errors.push((path.to_path_buf(), e.to_string()));
+            return files;
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push((path.to_path_buf(), e.to_string()));
+                continue;
+            }
+        };
         let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if exclude.iter().any(|pattern| pattern.matches(&name)) {
+            continue;
+        }
         if path.is_dir() {
-            files.append(&mut recursive_file_walk(&path));
+            files.append(&mut recursive_file_walk(
+                &path,
+                depth - 1,
+                exclude,
+                visited,
+                errors,
+            ));
         } else {
-            // Check if file is an mp3, flac, wav, or ogg and add it to the list
+            // Check if file is an mp3, flac, wav, ogg, aiff or cue sheet and add it to the list
             if let Some(ext) = path.extension() {
-                if ext == "mp3" || ext == "flac" || ext == "wav" || ext == "ogg" {
-                    files.push(path.as_os_str().to_str().unwrap().to_string());
+                if ext == "mp3"
+                    || ext == "flac"
+                    || ext == "wav"
+                    || ext == "ogg"
+                    || ext == "aiff"
+                    || ext == "aif"
+                    || ext == "cue"
+                {
+                    files.push(path.to_string_lossy().to_string());
                 }
             }
         }
@@ -271,7 +2252,46 @@ fn recursive_file_walk(path: &Path) -> Vec<String> {
     files
 }
 
-fn seconds_to_formatted_time(seconds: usize) -> String {
+// for auto advance, ranks the rest of the library by what it shares with the
+// track that just finished: the same artist beats the same album, which beats
+// the same genre. ties, including having nothing in common, are broken at random
+pub fn suggest_next<'a>(
+    library: &'a [AudioFile],
+    current: &AudioFile,
+    auto_advance: AutoAdvance,
+) -> Option<&'a AudioFile> {
+    let candidates = library.iter().filter(|file| *file != current);
+    let known = |value: &String, other: &String| value == other && value != "Unknown";
+    let similarity = |file: &AudioFile| {
+        if known(file.get_artist(), current.get_artist()) {
+            3
+        } else if known(file.get_album(), current.get_album()) {
+            2
+        } else if known(file.get_genre(), current.get_genre()) {
+            1
+        } else {
+            0
+        }
+    };
+    let candidates = match auto_advance {
+        AutoAdvance::Stop => return None,
+        AutoAdvance::Random => candidates.collect::<Vec<_>>(),
+        AutoAdvance::Similar => {
+            let scored = candidates
+                .map(|file| (similarity(file), file))
+                .collect::<Vec<_>>();
+            let best = scored.iter().map(|(score, _)| *score).max()?;
+            scored
+                .into_iter()
+                .filter(|(score, _)| *score == best)
+                .map(|(_, file)| file)
+                .collect()
+        }
+    };
+    candidates.choose(&mut rand::thread_rng()).copied()
+}
+
+pub fn seconds_to_formatted_time(seconds: usize) -> String {
     let minutes = seconds / 60;
     let seconds = seconds % 60;
     format!("{:02}:{:02}", minutes, seconds)
@@ -0,0 +1,195 @@
+use crate::{acoustid::USER_AGENT, audio::AudioFile, log, log::Level};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    fmt,
+    sync::mpsc::{self, Sender},
+};
+
+const SUBMIT_URL: &str = "https://api.listenbrainz.org/1/submit-listens";
+// the most listens one request may carry
+const MAX_LISTENS_PER_IMPORT: usize = 1000;
+
+#[derive(Debug)]
+pub enum ListenBrainzError {
+    Request(reqwest::Error),
+    // listenbrainz answered with an error, usually a bad token
+    Rejected(String),
+}
+
+impl fmt::Display for ListenBrainzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenBrainzError::Request(e) => write!(f, "submission failed: {}", e),
+            ListenBrainzError::Rejected(message) => write!(f, "listen rejected: {}", message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ListenBrainzError {
+    fn from(e: reqwest::Error) -> Self {
+        ListenBrainzError::Request(e)
+    }
+}
+
+// what is sent for a track, kept in the offline queue until it goes through
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Listen {
+    // unix time the track started playing
+    pub listened_at: i64,
+    pub artist_name: String,
+    pub track_name: String,
+    pub release_name: String,
+    pub duration_ms: u64,
+}
+
+impl Listen {
+    pub fn new(track: &AudioFile, listened_at: i64) -> Self {
+        Self {
+            listened_at,
+            artist_name: track.get_artist().clone(),
+            track_name: track.get_title().clone(),
+            release_name: track.get_album().clone(),
+            duration_ms: (track.get_raw_duration() * 1000.0).round() as u64,
+        }
+    }
+
+    fn payload(&self) -> serde_json::Value {
+        json!({
+            "listened_at": self.listened_at,
+            "track_metadata": {
+                "artist_name": self.artist_name,
+                "track_name": self.track_name,
+                "release_name": self.release_name,
+                "additional_info": {
+                    "duration_ms": self.duration_ms,
+                    "media_player": "rmus",
+                    "submission_client": "rmus",
+                    "submission_client_version": env!("CARGO_PKG_VERSION"),
+                },
+            },
+        })
+    }
+}
+
+pub struct Client {
+    token: String,
+}
+
+impl Client {
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+
+    pub fn submit_listen(
+        &self,
+        track: &AudioFile,
+        listened_at: i64,
+    ) -> Result<(), ListenBrainzError> {
+        self.submit(&[Listen::new(track, listened_at)])
+    }
+
+    // a single listen, or an import of several from the offline queue
+    fn submit(&self, listens: &[Listen]) -> Result<(), ListenBrainzError> {
+        let body = json!({
+            "listen_type": if listens.len() == 1 { "single" } else { "import" },
+            "payload": listens.iter().map(Listen::payload).collect::<Vec<_>>(),
+        });
+        let response = reqwest::blocking::Client::new()
+            .post(SUBMIT_URL)
+            .header("Authorization", format!("Token {}", self.token))
+            .header("User-Agent", USER_AGENT)
+            .json(&body)
+            .send()?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status = response.status();
+        let message = response
+            .json::<serde_json::Value>()
+            .ok()
+            .and_then(|body| body["error"].as_str().map(String::from))
+            .unwrap_or_else(|| status.to_string());
+        Err(ListenBrainzError::Rejected(message))
+    }
+}
+
+fn pending_path() -> std::path::PathBuf {
+    std::env::current_dir()
+        .unwrap_or_default()
+        .join("listenbrainz_pending.json")
+}
+
+fn load_pending() -> Vec<Listen> {
+    std::fs::read_to_string(pending_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_pending(pending: &[Listen]) {
+    let result = match pending {
+        [] => std::fs::remove_file(pending_path()).or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        }),
+        pending => std::fs::write(pending_path(), serde_json::to_string(pending).unwrap()),
+    };
+    if let Err(e) = result {
+        log!(Level::Warn, "could not save pending listens: {}", e);
+    }
+}
+
+// sends listens from its own thread so a slow or missing connection never
+// holds up the ui. listens that fail are written to disk and sent again
+// on the next startup
+pub struct Scrobbler {
+    sender: Sender<(AudioFile, i64)>,
+}
+
+impl Scrobbler {
+    pub fn start(token: String) -> Self {
+        let (sender, receiver) = mpsc::channel::<(AudioFile, i64)>();
+        std::thread::spawn(move || {
+            let client = Client::new(token);
+            let mut pending = load_pending();
+            if !pending.is_empty() {
+                flush(&client, &mut pending);
+            }
+            for (track, listened_at) in receiver {
+                if let Err(e) = client.submit_listen(&track, listened_at) {
+                    log!(Level::Warn, "listenbrainz: {}, will retry on startup", e);
+                    pending.push(Listen::new(&track, listened_at));
+                    save_pending(&pending);
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn scrobble(&self, track: &AudioFile, listened_at: i64) {
+        let _ = self.sender.send((track.clone(), listened_at));
+    }
+}
+
+// the offline queue, oldest first, in chunks listenbrainz accepts
+fn flush(client: &Client, pending: &mut Vec<Listen>) {
+    while !pending.is_empty() {
+        let chunk = pending.len().min(MAX_LISTENS_PER_IMPORT);
+        match client.submit(&pending[..chunk]) {
+            Ok(()) => {
+                pending.drain(..chunk);
+            }
+            Err(e) => {
+                log!(
+                    Level::Warn,
+                    "listenbrainz: {}, {} listens still pending",
+                    e,
+                    pending.len()
+                );
+                break;
+            }
+        }
+    }
+    save_pending(pending);
+}
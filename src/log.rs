@@ -0,0 +1,151 @@
+use crate::ui::{centered_rect, render_popup, Window};
+use crossterm::event::KeyCode;
+use std::{
+    collections::VecDeque,
+    io::{self, Stdout},
+    sync::{Arc, Mutex, OnceLock},
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+const LOG_CAPACITY: usize = 500;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Level::Error => Color::Red,
+            Level::Warn => Color::Yellow,
+            Level::Info => Color::Green,
+        }
+    }
+}
+
+// the most recent messages, the oldest are dropped once it is full
+pub struct LogBuffer(VecDeque<(Level, String)>, usize);
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self(VecDeque::with_capacity(capacity), capacity)
+    }
+
+    pub fn push(&mut self, level: Level, message: String) {
+        if self.0.len() == self.1 {
+            self.0.pop_front();
+        }
+        self.0.push_back((level, message));
+    }
+
+    pub fn entries(&self) -> &VecDeque<(Level, String)> {
+        &self.0
+    }
+}
+
+// errors come from the audio thread too, so the buffer is shared
+pub fn buffer() -> Arc<Mutex<LogBuffer>> {
+    static BUFFER: OnceLock<Arc<Mutex<LogBuffer>>> = OnceLock::new();
+    BUFFER
+        .get_or_init(|| Arc::new(Mutex::new(LogBuffer::new(LOG_CAPACITY))))
+        .clone()
+}
+
+pub fn push(level: Level, message: String) {
+    if let Ok(mut buffer) = buffer().lock() {
+        buffer.push(level, message);
+    }
+}
+
+// eprintln! is lost behind the alternate screen, this ends up in the log window
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::log::push($level, format!($($arg)*))
+    };
+}
+
+// shown over the current window with !, newest entries at the bottom
+pub struct LogWindow {
+    buffer: Arc<Mutex<LogBuffer>>,
+    // lines scrolled up from the bottom
+    scroll: usize,
+}
+
+impl LogWindow {
+    pub fn new() -> Self {
+        Self {
+            buffer: buffer(),
+            scroll: 0,
+        }
+    }
+}
+
+impl Default for LogWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Window for LogWindow {
+    fn get_title(&self) -> String {
+        String::from("Log - Up/Down to scroll, ! or Esc to close")
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let popup = centered_rect(80, 70, area);
+        let buffer = self.buffer.lock().unwrap();
+        let entries = buffer.entries();
+        let height = popup.height.saturating_sub(2) as usize;
+        self.scroll = self.scroll.min(entries.len().saturating_sub(height));
+        let end = entries.len() - self.scroll;
+        let lines = entries
+            .range(end.saturating_sub(height)..end)
+            .map(|(level, message)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<5} ", level.name()),
+                        Style::default().fg(level.color()),
+                    ),
+                    Span::raw(message.clone()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let log = Paragraph::new(lines).style(Style::default().fg(Color::White));
+        render_popup(f, popup, &self.get_title(), |inner, f| {
+            f.render_widget(log, inner)
+        });
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        match key {
+            KeyCode::Up => self.scroll += 1,
+            KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+// parses the lines of an lrc file into (seconds, text) pairs sorted by time.
+// a line may carry several timestamps when its text repeats, e.g.
+// [00:12.00][01:30.50]chorus. metadata tags like [ar:artist] are skipped
+pub fn parse(content: &str) -> Vec<(f64, String)> {
+    let mut lines: Vec<(f64, String)> = Vec::new();
+    for line in content.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some((timestamp, after)) = tag.split_once(']') else {
+                break;
+            };
+            match parse_timestamp(timestamp) {
+                Some(time) => times.push(time),
+                None => break,
+            }
+            rest = after;
+        }
+        for time in times {
+            lines.push((time, rest.trim().to_string()));
+        }
+    }
+    lines.sort_by(|a, b| a.0.total_cmp(&b.0));
+    lines
+}
+
+// mm:ss.xx, where the fraction is optional
+fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let minutes = minutes.trim().parse::<u32>().ok()?;
+    let seconds = seconds.trim().parse::<f64>().ok()?;
+    Some(minutes as f64 * 60.0 + seconds)
+}
@@ -0,0 +1,118 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    lrc,
+    ui::Window,
+};
+use std::{
+    cell::RefCell,
+    io::{self, Stdout},
+    rc::Rc,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+pub struct LyricsWindow {
+    title: String,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    // the track the lyrics below were parsed for
+    track: Option<AudioFile>,
+    synced: Vec<(f64, String)>,
+}
+
+impl LyricsWindow {
+    pub fn new(audio_interface: Rc<RefCell<AudioInterface>>) -> Self {
+        Self {
+            title: String::from("Lyrics"),
+            audio_interface,
+            track: None,
+            synced: Vec::new(),
+        }
+    }
+
+    fn update_track(&mut self) {
+        let playing = self
+            .audio_interface
+            .borrow()
+            .get_currently_playing()
+            .clone();
+        if playing != self.track {
+            self.synced = match playing.as_ref().and_then(|file| file.get_lyrics()) {
+                Some(lyrics) => lrc::parse(lyrics),
+                None => Vec::new(),
+            };
+            self.track = playing;
+        }
+    }
+
+    fn draw_synced(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let position = self.audio_interface.borrow().get_sink_length() as f64;
+        let current = self.synced.iter().rposition(|(time, _)| *time <= position);
+        let lines = self
+            .synced
+            .iter()
+            .enumerate()
+            .map(|(i, (_, text))| {
+                let style = if Some(i) == current {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Line::from(Span::styled(text.clone(), style))
+            })
+            .collect::<Vec<_>>();
+        // keep the current line in the middle of the window
+        let middle = area.height.saturating_sub(2) / 2;
+        let scroll = (current.unwrap_or(0) as u16).saturating_sub(middle);
+        let lyrics = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(self.get_title())
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .scroll((scroll, 0));
+        f.render_widget(lyrics, area);
+    }
+}
+
+impl Window for LyricsWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        self.update_track();
+        if !self.synced.is_empty() {
+            self.draw_synced(area, f);
+            return Ok(());
+        }
+        // lyrics without timestamps are shown as they are
+        let text = match self.track.as_ref().and_then(|file| file.get_lyrics()) {
+            Some(lyrics) => lyrics.to_string(),
+            None if self.track.is_some() => String::from("No lyrics found"),
+            None => String::from("Nothing Playing"),
+        };
+        let lyrics = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title(self.get_title())
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .wrap(Wrap { trim: false });
+        f.render_widget(lyrics, area);
+        Ok(())
+    }
+}
@@ -1,44 +1,172 @@
-use std::{cell::RefCell, io, rc::Rc};
+use std::{cell::RefCell, io, rc::Rc, time::Duration};
 
-use folders::FoldersWindow;
-use library::LibraryWindow;
-use settings::SettingsWindow;
-use tidal::TidalWindow;
-
-mod audio;
-mod folders;
-mod library;
-mod settings;
-mod tidal;
-mod ui;
+use music_player::album::AlbumBrowserWindow;
+use music_player::bookmarks::BookmarksWindow;
+use music_player::folders::FoldersWindow;
+use music_player::library::LibraryWindow;
+use music_player::lyrics::LyricsWindow;
+use music_player::playlist_manager::PlaylistManagerWindow;
+use music_player::podcast::PodcastWindow;
+use music_player::queue::QueueWindow;
+use music_player::settings::SettingsWindow;
+use music_player::smart_playlist::SmartPlaylistWindow;
+use music_player::subsonic::SubsonicWindow;
+use music_player::tidal::TidalWindow;
+use music_player::visualizer::AudioVisualizerWindow;
+use music_player::{audio, cache, mpd_compat, server, settings, tidal, ui};
+use music_player::{log, log::Level};
 
 fn main() -> Result<(), io::Error> {
     // terminal initialization
-    let settings = Rc::new(RefCell::new(settings::Settings::load()));
+    let settings = match settings::Settings::load() {
+        Ok(settings) => Rc::new(RefCell::new(settings)),
+        Err(e) => {
+            eprintln!("rmus: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let devices = audio::Devices::new(settings.borrow().get_device());
+    settings.borrow_mut().validate_and_fix(&devices);
     let device = settings.borrow().get_device();
-    let devices = audio::Devices::new(device);
     println!("{}", devices.get_device_names().len());
-    let (stream, stream_handle) = rodio::OutputStream::try_from_device(&device).unwrap();
+    let mut buffer_size = settings.borrow().audio_buffer_size;
+    let output_device = devices.get_device_by_index_or_default(device);
+    let opened = match (
+        audio::Devices::open_stream(output_device, buffer_size),
+        buffer_size,
+    ) {
+        // the saved size may not suit this device, fall back to its default
+        (Err(e), Some(size)) => {
+            log!(
+                Level::Warn,
+                "buffer size {} was rejected ({}), using the device default",
+                size,
+                e
+            );
+            buffer_size = None;
+            settings.borrow_mut().audio_buffer_size = None;
+            audio::Devices::open_stream(output_device, None)
+        }
+        (opened, _) => opened,
+    };
+    let (stream, sink) = match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("rmus: could not open the output device: {}", e);
+            std::process::exit(1);
+        }
+    };
     let audio_interface = Rc::new(RefCell::new(audio::AudioInterface::new(
         stream,
-        rodio::Sink::try_new(&stream_handle).unwrap(),
+        sink,
         devices,
+        buffer_size,
     )));
+    audio_interface
+        .borrow_mut()
+        .set_volume(settings.borrow().volume);
+    audio_interface
+        .borrow_mut()
+        .set_skip_silence(settings.borrow().get_skip_silence());
+    audio_interface
+        .borrow_mut()
+        .set_channel_mapping(settings.borrow().channel_mapping);
+    audio_interface
+        .borrow_mut()
+        .set_resampler_quality(settings.borrow().resampler_quality);
+    audio_interface.borrow_mut().set_fades(
+        Duration::from_secs_f64(settings.borrow().fade_in_secs.max(0.0)),
+        Duration::from_secs_f64(settings.borrow().fade_out_secs.max(0.0)),
+    );
     let tidal_session = Rc::new(RefCell::new(tidal::TidalSession::new()));
+    let cache = Rc::new(RefCell::new(cache::LibraryCache::load()));
     let mut ui: ui::UI = ui::UI::new(
         settings.clone(),
         audio_interface.clone(),
         tidal_session.clone(),
+        cache.clone(),
     )?;
-    ui.push_window(Box::new(LibraryWindow::new(
+    let library_window = Rc::new(RefCell::new(LibraryWindow::new(
+        settings.clone(),
+        audio_interface.clone(),
+        cache.clone(),
+    )));
+    if !ui.wait_for_scan(&mut library_window.borrow_mut())? {
+        // quit before the library finished scanning, dropping the ui restores the terminal
+        return Ok(());
+    }
+    // the windows below start from this list, later changes reach them
+    // through Window::on_library_changed
+    let music_list = {
+        let mut library_window = library_window.borrow_mut();
+        library_window.take_library_changed();
+        library_window.get_music_list().clone()
+    };
+    audio_interface
+        .borrow_mut()
+        .set_auto_advance(settings.borrow().auto_advance, music_list.clone());
+    let album_browser_window =
+        AlbumBrowserWindow::new(&music_list, audio_interface.clone(), library_window.clone());
+    let smart_playlist_window = SmartPlaylistWindow::new(
+        settings.clone(),
+        audio_interface.clone(),
+        cache.clone(),
+        &music_list,
+    );
+    let bookmarks_window = BookmarksWindow::new(audio_interface.clone(), cache, &music_list);
+    if std::env::args().any(|arg| arg == "--server") {
+        let settings = settings.borrow();
+        ui.set_api_server(server::ApiServer::start(
+            settings.server_port,
+            settings.server_lan,
+            settings.server_token.clone(),
+        )?);
+    }
+    if std::env::args().any(|arg| arg == "--mpd") {
+        let port = settings.borrow().mpd_port;
+        ui.set_mpd_server(mpd_compat::MpdServer::start(port)?);
+    }
+    ui.set_library_window(library_window.clone());
+    ui.push_window(Box::new(library_window.clone()));
+    ui.push_window(Box::new(album_browser_window));
+    ui.push_window(Box::new(smart_playlist_window));
+    ui.push_window(Box::new(QueueWindow::new(
+        audio_interface.clone(),
+        tidal_session.clone(),
+    )));
+    ui.push_window(Box::new(PlaylistManagerWindow::new(
+        settings.clone(),
+        audio_interface.clone(),
+        library_window.clone(),
+    )));
+    ui.push_window(Box::new(bookmarks_window));
+    ui.push_window(Box::new(FoldersWindow::new(
+        settings.clone(),
+        audio_interface.clone(),
+        library_window,
+    )));
+    ui.push_window(Box::new(TidalWindow::new(
+        tidal_session.clone(),
+        audio_interface.clone(),
+    )));
+    ui.push_window(Box::new(SubsonicWindow::new(
+        settings.clone(),
+        audio_interface.clone(),
+    )));
+    ui.push_window(Box::new(PodcastWindow::new(
+        settings.clone(),
+        audio_interface.clone(),
+    )));
+    ui.push_window(Box::new(LyricsWindow::new(audio_interface.clone())));
+    ui.push_window(Box::new(AudioVisualizerWindow::new(
         settings.clone(),
         audio_interface.clone(),
     )));
-    ui.push_window(Box::new(FoldersWindow::new(settings.clone())));
-    ui.push_window(Box::new(TidalWindow::new(tidal_session.clone())));
+    let tab_names = ui.tab_names();
     ui.push_window(Box::new(SettingsWindow::new(
         settings.clone(),
         audio_interface,
+        tab_names,
     )));
     ui.run()
 }
@@ -0,0 +1,243 @@
+use crate::{audio::AudioFile, cover_art, ui::Window};
+use crossterm::event::KeyCode;
+use std::{
+    collections::BTreeMap,
+    io::{self, Stdout},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState},
+    Frame,
+};
+
+// musicbrainz allows one request a second
+const MUSICBRAINZ_INTERVAL: Duration = Duration::from_secs(1);
+
+struct MissingAlbum {
+    album_artist: String,
+    album: String,
+    // tracks from a cue sheet share their file, it is only written once
+    paths: Vec<PathBuf>,
+    // None until the cover has been fetched for it
+    result: Option<Result<(), String>>,
+}
+
+enum FetchProgress {
+    // tracks handled, whether or not the cover made it in
+    Tracks(usize),
+    Finished(usize, Result<(), String>),
+}
+
+// lists the albums with tracks that have no cover art, and fetches covers for
+// them from the cover art archive. the library window owns it
+pub struct MissingArtWindow {
+    albums: Vec<MissingAlbum>,
+    state: ListState,
+    fetch: Option<Receiver<FetchProgress>>,
+    tracks_done: usize,
+    tracks_total: usize,
+}
+
+impl MissingArtWindow {
+    pub fn new(missing: &[&AudioFile]) -> Self {
+        let mut grouped: BTreeMap<(String, String), Vec<PathBuf>> = BTreeMap::new();
+        for file in missing {
+            let paths = grouped
+                .entry((file.get_album_artist().clone(), file.get_album().clone()))
+                .or_default();
+            if !paths.iter().any(|path| path == file.get_path()) {
+                paths.push(file.get_path().to_path_buf());
+            }
+        }
+        let albums = grouped
+            .into_iter()
+            .map(|((album_artist, album), paths)| MissingAlbum {
+                album_artist,
+                album,
+                paths,
+                result: None,
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            albums,
+            state,
+            fetch: None,
+            tracks_done: 0,
+            tracks_total: 0,
+        }
+    }
+
+    pub fn is_fetching(&self) -> bool {
+        self.fetch.is_some()
+    }
+
+    // fetches each album's cover on a thread and embeds it in all of its tracks
+    fn start_fetch(&mut self, indices: Vec<usize>) {
+        if self.is_fetching() || indices.is_empty() {
+            return;
+        }
+        let albums = indices
+            .into_iter()
+            .map(|i| {
+                let album = &self.albums[i];
+                (
+                    i,
+                    album.album_artist.clone(),
+                    album.album.clone(),
+                    album.paths.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.tracks_done = 0;
+        self.tracks_total = albums.iter().map(|(_, _, _, paths)| paths.len()).sum();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (n, (i, album_artist, album, paths)) in albums.into_iter().enumerate() {
+                if n > 0 {
+                    thread::sleep(MUSICBRAINZ_INTERVAL);
+                }
+                let result = match cover_art::fetch(&album_artist, &album) {
+                    Ok(cover) => {
+                        let mut failed = Vec::new();
+                        for path in &paths {
+                            if let Err(e) = cover_art::embed(path, &cover) {
+                                failed.push(e.to_string());
+                            }
+                            let _ = sender.send(FetchProgress::Tracks(1));
+                        }
+                        match failed.first() {
+                            Some(e) => {
+                                Err(format!("{} of {} failed: {}", failed.len(), paths.len(), e))
+                            }
+                            None => Ok(()),
+                        }
+                    }
+                    Err(e) => {
+                        let _ = sender.send(FetchProgress::Tracks(paths.len()));
+                        Err(e.to_string())
+                    }
+                };
+                if sender.send(FetchProgress::Finished(i, result)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.fetch = Some(receiver);
+    }
+
+    fn poll_fetch(&mut self) {
+        let Some(receiver) = &self.fetch else {
+            return;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok(FetchProgress::Tracks(count)) => self.tracks_done += count,
+                Ok(FetchProgress::Finished(i, result)) => self.albums[i].result = Some(result),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        self.fetch = None;
+    }
+}
+
+impl Window for MissingArtWindow {
+    fn get_title(&self) -> String {
+        format!(
+            "Missing Cover Art - {} albums, Enter to fetch one, a to fetch all, Esc to close",
+            self.albums.len()
+        )
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        self.poll_fetch();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(area);
+        let mut items = self
+            .albums
+            .iter()
+            .map(|album| {
+                let result = match &album.result {
+                    Some(Ok(())) => String::from(" - cover added"),
+                    Some(Err(e)) => format!(" - {}", e),
+                    None => String::new(),
+                };
+                ListItem::new(format!(
+                    "{} - {} ({} tracks){}",
+                    album.album,
+                    album.album_artist,
+                    album.paths.len(),
+                    result
+                ))
+            })
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            items.push(ListItem::new("Every track has cover art"));
+        }
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(self.get_title())
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let (ratio, label) = match self.tracks_total {
+            0 => (0.0, String::from("Nothing fetched yet")),
+            total => (
+                self.tracks_done as f64 / total as f64,
+                format!("{} of {} tracks", self.tracks_done, total),
+            ),
+        };
+        let gauge = Gauge::default()
+            .block(Block::default().title("Embedding").borders(Borders::ALL))
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio.min(1.0))
+            .label(label);
+        f.render_stateful_widget(list, chunks[0], &mut self.state);
+        f.render_widget(gauge, chunks[1]);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if self.albums.is_empty() {
+            return Ok(());
+        }
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => self.state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => self
+                .state
+                .select(Some((selected + 1).min(self.albums.len() - 1))),
+            KeyCode::Enter => self.start_fetch(vec![selected]),
+            // albums that already got their cover are left alone
+            KeyCode::Char('a') => {
+                let indices = (0..self.albums.len())
+                    .filter(|i| !matches!(self.albums[*i].result, Some(Ok(()))))
+                    .collect();
+                self.start_fetch(indices);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        true
+    }
+}
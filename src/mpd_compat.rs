@@ -0,0 +1,288 @@
+use crate::audio::{AudioFile, AudioInterface, RepeatMode};
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+// the protocol version reported to clients, new enough for mpc and ncmpcpp
+const GREETING: &str = "OK MPD 0.23.0";
+
+// the subset of the mpd protocol that maps onto the audio interface
+enum Command {
+    Status,
+    CurrentSong,
+    Play,
+    Pause(Option<bool>),
+    Stop,
+    Next,
+    Previous,
+    SetVol(u8),
+    // looked up in the library when the command runs
+    Add(String),
+    Clear,
+    Playlist,
+}
+
+struct MpdRequest {
+    command: Command,
+    reply: Sender<Result<String, (u8, String)>>,
+}
+
+// speaks enough of the music player daemon protocol for mpd clients to act
+// as a remote. like the ipc socket, client threads only parse commands and
+// the ui thread runs them
+pub struct MpdServer {
+    requests: Receiver<MpdRequest>,
+}
+
+impl MpdServer {
+    pub fn start(port: u16) -> Result<Self, io::Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || handle_client(stream, sender));
+            }
+        });
+        Ok(Self { requests })
+    }
+
+    // add looks songs up in library, the library window's track list
+    pub fn handle(&self, audio_interface: &mut AudioInterface, library: &[AudioFile]) {
+        for request in self.requests.try_iter() {
+            let response = run(request.command, audio_interface, library);
+            let _ = request.reply.send(response);
+        }
+    }
+}
+
+fn run(
+    command: Command,
+    audio_interface: &mut AudioInterface,
+    library: &[AudioFile],
+) -> Result<String, (u8, String)> {
+    match command {
+        Command::Status => return Ok(status(audio_interface)),
+        Command::CurrentSong => {
+            return Ok(match audio_interface.get_currently_playing() {
+                Some(file) => song(file, 0),
+                None => String::new(),
+            })
+        }
+        Command::Playlist => {
+            return Ok(playlist(audio_interface)
+                .iter()
+                .enumerate()
+                .map(|(i, file)| format!("{}:file: {}\n", i, file.get_path().display()))
+                .collect())
+        }
+        Command::Play => {
+            if audio_interface.get_paused() {
+                audio_interface.toggle_pause();
+            }
+        }
+        Command::Pause(pause) => {
+            if pause.unwrap_or(!audio_interface.get_paused()) != audio_interface.get_paused() {
+                audio_interface.toggle_pause();
+            }
+        }
+        // rmus has no stopped state, go back to the start of the track and pause there
+        Command::Stop => {
+            audio_interface.seek(0.0).map_err(|e| (52, e.to_string()))?;
+            if !audio_interface.get_paused() {
+                audio_interface.toggle_pause();
+            }
+        }
+        Command::Next => audio_interface.skip_to_next(),
+        Command::Previous => audio_interface.skip_to_previous(),
+        Command::SetVol(volume) => audio_interface.set_volume(volume as f32 / 100.0),
+        Command::Add(uri) => match find_song(library, &uri) {
+            Some(file) => audio_interface.append_to_queue(&mut vec![file]),
+            None => return Err((50, String::from("No such song"))),
+        },
+        Command::Clear => audio_interface.hard_clear_queue(),
+    }
+    Ok(String::new())
+}
+
+// mpd's playlist is the current track followed by the queue
+fn playlist(audio_interface: &AudioInterface) -> Vec<&AudioFile> {
+    audio_interface
+        .get_currently_playing()
+        .iter()
+        .chain(audio_interface.get_queue().iter())
+        .collect()
+}
+
+fn status(audio_interface: &AudioInterface) -> String {
+    let repeat = audio_interface.get_repeat();
+    let mut status = format!(
+        "volume: {:.0}\nrepeat: {}\nrandom: {}\nsingle: {}\nconsume: 0\nplaylistlength: {}\n",
+        audio_interface.get_volume() * 100.0,
+        (repeat != RepeatMode::None) as u8,
+        audio_interface.get_shuffle() as u8,
+        (repeat == RepeatMode::One) as u8,
+        playlist(audio_interface).len()
+    );
+    match audio_interface.get_currently_playing() {
+        Some(file) => {
+            let elapsed = audio_interface.get_sink_length();
+            let duration = file.get_raw_duration();
+            let _ = write!(
+                status,
+                "state: {}\nsong: 0\nsongid: 0\ntime: {}:{}\nelapsed: {:.3}\nduration: {:.3}\n",
+                if audio_interface.get_paused() {
+                    "pause"
+                } else {
+                    "play"
+                },
+                elapsed,
+                duration as usize,
+                elapsed as f64,
+                duration
+            );
+        }
+        None => status.push_str("state: stop\n"),
+    }
+    status
+}
+
+fn song(file: &AudioFile, position: usize) -> String {
+    format!(
+        "file: {}\nTitle: {}\nArtist: {}\nAlbum: {}\nGenre: {}\nDate: {}\nTime: {}\nduration: {:.3}\nPos: {}\nId: {}\n",
+        file.get_path().display(),
+        file.get_title(),
+        file.get_artist(),
+        file.get_album(),
+        file.get_genre(),
+        file.get_year(),
+        file.get_raw_duration() as usize,
+        file.get_raw_duration(),
+        position,
+        position
+    )
+}
+
+// splits a command line into words, arguments may be double quoted with
+// backslash escapes
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => word.extend(chars.next()),
+                    Some(c) => word.push(c),
+                    None => return Err(String::from("missing closing '\"'")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// uris are either a full path or relative to one of the library folders
+fn find_song(library: &[AudioFile], uri: &str) -> Option<AudioFile> {
+    library
+        .iter()
+        .find(|file| {
+            let path = file.get_path();
+            path.to_str() == Some(uri) || (!uri.is_empty() && path.ends_with(uri))
+        })
+        .cloned()
+}
+
+// an ack is "ACK [error@command_list_num] {command} message"
+fn parse(words: &[String]) -> Result<Command, (u8, String)> {
+    let argument = words.get(1).map(String::as_str);
+    match (words[0].as_str(), argument) {
+        ("status", _) => Ok(Command::Status),
+        ("currentsong", _) => Ok(Command::CurrentSong),
+        ("play" | "playid", _) => Ok(Command::Play),
+        ("pause", None) => Ok(Command::Pause(None)),
+        ("pause", Some("0")) => Ok(Command::Pause(Some(false))),
+        ("pause", Some("1")) => Ok(Command::Pause(Some(true))),
+        ("stop", _) => Ok(Command::Stop),
+        ("next", _) => Ok(Command::Next),
+        ("previous", _) => Ok(Command::Previous),
+        ("setvol", Some(volume)) => match volume.parse::<u8>() {
+            Ok(volume) if volume <= 100 => Ok(Command::SetVol(volume)),
+            _ => Err((2, String::from("Invalid volume value"))),
+        },
+        ("add", Some(uri)) => Ok(Command::Add(uri.to_string())),
+        ("clear", _) => Ok(Command::Clear),
+        ("playlist" | "playlistinfo", _) => Ok(Command::Playlist),
+        ("setvol" | "add" | "pause", _) => Err((2, String::from("wrong number of arguments"))),
+        (command, _) => Err((5, format!("unknown command \"{}\"", command))),
+    }
+}
+
+fn handle_client(stream: TcpStream, sender: Sender<MpdRequest>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    if writeln!(writer, "{}", GREETING).is_err() {
+        return;
+    }
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let words = match tokenize(&line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) => words,
+            Err(e) => {
+                if writeln!(writer, "ACK [2@0] {{}} {}", e).is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+        let response = match words[0].as_str() {
+            "close" => return,
+            "ping" => Ok(String::new()),
+            _ => match parse(&words) {
+                Ok(command) => {
+                    let (reply, response) = mpsc::channel();
+                    if sender.send(MpdRequest { command, reply }).is_err() {
+                        return;
+                    }
+                    response
+                        .recv()
+                        .unwrap_or_else(|_| Err((52, String::from("shutting down"))))
+                }
+                Err(e) => Err(e),
+            },
+        };
+        let written = match response {
+            Ok(body) => writeln!(writer, "{}OK", body),
+            Err((code, message)) => {
+                writeln!(writer, "ACK [{}@0] {{{}}} {}", code, words[0], message)
+            }
+        };
+        if written.is_err() {
+            return;
+        }
+    }
+}
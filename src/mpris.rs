@@ -0,0 +1,173 @@
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex,
+};
+use zbus::{blocking::ConnectionBuilder, dbus_interface};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.rmus";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+// what media keys and desktop players can ask for
+pub enum MediaCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+}
+
+struct Root;
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[dbus_interface(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn identity(&self) -> String {
+        String::from("rmus")
+    }
+
+    #[dbus_interface(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[dbus_interface(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Player {
+    commands: Sender<MediaCommand>,
+    status: Arc<Mutex<&'static str>>,
+}
+
+impl Player {
+    fn send(&self, command: MediaCommand) {
+        // the ui is shutting down if nobody is listening
+        let _ = self.commands.send(command);
+    }
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn play(&self) {
+        self.send(MediaCommand::Play);
+    }
+
+    fn pause(&self) {
+        self.send(MediaCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(MediaCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(MediaCommand::Stop);
+    }
+
+    fn next(&self) {
+        self.send(MediaCommand::Next);
+    }
+
+    fn previous(&self) {
+        self.send(MediaCommand::Previous);
+    }
+
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> String {
+        self.status.lock().unwrap().to_string()
+    }
+
+    #[dbus_interface(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+}
+
+// registers rmus as an mpris2 player on the session bus, so media keys
+// handled by the desktop reach it even when the terminal doesn't pass them
+// on. calls come in on zbus' own thread and wait in a channel for the ui
+pub struct MprisServer {
+    // dropping the connection takes the player off the bus
+    _connection: zbus::blocking::Connection,
+    commands: Receiver<MediaCommand>,
+    status: Arc<Mutex<&'static str>>,
+}
+
+impl MprisServer {
+    pub fn start() -> Result<Self, zbus::Error> {
+        let (sender, commands) = mpsc::channel();
+        let status = Arc::new(Mutex::new("Stopped"));
+        let connection = ConnectionBuilder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, Root)?
+            .serve_at(
+                OBJECT_PATH,
+                Player {
+                    commands: sender,
+                    status: status.clone(),
+                },
+            )?
+            .build()?;
+        Ok(Self {
+            _connection: connection,
+            commands,
+            status,
+        })
+    }
+
+    pub fn pending(&self) -> Vec<MediaCommand> {
+        self.commands.try_iter().collect()
+    }
+
+    // one of "Playing", "Paused" or "Stopped"
+    pub fn set_status(&self, status: &'static str) {
+        *self.status.lock().unwrap() = status;
+    }
+}
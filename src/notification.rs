@@ -0,0 +1,101 @@
+use crate::audio::{art_cache_dir, AudioFile};
+use std::{io, path::PathBuf};
+
+#[derive(Debug, PartialEq)]
+pub struct TrackNotification {
+    pub summary: String,
+    pub body: String,
+    pub image: Option<PathBuf>,
+}
+
+impl TrackNotification {
+    pub fn new(file: &AudioFile, image: Option<PathBuf>) -> Self {
+        Self {
+            summary: file.get_title().clone(),
+            body: format!("{} - {}", file.get_artist(), file.get_album()),
+            image,
+        }
+    }
+}
+
+pub trait Notifier {
+    fn notify(&self, notification: &TrackNotification) -> Result<(), io::Error>;
+}
+
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, notification: &TrackNotification) -> Result<(), io::Error> {
+        let mut desktop_notification = notify_rust::Notification::new();
+        desktop_notification
+            .appname("rmus")
+            .summary(&notification.summary)
+            .body(&notification.body);
+        #[cfg(not(target_os = "macos"))]
+        if let Some(image) = &notification.image {
+            desktop_notification.image_path(&image.to_string_lossy());
+        }
+        desktop_notification.show().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+// notification daemons want a path to the image, the art cache has one
+pub fn notify_track(notifier: &dyn Notifier, file: &AudioFile) -> Result<(), io::Error> {
+    let image = file.embedded_art_path(&art_cache_dir());
+    notifier.notify(&TrackNotification::new(file, image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct MockNotifier {
+        sent: RefCell<Vec<TrackNotification>>,
+    }
+
+    impl Notifier for MockNotifier {
+        fn notify(&self, notification: &TrackNotification) -> Result<(), io::Error> {
+            self.sent.borrow_mut().push(TrackNotification {
+                summary: notification.summary.clone(),
+                body: notification.body.clone(),
+                image: notification.image.clone(),
+            });
+            Ok(())
+        }
+    }
+
+    fn track() -> AudioFile {
+        AudioFile::from_stream(
+            String::from("http://localhost/stream/1"),
+            String::from("Song"),
+            String::from("Artist"),
+            String::from("Album"),
+            2020,
+            180.0,
+        )
+    }
+
+    #[test]
+    fn notification_contains_track_details() {
+        let notifier = MockNotifier::default();
+        notify_track(&notifier, &track()).unwrap();
+        assert_eq!(
+            *notifier.sent.borrow(),
+            vec![TrackNotification {
+                summary: String::from("Song"),
+                body: String::from("Artist - Album"),
+                image: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn image_is_passed_through() {
+        let image = PathBuf::from("/tmp/cover.png");
+        let notification = TrackNotification::new(&track(), Some(image.clone()));
+        assert_eq!(notification.image, Some(image));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::audio::AudioFile;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufReader},
+    path::Path,
+};
+use url::Url;
+
+const XSPF_NAMESPACE: &str = "http://xspf.org/ns/0/";
+
+#[derive(Serialize, Deserialize)]
+struct XspfPlaylist {
+    #[serde(rename = "@version", default)]
+    version: String,
+    #[serde(rename = "@xmlns", default)]
+    xmlns: String,
+    title: Option<String>,
+    #[serde(rename = "trackList")]
+    track_list: XspfTrackList,
+}
+
+#[derive(Serialize, Deserialize)]
+struct XspfTrackList {
+    #[serde(default)]
+    track: Vec<XspfTrack>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct XspfTrack {
+    location: Option<String>,
+    title: Option<String>,
+    creator: Option<String>,
+    album: Option<String>,
+    // milliseconds
+    duration: Option<u64>,
+}
+
+// a named list of tracks that can be written to and read from playlist files
+pub struct Playlist {
+    pub title: String,
+    pub tracks: Vec<AudioFile>,
+}
+
+impl Playlist {
+    pub fn new(title: String, tracks: Vec<AudioFile>) -> Self {
+        Self { title, tracks }
+    }
+
+    pub fn to_xspf(&self, path: &Path) -> Result<(), io::Error> {
+        let playlist = XspfPlaylist {
+            version: String::from("1"),
+            xmlns: String::from(XSPF_NAMESPACE),
+            title: Some(self.title.clone()),
+            track_list: XspfTrackList {
+                track: self.tracks.iter().map(xspf_track).collect(),
+            },
+        };
+        let mut contents = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let mut serializer = quick_xml::se::Serializer::with_root(&mut contents, Some("playlist"))
+            .map_err(io::Error::other)?;
+        serializer.indent(' ', 2);
+        playlist.serialize(serializer).map_err(io::Error::other)?;
+        contents.push('\n');
+        std::fs::write(path, contents)
+    }
+
+    // local tracks that can no longer be read are left out, anything that
+    // isn't a file uri is treated as a stream
+    pub fn from_xspf(path: &Path) -> Result<Playlist, io::Error> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let playlist: XspfPlaylist = quick_xml::de::from_reader(reader)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // relative locations are relative to the playlist file
+        let base = std::fs::canonicalize(path)
+            .ok()
+            .and_then(|path| Url::from_file_path(path).ok());
+        let tracks = playlist
+            .track_list
+            .track
+            .into_iter()
+            .filter_map(|track| audio_file(track, base.as_ref()))
+            .collect();
+        let title = playlist.title.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        Ok(Playlist::new(title, tracks))
+    }
+
+    pub fn to_m3u(&self, path: &Path) -> Result<(), io::Error> {
+        let mut contents = String::from("#EXTM3U\n");
+        for file in &self.tracks {
+            let location = match file.get_stream_url() {
+                Some(url) => url.to_string(),
+                None => file.get_path().display().to_string(),
+            };
+            contents.push_str(&format!(
+                "#EXTINF:{},{} - {}\n{}\n",
+                file.get_raw_duration().round() as i64,
+                file.get_artist(),
+                file.get_title(),
+                location
+            ));
+        }
+        std::fs::write(path, contents)
+    }
+
+    // the title is the file name, #EXTINF lines only name streams since
+    // local files have their own tags
+    pub fn from_m3u(path: &Path) -> Result<Playlist, io::Error> {
+        let contents = std::fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut info = None;
+        let mut tracks = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+                info = Some(extinf.to_string());
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let info = info.take();
+            match Url::parse(line) {
+                Ok(url) if url.scheme() != "file" => {
+                    tracks.push(m3u_stream(url, info.as_deref()));
+                }
+                Ok(url) => {
+                    if let Ok(file_path) = url.to_file_path() {
+                        tracks
+                            .extend(AudioFile::new(&file_path.to_string_lossy().to_string()).ok());
+                    }
+                }
+                // relative paths are relative to the playlist file
+                Err(_) => tracks
+                    .extend(AudioFile::new(&base.join(line).to_string_lossy().to_string()).ok()),
+            }
+        }
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Playlist::new(title, tracks))
+    }
+}
+
+// #EXTINF:<seconds>,<artist> - <title>
+fn m3u_stream(url: Url, info: Option<&str>) -> AudioFile {
+    let (duration, name) = info
+        .and_then(|info| info.split_once(','))
+        .unwrap_or(("0", ""));
+    let (artist, title) = name.split_once(" - ").unwrap_or(("Unknown", name));
+    let title = match title.trim() {
+        "" => url.to_string(),
+        title => title.to_string(),
+    };
+    AudioFile::from_stream(
+        String::from(url),
+        title,
+        artist.trim().to_string(),
+        String::from("Unknown"),
+        0,
+        duration.trim().parse::<f64>().unwrap_or(0.0).max(0.0),
+    )
+}
+
+fn xspf_track(file: &AudioFile) -> XspfTrack {
+    let location = match file.get_stream_url() {
+        Some(url) => Some(url.to_string()),
+        None => std::fs::canonicalize(file.get_path())
+            .ok()
+            .and_then(|path| Url::from_file_path(path).ok())
+            .map(String::from),
+    };
+    XspfTrack {
+        location,
+        title: Some(file.get_title().clone()),
+        creator: Some(file.get_artist().clone()),
+        album: Some(file.get_album().clone()),
+        duration: Some((file.get_raw_duration() * 1000.0).round() as u64),
+    }
+}
+
+fn audio_file(track: XspfTrack, base: Option<&Url>) -> Option<AudioFile> {
+    let location = track.location?;
+    let url = match base {
+        Some(base) => base.join(location.trim()).ok()?,
+        None => Url::parse(location.trim()).ok()?,
+    };
+    if url.scheme() == "file" {
+        let path = url.to_file_path().ok()?;
+        return AudioFile::new(&path.to_string_lossy().to_string()).ok();
+    }
+    Some(AudioFile::from_stream(
+        String::from(url),
+        track.title.unwrap_or_else(|| String::from("Unknown")),
+        track.creator.unwrap_or_else(|| String::from("Unknown")),
+        track.album.unwrap_or_else(|| String::from("Unknown")),
+        0,
+        track.duration.unwrap_or(0) as f64 / 1000.0,
+    ))
+}
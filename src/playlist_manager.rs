@@ -0,0 +1,478 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    input::InputWidget,
+    library::LibraryWindow,
+    log,
+    log::Level,
+    playlist::Playlist,
+    settings::Settings,
+    ui::{centered_rect, render_popup, Window},
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    cell::RefCell,
+    io::{self, Stdout},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+enum ManagerPrompt {
+    // name of the playlist to create
+    Create(InputWidget),
+    Delete(PathBuf),
+}
+
+// the .m3u files in the playlist directory
+pub struct PlaylistManagerWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    library_window: Rc<RefCell<LibraryWindow>>,
+    playlists: Vec<PathBuf>,
+    state: ListState,
+    prompt: Option<ManagerPrompt>,
+    // the playlist opened with Enter, shown instead of the list until Esc
+    open: Option<PlaylistWindow>,
+    status: Option<String>,
+}
+
+impl PlaylistManagerWindow {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        library_window: Rc<RefCell<LibraryWindow>>,
+    ) -> Self {
+        let mut window = Self {
+            title: String::from("Playlists"),
+            settings,
+            audio_interface,
+            library_window,
+            playlists: Vec::new(),
+            state: ListState::default(),
+            prompt: None,
+            open: None,
+            status: None,
+        };
+        window.read_playlists();
+        window
+    }
+
+    fn read_playlists(&mut self) {
+        let dir = self.settings.borrow().playlist_dir.clone();
+        let mut playlists = std::fs::read_dir(dir)
+            .map(|dir| {
+                dir.filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.extension()
+                            .is_some_and(|ext| ext == "m3u" || ext == "m3u8")
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        playlists.sort();
+        self.playlists = playlists;
+        let selected = self.state.selected().unwrap_or(0);
+        self.state
+            .select(Some(selected.min(self.playlists.len().saturating_sub(1))));
+    }
+
+    fn selected_playlist(&self) -> Option<PathBuf> {
+        self.state
+            .selected()
+            .and_then(|i| self.playlists.get(i))
+            .cloned()
+    }
+
+    fn create(&mut self, name: &str) -> Result<PathBuf, io::Error> {
+        let dir = self.settings.borrow().playlist_dir.clone();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.m3u", name));
+        if path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "a playlist with that name already exists",
+            ));
+        }
+        Playlist::new(name.to_string(), Vec::new()).to_m3u(&path)?;
+        Ok(path)
+    }
+
+    // appends the queue to the end of the playlist file
+    fn add_queue(&self, path: &Path) -> Result<usize, io::Error> {
+        let mut playlist = Playlist::from_m3u(path)?;
+        let queue = self.audio_interface.borrow().get_queue().clone();
+        playlist.tracks.extend(queue.iter().cloned());
+        playlist.to_m3u(path)?;
+        Ok(queue.len())
+    }
+
+    fn enqueue(&self, path: &Path) -> Result<usize, io::Error> {
+        let mut tracks = Playlist::from_m3u(path)?.tracks;
+        let len = tracks.len();
+        self.audio_interface
+            .borrow_mut()
+            .append_to_queue(&mut tracks);
+        Ok(len)
+    }
+
+    fn handle_prompt_input(&mut self, key: KeyEvent) {
+        match (&mut self.prompt, key.code) {
+            (Some(_), KeyCode::Esc) => self.prompt = None,
+            (Some(ManagerPrompt::Create(name)), KeyCode::Enter) => {
+                let name = name.text().trim().to_string();
+                if name.is_empty() {
+                    return;
+                }
+                self.prompt = None;
+                self.status = Some(match self.create(&name) {
+                    Ok(path) => {
+                        self.read_playlists();
+                        let i = self.playlists.iter().position(|p| *p == path);
+                        self.state.select(i);
+                        format!("created {}", name)
+                    }
+                    Err(e) => format!("could not create {}: {}", name, e),
+                });
+            }
+            (Some(ManagerPrompt::Create(name)), code) => {
+                name.handle_key(code, key.modifiers);
+            }
+            (Some(ManagerPrompt::Delete(path)), KeyCode::Char('y')) => {
+                let path = path.clone();
+                self.prompt = None;
+                self.status = Some(match std::fs::remove_file(&path) {
+                    Ok(()) => format!("deleted {}", playlist_name(&path)),
+                    Err(e) => format!("could not delete {}: {}", playlist_name(&path), e),
+                });
+                self.read_playlists();
+            }
+            (Some(ManagerPrompt::Delete(_)), KeyCode::Char('n')) => self.prompt = None,
+            _ => {}
+        }
+    }
+
+    fn draw_prompt(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        if self.prompt.is_none() {
+            return;
+        }
+        let popup = centered_rect(60, 20, area);
+        match &self.prompt {
+            Some(ManagerPrompt::Create(name)) => render_popup(
+                f,
+                popup,
+                "New Playlist - Name, Enter to create",
+                |inner, f| {
+                    name.draw(
+                        Block::default(),
+                        Style::default().fg(Color::Yellow),
+                        inner,
+                        f,
+                    )
+                },
+            ),
+            Some(ManagerPrompt::Delete(path)) => {
+                let prompt = Paragraph::new(format!("Delete {}? (y/n)", playlist_name(path)))
+                    .style(Style::default().fg(Color::Yellow));
+                render_popup(f, popup, "Delete Playlist", |inner, f| {
+                    f.render_widget(prompt, inner)
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+fn playlist_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+impl Window for PlaylistManagerWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        if let Some(open) = &mut self.open {
+            return open.draw(area, f);
+        }
+        let mut items = self
+            .playlists
+            .iter()
+            .map(|path| ListItem::new(playlist_name(path)))
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            items.push(ListItem::new("No playlists yet, C to create one"));
+        }
+        let title = match &self.status {
+            Some(status) => format!("{} - {}", self.get_title(), status),
+            None => format!(
+                "{} - Enter to open, C create, d delete, a add the queue, p play",
+                self.get_title()
+            ),
+        };
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut self.state);
+        self.draw_prompt(area, f);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if self.prompt.is_some() {
+            self.handle_prompt_input(KeyEvent::from(key));
+            return Ok(());
+        }
+        if let Some(open) = &mut self.open {
+            if key == KeyCode::Esc {
+                self.open = None;
+                return Ok(());
+            }
+            return open.handle_input(key);
+        }
+        let len = self.playlists.len().max(1);
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => self.state.select(Some((selected + len - 1) % len)),
+            KeyCode::Down => self.state.select(Some((selected + 1) % len)),
+            // n skips to the next track everywhere
+            KeyCode::Char('C') => self.prompt = Some(ManagerPrompt::Create(InputWidget::new())),
+            KeyCode::Char('d') => {
+                self.prompt = self.selected_playlist().map(ManagerPrompt::Delete);
+            }
+            KeyCode::Enter => {
+                if let Some(path) = self.selected_playlist() {
+                    match Playlist::from_m3u(&path) {
+                        Ok(playlist) => {
+                            self.open = Some(PlaylistWindow::new(
+                                playlist,
+                                path,
+                                self.audio_interface.clone(),
+                                self.library_window.clone(),
+                            ))
+                        }
+                        Err(e) => self.status = Some(format!("could not open: {}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('a') => {
+                if let Some(path) = self.selected_playlist() {
+                    self.status = Some(match self.add_queue(&path) {
+                        Ok(len) => format!("added {} tracks to {}", len, playlist_name(&path)),
+                        Err(e) => format!("could not add the queue: {}", e),
+                    });
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(path) = self.selected_playlist() {
+                    self.status = Some(match self.enqueue(&path) {
+                        Ok(len) => format!("queued {} tracks from {}", len, playlist_name(&path)),
+                        Err(e) => format!("could not queue: {}", e),
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // the prompts take every key, the name can contain global keys like q
+    fn is_typing(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<(), io::Error> {
+        if self.prompt.is_some() {
+            self.handle_prompt_input(key);
+            return Ok(());
+        }
+        match &mut self.open {
+            Some(open) if key.code != KeyCode::Esc => open.handle_key(key),
+            _ => self.handle_input(key.code),
+        }
+    }
+}
+
+// the tracks of one playlist, edited in place. every change is written
+// to the file straight away
+pub struct PlaylistWindow {
+    playlist: Playlist,
+    path: PathBuf,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    // a adds the track selected there
+    library_window: Rc<RefCell<LibraryWindow>>,
+    state: ListState,
+    // set while the last change couldn't be written
+    unsaved: bool,
+}
+
+impl PlaylistWindow {
+    pub fn new(
+        playlist: Playlist,
+        path: PathBuf,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        library_window: Rc<RefCell<LibraryWindow>>,
+    ) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            playlist,
+            path,
+            audio_interface,
+            library_window,
+            state,
+            unsaved: false,
+        }
+    }
+
+    fn track_name(i: usize, file: &AudioFile) -> String {
+        format!(
+            "{}. {} - {} ({})",
+            i + 1,
+            file.get_title(),
+            file.get_artist(),
+            file.get_duration()
+        )
+    }
+
+    fn save(&mut self) {
+        match self.playlist.to_m3u(&self.path) {
+            Ok(()) => self.unsaved = false,
+            Err(e) => {
+                log!(Level::Warn, "could not save {}: {}", self.path.display(), e);
+                self.unsaved = true;
+            }
+        }
+    }
+
+    // swaps the selected track with its neighbour, the selection moves along
+    fn move_selected(&mut self, up: bool) {
+        let Some(selected) = self.state.selected() else {
+            return;
+        };
+        let other = match up {
+            true if selected > 0 => selected - 1,
+            false if selected + 1 < self.playlist.tracks.len() => selected + 1,
+            _ => return,
+        };
+        self.playlist.tracks.swap(selected, other);
+        self.state.select(Some(other));
+        self.save();
+    }
+
+    fn delete_selected(&mut self) {
+        let Some(selected) = self.state.selected() else {
+            return;
+        };
+        if selected >= self.playlist.tracks.len() {
+            return;
+        }
+        self.playlist.tracks.remove(selected);
+        self.state.select(Some(
+            selected.min(self.playlist.tracks.len().saturating_sub(1)),
+        ));
+        self.save();
+    }
+
+    // goes in after the selected track
+    fn add_library_selection(&mut self) {
+        let Some(track) = self.library_window.borrow().selected_track() else {
+            return;
+        };
+        let i = match self.playlist.tracks.len() {
+            0 => 0,
+            len => (self.state.selected().unwrap_or(len - 1) + 1).min(len),
+        };
+        self.playlist.tracks.insert(i, track);
+        self.state.select(Some(i));
+        self.save();
+    }
+}
+
+impl Window for PlaylistWindow {
+    fn get_title(&self) -> String {
+        format!(
+            "{}{} - {} tracks, Enter to queue a track, Shift+Up/Down to move, d to remove, a to add the library selection, Esc to go back",
+            self.playlist.title,
+            if self.unsaved { " [*]" } else { "" },
+            self.playlist.tracks.len()
+        )
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let items = self
+            .playlist
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, file)| ListItem::new(Self::track_name(i, file)))
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(self.get_title())
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut self.state);
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<(), io::Error> {
+        match key.code {
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => self.move_selected(true),
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.move_selected(false)
+            }
+            code => return self.handle_input(code),
+        }
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if key == KeyCode::Char('a') {
+            self.add_library_selection();
+            return Ok(());
+        }
+        let len = self.playlist.tracks.len();
+        if len == 0 {
+            return Ok(());
+        }
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => self.state.select(Some((selected + len - 1) % len)),
+            KeyCode::Down => self.state.select(Some((selected + 1) % len)),
+            KeyCode::Enter => {
+                let mut track = vec![self.playlist.tracks[selected].clone()];
+                self.audio_interface
+                    .borrow_mut()
+                    .append_to_queue(&mut track);
+            }
+            KeyCode::Char('d') => self.delete_selected(),
+            _ => {}
+        }
+        Ok(())
+    }
+}
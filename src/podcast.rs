@@ -0,0 +1,385 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    library::seconds_to_formatted_time,
+    settings::Settings,
+    ui::{focus_border_style, Window},
+};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    io::{self, ErrorKind, Stdout},
+    rc::Rc,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Episode {
+    pub title: String,
+    pub url: String,
+    pub duration: f64,
+    pub played_position: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Feed {
+    pub url: String,
+    pub title: String,
+    pub episodes: Vec<Episode>,
+}
+
+impl Feed {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            title: url.to_string(),
+            episodes: Vec::new(),
+        }
+    }
+
+    pub fn fetch(&mut self) -> Result<(), io::Error> {
+        let bytes = reqwest::blocking::get(&self.url)
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())
+            .map_err(io::Error::other)?;
+        let channel = rss::Channel::read_from(&bytes[..])
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        self.title = channel.title().to_string();
+        self.episodes = channel
+            .items()
+            .iter()
+            .filter_map(|item| {
+                let url = item.enclosure()?.url().to_string();
+                // keep the progress of episodes we already knew about
+                let played_position = self
+                    .episodes
+                    .iter()
+                    .find(|episode| episode.url == url)
+                    .map(|episode| episode.played_position)
+                    .unwrap_or(0.0);
+                Some(Episode {
+                    title: item.title().unwrap_or("Unknown").to_string(),
+                    url,
+                    duration: item
+                        .itunes_ext()
+                        .and_then(|ext| ext.duration())
+                        .map(parse_duration)
+                        .unwrap_or(0.0),
+                    played_position,
+                })
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+// itunes durations are either plain seconds or [HH:]MM:SS
+fn parse_duration(duration: &str) -> f64 {
+    duration.split(':').fold(0.0, |total, part| {
+        total * 60.0 + part.trim().parse::<f64>().unwrap_or(0.0)
+    })
+}
+
+pub struct PodcastWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    feed_state: ListState,
+    episode_state: ListState,
+    selected_window: usize,
+    adding: bool,
+    input: String,
+    status: String,
+    // url of the episode last handed to the audio interface
+    playing: Option<String>,
+}
+
+impl PodcastWindow {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+    ) -> Self {
+        let mut feed_state = ListState::default();
+        feed_state.select(Some(0));
+        let mut episode_state = ListState::default();
+        episode_state.select(Some(0));
+        Self {
+            title: String::from("Podcasts"),
+            settings,
+            audio_interface,
+            feed_state,
+            episode_state,
+            selected_window: 0,
+            adding: false,
+            input: String::new(),
+            status: String::from("Press a to subscribe to a feed, r to refresh, d to remove"),
+            playing: None,
+        }
+    }
+
+    fn feed_count(&self) -> usize {
+        self.settings.borrow().podcast_feeds.len()
+    }
+
+    fn episode_count(&self) -> usize {
+        let settings = self.settings.borrow();
+        self.feed_state
+            .selected()
+            .and_then(|i| settings.podcast_feeds.get(i))
+            .map(|feed| feed.episodes.len())
+            .unwrap_or(0)
+    }
+
+    pub fn next(&mut self) {
+        let (len, state) = match self.selected_window {
+            0 => (self.feed_count(), &mut self.feed_state),
+            _ => (self.episode_count(), &mut self.episode_state),
+        };
+        let i = match state.selected() {
+            Some(i) => {
+                if i + 1 >= len {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        state.select(Some(i));
+        if self.selected_window == 0 {
+            self.episode_state.select(Some(0));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        let (len, state) = match self.selected_window {
+            0 => (self.feed_count(), &mut self.feed_state),
+            _ => (self.episode_count(), &mut self.episode_state),
+        };
+        let i = match state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    len.saturating_sub(1)
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        state.select(Some(i));
+        if self.selected_window == 0 {
+            self.episode_state.select(Some(0));
+        }
+    }
+
+    fn subscribe(&mut self) {
+        let url = std::mem::take(&mut self.input);
+        let mut feed = Feed::new(url.trim());
+        match feed.fetch() {
+            Ok(()) => {
+                self.status = format!("Subscribed to {}", feed.title);
+                self.settings.borrow_mut().podcast_feeds.push(feed);
+            }
+            Err(e) => self.status = format!("Failed to fetch feed: {}", e),
+        }
+    }
+
+    fn refresh(&mut self) {
+        let Some(i) = self.feed_state.selected() else {
+            return;
+        };
+        let mut settings = self.settings.borrow_mut();
+        if let Some(feed) = settings.podcast_feeds.get_mut(i) {
+            self.status = match feed.fetch() {
+                Ok(()) => format!("{} episodes in {}", feed.episodes.len(), feed.title),
+                Err(e) => format!("Failed to fetch feed: {}", e),
+            };
+        }
+    }
+
+    fn remove(&mut self) {
+        if let Some(i) = self.feed_state.selected() {
+            let mut settings = self.settings.borrow_mut();
+            if i < settings.podcast_feeds.len() {
+                let feed = settings.podcast_feeds.remove(i);
+                self.status = format!("Unsubscribed from {}", feed.title);
+                self.feed_state.select(Some(i.saturating_sub(1)));
+            }
+        }
+    }
+
+    fn play_selected(&mut self) {
+        self.save_position();
+        let (Some(f), Some(e)) = (self.feed_state.selected(), self.episode_state.selected()) else {
+            return;
+        };
+        let settings = self.settings.borrow();
+        let Some(feed) = settings.podcast_feeds.get(f) else {
+            return;
+        };
+        if let Some(episode) = feed.episodes.get(e) {
+            let audio_file = AudioFile::from_stream(
+                episode.url.clone(),
+                episode.title.clone(),
+                feed.title.clone(),
+                feed.title.clone(),
+                0,
+                episode.duration,
+            );
+            self.playing = Some(episode.url.clone());
+            let mut audio_interface = self.audio_interface.borrow_mut();
+            audio_interface.hard_clear_queue();
+            audio_interface.append_to_queue(&mut vec![audio_file]);
+        }
+    }
+
+    // writes the playback position of the current episode back to the settings
+    fn save_position(&mut self) {
+        let Some(url) = &self.playing else {
+            return;
+        };
+        let audio_interface = self.audio_interface.borrow();
+        let still_playing = audio_interface
+            .get_currently_playing()
+            .as_ref()
+            .and_then(|file| file.get_stream_url())
+            == Some(url.as_str());
+        if !still_playing {
+            return;
+        }
+        let position = audio_interface.get_sink_length() as f64;
+        for feed in self.settings.borrow_mut().podcast_feeds.iter_mut() {
+            for episode in feed.episodes.iter_mut().filter(|e| &e.url == url) {
+                episode.played_position = position;
+            }
+        }
+    }
+}
+
+impl Window for PodcastWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+            .split(area);
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(rows[0]);
+        let settings = self.settings.borrow();
+        let feeds = settings
+            .podcast_feeds
+            .iter()
+            .map(|feed| ListItem::new(feed.title.as_str()))
+            .collect::<Vec<_>>();
+        let episodes = self
+            .feed_state
+            .selected()
+            .and_then(|i| settings.podcast_feeds.get(i))
+            .map(|feed| {
+                feed.episodes
+                    .iter()
+                    .map(|episode| {
+                        ListItem::new(format!(
+                            "{} [{} / {}]",
+                            episode.title,
+                            seconds_to_formatted_time(episode.played_position as usize),
+                            seconds_to_formatted_time(episode.duration as usize)
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let feed_list = List::new(feeds)
+            .block(
+                Block::default()
+                    .title("Feeds")
+                    .borders(Borders::ALL)
+                    .border_style(focus_border_style(self.selected_window == 0)),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let episode_list = List::new(episodes)
+            .block(
+                Block::default()
+                    .title("Episodes")
+                    .borders(Borders::ALL)
+                    .border_style(focus_border_style(self.selected_window == 1)),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let status = if self.adding {
+            Paragraph::new(self.input.clone())
+                .block(Block::default().title("Feed URL").borders(Borders::ALL))
+                .style(Style::default().fg(Color::Yellow))
+        } else {
+            Paragraph::new(self.status.clone())
+                .block(Block::default().borders(Borders::ALL))
+                .style(Style::default().fg(Color::Green))
+        };
+        f.render_stateful_widget(feed_list, chunks[0], &mut self.feed_state);
+        f.render_stateful_widget(episode_list, chunks[1], &mut self.episode_state);
+        f.render_widget(status, rows[1]);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if self.adding {
+            match key {
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Enter => {
+                    self.adding = false;
+                    self.subscribe();
+                }
+                KeyCode::Esc => {
+                    self.adding = false;
+                    self.input.clear();
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        match key {
+            KeyCode::Up => self.previous(),
+            KeyCode::Down => self.next(),
+            KeyCode::Left => self.selected_window = 0,
+            KeyCode::Right => self.selected_window = 1,
+            KeyCode::Char('a') => self.adding = true,
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Char('d') => self.remove(),
+            KeyCode::Enter => match self.selected_window {
+                0 => self.selected_window = 1,
+                _ => self.play_selected(),
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        self.adding
+    }
+
+    fn on_quit(&mut self) {
+        self.save_position();
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    audio::AudioInterface,
+    playlist::Playlist,
+    tidal::{TidalError, TidalSession},
+    ui::{centered_rect, render_popup, Window},
+};
+use crossterm::event::KeyCode;
+use std::{
+    cell::RefCell,
+    io::{self, Stdout},
+    path::Path,
+    rc::Rc,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+// asks where to save the queue as an xspf playlist, or which one to load into
+// it, or what to name the tidal playlist the queue is saved as
+enum PlaylistPrompt {
+    Export(String),
+    Import(String),
+    Tidal(String),
+}
+
+fn default_playlist_path() -> String {
+    format!("{}", home::home_dir().unwrap().join("queue.xspf").display())
+}
+
+pub struct QueueWindow {
+    title: String,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    tidal_session: Rc<RefCell<TidalSession>>,
+    state: ListState,
+    // where the track being moved started, so Esc can put it back
+    move_origin: Option<usize>,
+    prompt: Option<PlaylistPrompt>,
+    // result of the last export or import, shown in the title
+    status: Option<String>,
+}
+
+impl QueueWindow {
+    pub fn new(
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        tidal_session: Rc<RefCell<TidalSession>>,
+    ) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            title: String::from("Queue"),
+            audio_interface,
+            tidal_session,
+            state,
+            move_origin: None,
+            prompt: None,
+            status: None,
+        }
+    }
+
+    fn queue_len(&self) -> usize {
+        self.audio_interface.borrow().get_queue().len()
+    }
+
+    pub fn next(&mut self) {
+        let len = self.queue_len();
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < len => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let len = self.queue_len();
+        let i = match self.state.selected() {
+            Some(i) if i > 0 => i - 1,
+            _ => len.saturating_sub(1),
+        };
+        self.state.select(Some(i));
+    }
+
+    // moves the selected track one place up or down, keeping it selected
+    fn move_selected(&mut self, up: bool) {
+        let Some(i) = self.state.selected() else {
+            return;
+        };
+        let j = if up {
+            match i.checked_sub(1) {
+                Some(j) => j,
+                None => return,
+            }
+        } else if i + 1 < self.queue_len() {
+            i + 1
+        } else {
+            return;
+        };
+        self.audio_interface.borrow_mut().swap_queue_items(i, j);
+        self.state.select(Some(j));
+    }
+
+    pub fn export_to_xspf(&self, path: &Path) -> Result<usize, io::Error> {
+        let tracks = self
+            .audio_interface
+            .borrow()
+            .get_queue()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let len = tracks.len();
+        Playlist::new(String::from("Queue"), tracks).to_xspf(path)?;
+        Ok(len)
+    }
+
+    pub fn import_from_xspf(&self, path: &Path) -> Result<usize, io::Error> {
+        let mut playlist = Playlist::from_xspf(path)?;
+        let len = playlist.tracks.len();
+        self.audio_interface
+            .borrow_mut()
+            .append_to_queue(&mut playlist.tracks);
+        Ok(len)
+    }
+
+    // only the tracks that were streamed from tidal can go in, returns how many did
+    pub fn save_to_tidal(&self, name: &str) -> Result<usize, TidalError> {
+        let track_ids = self
+            .audio_interface
+            .borrow()
+            .get_queue()
+            .iter()
+            .filter_map(|file| file.get_tidal_id())
+            .collect::<Vec<_>>();
+        let mut session = self.tidal_session.borrow_mut();
+        let playlist_id = session.create_playlist(name)?;
+        if !track_ids.is_empty() {
+            session.add_tracks_to_playlist(&playlist_id, &track_ids)?;
+        }
+        Ok(track_ids.len())
+    }
+
+    fn handle_prompt_input(&mut self, key: KeyCode) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        let (PlaylistPrompt::Export(path)
+        | PlaylistPrompt::Import(path)
+        | PlaylistPrompt::Tidal(path)) = prompt;
+        match key {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Char(c) => path.push(c),
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Enter => {
+                self.status = Some(match self.prompt.take() {
+                    Some(PlaylistPrompt::Export(path)) => {
+                        match self.export_to_xspf(Path::new(&path)) {
+                            Ok(len) => format!("saved {} tracks to {}", len, path),
+                            Err(e) => format!("export failed: {}", e),
+                        }
+                    }
+                    Some(PlaylistPrompt::Import(path)) => {
+                        match self.import_from_xspf(Path::new(&path)) {
+                            Ok(len) => format!("added {} tracks from {}", len, path),
+                            Err(e) => format!("import failed: {}", e),
+                        }
+                    }
+                    Some(PlaylistPrompt::Tidal(name)) => match self.save_to_tidal(&name) {
+                        Ok(len) => format!("saved {} Tidal tracks to {}", len, name),
+                        Err(e) => format!("Tidal playlist failed: {}", e),
+                    },
+                    None => return,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_prompt(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let (title, path) = match &self.prompt {
+            Some(PlaylistPrompt::Export(path)) => ("Save Queue as XSPF - Enter to save", path),
+            Some(PlaylistPrompt::Import(path)) => ("Load XSPF Playlist - Enter to add", path),
+            Some(PlaylistPrompt::Tidal(name)) => ("Save Queue as Tidal Playlist - Name", name),
+            None => return,
+        };
+        let prompt = Paragraph::new(format!("{}_", path)).style(Style::default().fg(Color::Yellow));
+        render_popup(f, centered_rect(60, 20, area), title, |inner, f| {
+            f.render_widget(prompt, inner)
+        });
+    }
+
+    fn cancel_move(&mut self, origin: usize) {
+        while let Some(i) = self.state.selected() {
+            if i == origin || i >= self.queue_len() {
+                break;
+            }
+            self.move_selected(i > origin);
+        }
+    }
+}
+
+impl Window for QueueWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let audio_interface = self.audio_interface.borrow();
+        let queue = audio_interface.get_queue();
+        // the queue shrinks as tracks finish playing
+        if self.state.selected().unwrap_or(0) >= queue.len() {
+            self.state.select(Some(queue.len().saturating_sub(1)));
+        }
+        let items = queue
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                let prefix = if self.move_origin.is_some() && self.state.selected() == Some(i) {
+                    "✦ "
+                } else {
+                    ""
+                };
+                ListItem::new(format!(
+                    "{}{} - {} ({})",
+                    prefix,
+                    file.get_title(),
+                    file.get_artist(),
+                    file.get_duration()
+                ))
+            })
+            .collect::<Vec<_>>();
+        let title = match self.move_origin {
+            Some(_) => format!(
+                "{} - moving, Space to drop, Esc to cancel",
+                self.get_title()
+            ),
+            None => match &self.status {
+                Some(status) => format!("{} - {}", self.get_title(), status),
+                None => self.get_title(),
+            },
+        };
+        let queue_list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(queue_list, area, &mut self.state);
+        drop(audio_interface);
+        self.draw_prompt(area, f);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if self.prompt.is_some() {
+            self.handle_prompt_input(key);
+            return Ok(());
+        }
+        match (self.move_origin, key) {
+            (None, KeyCode::Up) => self.previous(),
+            (None, KeyCode::Down) => self.next(),
+            (None, KeyCode::Char(' ')) if self.queue_len() > 0 => {
+                self.move_origin = self.state.selected();
+            }
+            (None, KeyCode::Char('E')) => {
+                self.prompt = Some(PlaylistPrompt::Export(default_playlist_path()));
+            }
+            (None, KeyCode::Char('I')) => {
+                self.prompt = Some(PlaylistPrompt::Import(default_playlist_path()));
+            }
+            // S is taken by smart shuffle everywhere
+            (None, KeyCode::Char('T')) => self.prompt = Some(PlaylistPrompt::Tidal(String::new())),
+            (Some(_), KeyCode::Up) => self.move_selected(true),
+            (Some(_), KeyCode::Down) => self.move_selected(false),
+            (Some(_), KeyCode::Char(' ')) => self.move_origin = None,
+            (Some(origin), KeyCode::Esc) => {
+                self.cancel_move(origin);
+                self.move_origin = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        self.prompt.is_some()
+    }
+}
@@ -0,0 +1,211 @@
+use crate::log;
+use crate::log::Level;
+use rodio::Source;
+use rubato::{
+    calculate_cutoff, Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+    WindowFunction,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
+
+// input frames handed to rubato at a time
+const CHUNK_FRAMES: usize = 1024;
+
+// how tracks are converted when their sample rate isn't the device's
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ResamplerQuality {
+    // rodio's own linear interpolation, cheap but can alias
+    #[default]
+    Linear,
+    Sinc64,
+    Sinc256,
+}
+
+impl ResamplerQuality {
+    pub fn next(self) -> Self {
+        match self {
+            ResamplerQuality::Linear => ResamplerQuality::Sinc64,
+            ResamplerQuality::Sinc64 => ResamplerQuality::Sinc256,
+            ResamplerQuality::Sinc256 => ResamplerQuality::Linear,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResamplerQuality::Linear => "Linear",
+            ResamplerQuality::Sinc64 => "Sinc 64",
+            ResamplerQuality::Sinc256 => "Sinc 256",
+        }
+    }
+
+    fn parameters(&self) -> Option<SincInterpolationParameters> {
+        let (sinc_len, oversampling_factor, interpolation) = match self {
+            ResamplerQuality::Linear => return None,
+            ResamplerQuality::Sinc64 => (64, 128, SincInterpolationType::Linear),
+            ResamplerQuality::Sinc256 => (256, 256, SincInterpolationType::Cubic),
+        };
+        let window = WindowFunction::BlackmanHarris2;
+        Some(SincInterpolationParameters {
+            sinc_len,
+            f_cutoff: calculate_cutoff(sinc_len, window),
+            oversampling_factor,
+            interpolation,
+            window,
+        })
+    }
+}
+
+// converts the source to the output rate itself, so rodio has nothing left
+// to resample. linear quality, matching rates or a resampler that can't be
+// built leave the source as it is
+pub fn resample<S>(
+    source: S,
+    quality: ResamplerQuality,
+    output_rate: u32,
+) -> Box<dyn Source<Item = f32> + Send>
+where
+    S: Source<Item = f32> + Send + 'static,
+{
+    let Some(parameters) = quality.parameters() else {
+        return Box::new(source);
+    };
+    if source.sample_rate() == output_rate || source.sample_rate() == 0 {
+        return Box::new(source);
+    }
+    match SincResampler::new(source, parameters, output_rate) {
+        Ok(resampler) => Box::new(resampler),
+        Err((source, e)) => {
+            log!(Level::Warn, "falling back to linear resampling: {}", e);
+            Box::new(source)
+        }
+    }
+}
+
+struct SincResampler<I> {
+    input: I,
+    channels: usize,
+    output_rate: u32,
+    ratio: f64,
+    resampler: SincFixedIn<f32>,
+    // interleaved samples ready to be played
+    output: VecDeque<f32>,
+    // output frames still to drop, rubato's output starts late by its filter delay
+    delay: usize,
+    frames_in: usize,
+    frames_out: usize,
+    input_done: bool,
+}
+
+impl<I: Source<Item = f32>> SincResampler<I> {
+    fn new(
+        input: I,
+        parameters: SincInterpolationParameters,
+        output_rate: u32,
+    ) -> Result<Self, (I, rubato::ResamplerConstructionError)> {
+        let channels = input.channels().max(1) as usize;
+        let ratio = output_rate as f64 / input.sample_rate() as f64;
+        let resampler = match SincFixedIn::new(ratio, 1.0, parameters, CHUNK_FRAMES, channels) {
+            Ok(resampler) => resampler,
+            Err(e) => return Err((input, e)),
+        };
+        Ok(Self {
+            input,
+            channels,
+            output_rate,
+            ratio,
+            delay: resampler.output_delay(),
+            resampler,
+            output: VecDeque::new(),
+            frames_in: 0,
+            frames_out: 0,
+            input_done: false,
+        })
+    }
+
+    // resamples the next chunk, false once everything has been handed out
+    fn refill(&mut self) -> bool {
+        if self.input_done {
+            return false;
+        }
+        let frames = self.resampler.input_frames_next();
+        let mut chunk = vec![Vec::with_capacity(frames); self.channels];
+        'read: for _ in 0..frames {
+            for channel in chunk.iter_mut() {
+                match self.input.next() {
+                    Some(sample) => channel.push(sample),
+                    None => break 'read,
+                }
+            }
+        }
+        // a frame cut short at the end is dropped
+        let read = chunk.iter().map(Vec::len).min().unwrap_or(0);
+        chunk.iter_mut().for_each(|channel| channel.truncate(read));
+        self.frames_in += read;
+        let resampled = if read == frames {
+            self.resampler.process(&chunk, None)
+        } else {
+            // the last chunk, then one more to push out what the filter held back
+            self.input_done = true;
+            self.resampler
+                .process_partial(Some(&chunk), None)
+                .and_then(|mut resampled| {
+                    let tail = self.resampler.process_partial::<Vec<f32>>(None, None)?;
+                    resampled
+                        .iter_mut()
+                        .zip(tail)
+                        .for_each(|(channel, tail)| channel.extend(tail));
+                    Ok(resampled)
+                })
+        };
+        let Ok(resampled) = resampled else {
+            self.input_done = true;
+            return false;
+        };
+        let expected = (self.frames_in as f64 * self.ratio).ceil() as usize;
+        for frame in 0..resampled.first().map(Vec::len).unwrap_or(0) {
+            if self.delay > 0 {
+                self.delay -= 1;
+                continue;
+            }
+            if self.input_done && self.frames_out >= expected {
+                break;
+            }
+            self.output
+                .extend(resampled.iter().map(|channel| channel[frame]));
+            self.frames_out += 1;
+        }
+        true
+    }
+}
+
+impl<I: Source<Item = f32>> Iterator for SincResampler<I> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.output.is_empty() {
+            if !self.refill() {
+                return None;
+            }
+        }
+        self.output.pop_front()
+    }
+}
+
+impl<I: Source<Item = f32>> Source for SincResampler<I> {
+    // the channel count and rate are fixed for the whole source
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
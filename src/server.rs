@@ -0,0 +1,172 @@
+use crate::audio::{AudioFile, AudioInterface};
+use serde::Serialize;
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+use tiny_http::{Header, Method, Response, Server};
+
+// requests that need the audio interface or the library, which only live on
+// the ui thread
+enum Action {
+    Library,
+    Status,
+    // an index into the library as it is when the request is handled
+    Play(usize),
+    Pause,
+    Next,
+    Seek(f64),
+    Queue,
+    ClearQueue,
+}
+
+struct ApiRequest {
+    action: Action,
+    reply: Sender<(u16, String)>,
+}
+
+// serves the library and playback controls over http. like the ipc socket,
+// the listener thread forwards anything touching playback to the ui thread
+pub struct ApiServer {
+    requests: Receiver<ApiRequest>,
+}
+
+impl ApiServer {
+    // only reachable from this machine unless lan is set, which needs a token
+    pub fn start(port: u16, lan: bool, token: Option<String>) -> Result<Self, io::Error> {
+        let token = token.filter(|token| !token.is_empty());
+        if lan && token.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "server_lan needs a server_token",
+            ));
+        }
+        let host = if lan { "0.0.0.0" } else { "127.0.0.1" };
+        let server = Server::http((host, port)).map_err(io::Error::other)?;
+        let authorization = token.map(|token| format!("Bearer {}", token));
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let authorized = authorization.as_ref().is_none_or(|authorization| {
+                    request.headers().iter().any(|header| {
+                        header.field.equiv("Authorization") && header.value == authorization[..]
+                    })
+                });
+                let (status, body) = if authorized {
+                    route(request.method(), request.url(), &sender)
+                } else {
+                    error(401, "missing or wrong token")
+                };
+                let response = Response::from_string(body)
+                    .with_status_code(status)
+                    .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+                let _ = request.respond(response);
+            }
+        });
+        Ok(Self { requests })
+    }
+
+    // library is the library window's track list, so ids follow rescans
+    pub fn handle(&self, audio_interface: &mut AudioInterface, library: &[AudioFile]) {
+        for request in self.requests.try_iter() {
+            let _ = request
+                .reply
+                .send(run(request.action, audio_interface, library));
+        }
+    }
+}
+
+fn run(
+    action: Action,
+    audio_interface: &mut AudioInterface,
+    library: &[AudioFile],
+) -> (u16, String) {
+    let response = match action {
+        Action::Library => return json(200, library),
+        Action::Queue => return json(200, audio_interface.get_queue()),
+        Action::Status => Ok(()),
+        Action::Play(id) => {
+            let Some(file) = library.get(id) else {
+                return error(404, "no such track");
+            };
+            audio_interface.hard_clear_queue();
+            audio_interface.append_to_queue(&mut vec![file.clone()]);
+            Ok(())
+        }
+        Action::Pause => {
+            audio_interface.toggle_pause();
+            Ok(())
+        }
+        Action::Next => {
+            audio_interface.skip_to_next();
+            Ok(())
+        }
+        Action::Seek(seconds) => audio_interface.seek(seconds),
+        Action::ClearQueue => {
+            audio_interface.clear_queue();
+            Ok(())
+        }
+    };
+    match response {
+        Ok(()) => (200, status(audio_interface).to_string()),
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+// what's playing, answered to status requests here and on the ipc socket
+pub fn status(audio_interface: &AudioInterface) -> serde_json::Value {
+    let playing = audio_interface.get_currently_playing();
+    serde_json::json!({
+        "playing": playing.is_some(),
+        "paused": audio_interface.get_paused(),
+        "title": playing.as_ref().map(|file| file.get_title()),
+        "artist": playing.as_ref().map(|file| file.get_artist()),
+        "album": playing.as_ref().map(|file| file.get_album()),
+        "position": audio_interface.get_sink_length(),
+        "duration": playing.as_ref().map(|file| file.get_raw_duration()),
+        "volume": audio_interface.get_volume(),
+        "shuffle": audio_interface.get_shuffle(),
+        "repeat": audio_interface.get_repeat().name(),
+    })
+}
+
+fn json(status: u16, value: impl Serialize) -> (u16, String) {
+    (status, serde_json::to_string(&value).unwrap())
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    json(status, serde_json::json!({ "error": message }))
+}
+
+fn route(method: &Method, url: &str, sender: &Sender<ApiRequest>) -> (u16, String) {
+    let segments = url
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let action = match (method, segments.as_slice()) {
+        (Method::Get, ["library"]) => Action::Library,
+        (Method::Get, ["status"]) => Action::Status,
+        (Method::Post, ["play", id]) => match id.parse::<usize>() {
+            Ok(id) => Action::Play(id),
+            Err(_) => return error(404, "no such track"),
+        },
+        (Method::Post, ["pause"]) => Action::Pause,
+        (Method::Post, ["next"]) => Action::Next,
+        (Method::Post, ["seek", seconds]) => match seconds.parse::<f64>() {
+            Ok(seconds) => Action::Seek(seconds),
+            Err(_) => return error(400, "invalid position"),
+        },
+        (Method::Get, ["queue"]) => Action::Queue,
+        (Method::Delete, ["queue"]) => Action::ClearQueue,
+        _ => return error(404, "not found"),
+    };
+    let (reply, response) = mpsc::channel();
+    if sender.send(ApiRequest { action, reply }).is_err() {
+        return error(503, "shutting down");
+    }
+    response
+        .recv()
+        .unwrap_or_else(|_| error(503, "shutting down"))
+}
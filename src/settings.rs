@@ -1,55 +1,596 @@
-use crate::audio::AudioInterface;
-use crate::ui::Window;
+use crate::audio::{AudioInterface, AutoAdvance, ChannelMapping, Devices, SilenceSettings};
+use crate::library::{default_columns, ColumnConfig};
+use crate::log;
+use crate::log::Level;
+use crate::podcast::Feed;
+use crate::resample::ResamplerQuality;
+use crate::smart_playlist::SmartPlaylist;
+use crate::subsonic::SubsonicClient;
+use crate::ui::{centered_rect, focus_border_style, render_popup, FocusBorder, Window};
+use crate::visualizer::VisualizerMode;
 use crossterm::event::KeyCode;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::fmt;
 use std::io::{self, Stdout};
+use std::path::PathBuf;
 use std::rc::Rc;
 use tui::layout::{Constraint, Direction, Layout};
 use tui::{
     backend::CrosstermBackend,
     layout::Rect,
     style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, ListState},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
+/// The `schema_version` this rmus writes. It is bumped whenever
+/// `settings.json` changes in a way [`Settings::migrate`] has to handle.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Everything rmus remembers between runs, kept in `settings.json` in the
+/// working directory.
+///
+/// Fields added after a file was written take their defaults when it is
+/// loaded. Files from older versions are migrated first, see
+/// [`Settings::migrate`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use music_player::settings::Settings;
+///
+/// let mut settings = Settings::load()?;
+/// settings.volume = 0.8;
+/// settings.save()?;
+/// # Ok::<(), music_player::settings::SettingsError>(())
+/// ```
+#[derive(Serialize, Deserialize)]
 pub struct Settings {
+    /// The layout the file was written with. Files from before the field was
+    /// added count as version 0.
+    #[serde(default)]
+    pub schema_version: u32,
+    /// The folders the library is scanned from.
     pub lib_folders: Vec<String>,
     device: usize,
+    /// The Subsonic server to browse, when one is set up.
+    #[serde(default)]
+    pub subsonic: Option<SubsonicClient>,
+    /// Subscribed podcasts, with how far each episode has been played.
+    #[serde(default)]
+    pub podcast_feeds: Vec<Feed>,
+    /// Playlists built from rules rather than a fixed track list.
+    #[serde(default)]
+    pub smart_playlists: Vec<SmartPlaylist>,
+    /// Playback volume, from `0.0` to `1.0`.
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    /// What the visualizer draws.
+    #[serde(default)]
+    pub visualizer_mode: VisualizerMode,
+    /// How long the bars and spectrum keep their peak markers before the
+    /// markers fall.
+    #[serde(default = "default_peak_hold_secs")]
+    pub peak_hold_secs: f32,
+    /// How fast peak markers fall, in full heights per second.
+    #[serde(default = "default_peak_fall_rate")]
+    pub peak_fall_rate: f32,
+    /// Shows a desktop notification when a track starts.
+    #[serde(default)]
+    pub notifications_enabled: bool,
+    /// Looks for a newer rmus release on GitHub at startup.
+    #[serde(default)]
+    pub check_for_updates: bool,
+    /// The port the HTTP API listens on when started with `--server`.
+    #[serde(default = "default_server_port")]
+    pub server_port: u16,
+    /// Whether the HTTP API listens on every network interface instead of
+    /// only on this machine. Needs `server_token`, anyone on the network
+    /// could control playback otherwise.
+    #[serde(default)]
+    pub server_lan: bool,
+    /// When set, HTTP API requests must send it in an
+    /// `Authorization: Bearer <token>` header.
+    #[serde(default)]
+    pub server_token: Option<String>,
+    /// The port the MPD server listens on when started with `--mpd`.
+    #[serde(default = "default_mpd_port")]
+    pub mpd_port: u16,
+    /// How many directories deep the library scan goes. `usize::MAX` means no
+    /// limit.
+    #[serde(default = "default_scan_max_depth")]
+    pub scan_max_depth: usize,
+    /// Glob patterns for directory and file names the library scan skips.
+    #[serde(default)]
+    pub lib_exclude_patterns: Vec<String>,
+    /// The library table's columns, in order, with their widths.
+    #[serde(default = "default_columns")]
+    pub library_columns: Vec<ColumnConfig>,
+    /// Lists files modified in the last `recently_added_days` days first in
+    /// the library.
+    #[serde(default)]
+    pub show_recently_added: bool,
+    /// How recent a file has to be for `show_recently_added`.
+    #[serde(default = "default_recently_added_days")]
+    pub recently_added_days: u32,
+    /// Ends tracks early once they stay quieter than `silence_threshold_db`
+    /// for `silence_duration_secs`.
+    #[serde(default)]
+    pub skip_silence: bool,
+    /// The level below which a track counts as silent, in dBFS.
+    #[serde(default = "default_silence_threshold_db")]
+    pub silence_threshold_db: f32,
+    /// How long a track has to stay silent before it is ended.
+    #[serde(default = "default_silence_duration_secs")]
+    pub silence_duration_secs: f32,
+    /// How long tracks fade in. `0.0` starts them abruptly.
+    #[serde(default)]
+    pub fade_in_secs: f64,
+    /// How long tracks fade out when stopped. `0.0` stops them abruptly.
+    #[serde(default)]
+    pub fade_out_secs: f64,
+    /// The output buffer in frames. `None` leaves it to the device.
+    #[serde(default)]
+    pub audio_buffer_size: Option<u32>,
+    /// How a track's channels are mapped onto the output.
+    #[serde(default)]
+    pub channel_mapping: ChannelMapping,
+    /// How tracks are resampled when their rate differs from the device's.
+    #[serde(default)]
+    pub resampler_quality: ResamplerQuality,
+    /// The user's own AcoustID key, needed to submit fingerprints.
+    #[serde(default)]
+    pub acoustid_user_key: Option<String>,
+    /// Listens are submitted to ListenBrainz when this is set.
+    #[serde(default)]
+    pub listenbrainz_token: Option<String>,
+    /// What plays after the last track in the queue when repeat is off.
+    #[serde(default)]
+    pub auto_advance: AutoAdvance,
+    /// How often the settings are saved while running, not only on quit. `0`
+    /// turns it off.
+    #[serde(default = "default_auto_save_interval_secs")]
+    pub auto_save_interval_secs: u64,
+    /// How many tracks the up next panel lists. Read it through
+    /// [`get_up_next_count`](Settings::get_up_next_count), which keeps it in
+    /// range.
+    #[serde(default = "default_up_next_count")]
+    pub up_next_count: usize,
+    /// Wraps long values in the library table onto more lines instead of
+    /// cutting them off.
+    #[serde(default)]
+    pub word_wrap: bool,
+    /// Where the playlist manager keeps its `.m3u` files.
+    #[serde(default = "default_playlist_dir")]
+    pub playlist_dir: PathBuf,
+    /// Says the title and artist of each track as it starts, through espeak-ng.
+    #[serde(default)]
+    pub tts_enabled: bool,
+    /// The volume of those announcements, from `0.0` to `1.0`.
+    #[serde(default = "default_tts_volume")]
+    pub tts_volume: f32,
+    /// The title of the tab shown on startup. The first tab is shown when no
+    /// tab has this title.
+    #[serde(default = "default_startup_tab")]
+    pub startup_tab: String,
 }
 
-impl Settings {
-    pub fn load() -> Self {
-        let cwd = std::env::current_dir().unwrap();
-        let settings_path = cwd.join("settings.json");
-        let mut settings = Settings {
+const MAX_UP_NEXT_COUNT: usize = 10;
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_peak_hold_secs() -> f32 {
+    3.0
+}
+
+fn default_peak_fall_rate() -> f32 {
+    0.5
+}
+
+fn default_server_port() -> u16 {
+    8337
+}
+
+fn default_mpd_port() -> u16 {
+    6600
+}
+
+fn default_scan_max_depth() -> usize {
+    usize::MAX
+}
+
+fn default_recently_added_days() -> u32 {
+    7
+}
+
+fn default_silence_threshold_db() -> f32 {
+    -50.0
+}
+
+fn default_silence_duration_secs() -> f32 {
+    3.0
+}
+
+fn default_auto_save_interval_secs() -> u64 {
+    60
+}
+
+fn default_up_next_count() -> usize {
+    3
+}
+
+fn default_tts_volume() -> f32 {
+    0.5
+}
+
+fn default_startup_tab() -> String {
+    String::from("Library")
+}
+
+fn default_playlist_dir() -> PathBuf {
+    home::home_dir()
+        .unwrap_or_default()
+        .join(".config/rmus/playlists")
+}
+
+// version 0 relied on every newer field having a serde default. the saved
+// values are laid over the defaults instead, so nothing can be missing
+fn migrate_v0(value: serde_json::Value) -> Result<serde_json::Value, MigrationError> {
+    let serde_json::Value::Object(saved) = value else {
+        return Err(MigrationError::NotAnObject);
+    };
+    let mut migrated = serde_json::to_value(Settings::default())?;
+    let serde_json::Value::Object(fields) = &mut migrated else {
+        return Err(MigrationError::NotAnObject);
+    };
+    fields.extend(saved);
+    fields.insert(String::from("schema_version"), serde_json::Value::from(1));
+    Ok(migrated)
+}
+
+// a leading ~ is the home directory
+fn expand_home(folder: &str) -> String {
+    match (folder.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}{}", home, rest),
+        _ => folder.to_string(),
+    }
+}
+
+/// Why the settings couldn't be loaded or saved, or a library folder
+/// couldn't be added.
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    // settings.json isn't valid json, or has a value of the wrong type
+    Parse(serde_json::Error),
+    Migration(MigrationError),
+    // a folder passed to add_lib_folder that doesn't exist or can't be read
+    FolderUnreadable(String, io::Error),
+    NotADirectory(PathBuf),
+    DuplicateFolder(PathBuf),
+    // the folder, and the library folder it is inside of
+    NestedFolder(PathBuf, PathBuf),
+}
+
+/// Why an older `settings.json` couldn't be brought up to date.
+#[derive(Debug)]
+pub enum MigrationError {
+    NotAnObject,
+    // schema_version isn't a whole number
+    InvalidVersion,
+    // written by a newer rmus than this one
+    UnsupportedVersion(u64),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NotAnObject => write!(f, "expected a json object"),
+            MigrationError::InvalidVersion => write!(f, "schema_version is not a number"),
+            MigrationError::UnsupportedVersion(version) => write!(
+                f,
+                "schema version {} is newer than this rmus supports ({})",
+                version, CURRENT_SCHEMA_VERSION
+            ),
+            MigrationError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for MigrationError {
+    fn from(e: serde_json::Error) -> Self {
+        MigrationError::Parse(e)
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "couldn't read or write settings.json: {}", e),
+            SettingsError::Parse(e) => write!(
+                f,
+                "settings.json is invalid ({}), fix or delete it to start over",
+                e
+            ),
+            SettingsError::Migration(e) => write!(
+                f,
+                "settings.json could not be upgraded ({}), fix or delete it to start over",
+                e
+            ),
+            SettingsError::FolderUnreadable(folder, e) => {
+                write!(f, "{} can't be opened: {}", folder, e)
+            }
+            SettingsError::NotADirectory(path) => {
+                write!(f, "{} is not a directory", path.display())
+            }
+            SettingsError::DuplicateFolder(path) => {
+                write!(f, "{} is already in the library", path.display())
+            }
+            SettingsError::NestedFolder(path, parent) => write!(
+                f,
+                "{} is inside {}, which is already in the library",
+                path.display(),
+                parent.display()
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SettingsError {
+    fn from(e: serde_json::Error) -> Self {
+        SettingsError::Parse(e)
+    }
+}
+
+impl From<MigrationError> for SettingsError {
+    fn from(e: MigrationError) -> Self {
+        SettingsError::Migration(e)
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: CURRENT_SCHEMA_VERSION,
             lib_folders: Vec::new(),
             device: 0,
-        };
+            subsonic: None,
+            podcast_feeds: Vec::new(),
+            smart_playlists: Vec::new(),
+            volume: default_volume(),
+            visualizer_mode: VisualizerMode::default(),
+            peak_hold_secs: default_peak_hold_secs(),
+            peak_fall_rate: default_peak_fall_rate(),
+            notifications_enabled: false,
+            check_for_updates: false,
+            server_port: default_server_port(),
+            server_lan: false,
+            server_token: None,
+            mpd_port: default_mpd_port(),
+            scan_max_depth: default_scan_max_depth(),
+            lib_exclude_patterns: Vec::new(),
+            library_columns: default_columns(),
+            show_recently_added: false,
+            recently_added_days: default_recently_added_days(),
+            skip_silence: false,
+            silence_threshold_db: default_silence_threshold_db(),
+            silence_duration_secs: default_silence_duration_secs(),
+            fade_in_secs: 0.0,
+            fade_out_secs: 0.0,
+            audio_buffer_size: None,
+            channel_mapping: ChannelMapping::default(),
+            resampler_quality: ResamplerQuality::default(),
+            acoustid_user_key: None,
+            listenbrainz_token: None,
+            auto_advance: AutoAdvance::default(),
+            auto_save_interval_secs: default_auto_save_interval_secs(),
+            up_next_count: default_up_next_count(),
+            word_wrap: false,
+            playlist_dir: default_playlist_dir(),
+            tts_enabled: false,
+            tts_volume: default_tts_volume(),
+            startup_tab: default_startup_tab(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads `settings.json` from the working directory, or writes the
+    /// defaults there when it doesn't exist yet.
+    ///
+    /// Older files are migrated. Library columns added since the file was
+    /// written start out hidden. Library folders are canonicalized, and
+    /// duplicates and folders that no longer exist are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read or written, isn't valid JSON, or
+    /// can't be migrated.
+    pub fn load() -> Result<Self, SettingsError> {
+        let cwd = std::env::current_dir()?;
+        let settings_path = cwd.join("settings.json");
+        let mut settings = Settings::default();
         if settings_path.exists() {
-            let settings_contents = std::fs::read_to_string(settings_path).unwrap();
-            settings = serde_json::from_str(settings_contents.as_str()).unwrap();
+            let settings_contents = std::fs::read_to_string(settings_path)?;
+            settings = Settings::migrate(serde_json::from_str(settings_contents.as_str())?)?;
+            // columns added since the settings were saved start out hidden
+            for column in default_columns() {
+                if !settings
+                    .library_columns
+                    .iter()
+                    .any(|saved| saved.name == column.name)
+                {
+                    settings.library_columns.push(ColumnConfig {
+                        visible: false,
+                        ..column
+                    });
+                }
+            }
+            settings.dedup_lib_folders();
+            // the tabs don't exist yet, names no tab has are caught by the ui
+            if settings.startup_tab.trim().is_empty() {
+                settings.startup_tab = default_startup_tab();
+            }
         } else {
-            let settings_contents = serde_json::to_string(&settings).unwrap();
-            std::fs::write(settings_path, settings_contents).unwrap();
+            let settings_contents = serde_json::to_string(&settings)?;
+            std::fs::write(settings_path, settings_contents)?;
         };
-        settings
+        Ok(settings)
     }
 
+    /// Brings settings saved by an older rmus up to [`CURRENT_SCHEMA_VERSION`],
+    /// one version at a time.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `value` isn't an object or its `schema_version` isn't a
+    /// whole number. It also fails when the file was written by a newer rmus,
+    /// or when the migrated values have the wrong types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use music_player::settings::{Settings, CURRENT_SCHEMA_VERSION};
+    ///
+    /// let saved = serde_json::json!({ "lib_folders": ["/music"], "device": 0, "volume": 0.5 });
+    /// let settings = Settings::migrate(saved)?;
+    /// assert_eq!(settings.schema_version, CURRENT_SCHEMA_VERSION);
+    /// assert_eq!(settings.volume, 0.5);
+    /// # Ok::<(), music_player::settings::MigrationError>(())
+    /// ```
+    pub fn migrate(mut value: serde_json::Value) -> Result<Self, MigrationError> {
+        loop {
+            let version = match value.get("schema_version") {
+                Some(version) => version.as_u64().ok_or(MigrationError::InvalidVersion)?,
+                None => 0,
+            };
+            value = match version {
+                0 => migrate_v0(value)?,
+                version if version == CURRENT_SCHEMA_VERSION as u64 => {
+                    return Ok(serde_json::from_value(value)?)
+                }
+                version => return Err(MigrationError::UnsupportedVersion(version)),
+            };
+        }
+    }
+
+    // the same folder listed twice, even spelled differently, would have its
+    // files scanned and shown twice. folders that are gone are dropped
+    fn dedup_lib_folders(&mut self) {
+        let mut lib_folders = std::mem::take(&mut self.lib_folders)
+            .into_iter()
+            .filter_map(|folder| match std::fs::canonicalize(expand_home(&folder)) {
+                Ok(path) => Some(path.to_string_lossy().to_string()),
+                Err(e) => {
+                    log!(Level::Warn, "dropping library folder {}: {}", folder, e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        lib_folders.sort();
+        lib_folders.dedup();
+        self.lib_folders = lib_folders;
+    }
+
+    /// Puts every setting back to its default.
+    pub fn reset_to_defaults(&mut self) {
+        *self = Settings::default();
+    }
+
+    /// The silence skipping settings for the audio interface. `None` when
+    /// skipping is off.
+    pub fn get_skip_silence(&self) -> Option<SilenceSettings> {
+        self.skip_silence.then_some(SilenceSettings {
+            threshold_db: self.silence_threshold_db,
+            duration_secs: self.silence_duration_secs,
+        })
+    }
+
+    /// The index of the output device to play on.
     pub fn get_device(&self) -> usize {
         self.device
     }
 
-    pub fn get_lib_folders(&self) -> Vec<String> {
-        self.lib_folders.clone()
+    /// Fixes settings that no longer fit the machine. A device index past
+    /// the end of `devices`, left behind when hardware was removed between
+    /// runs, is moved to the last device and a warning is logged.
+    pub fn validate_and_fix(&mut self, devices: &Devices) {
+        let last = devices.get_device_names().len().saturating_sub(1);
+        if self.device > last {
+            log!(
+                Level::Warn,
+                "output device {} no longer exists, using device {}",
+                self.device,
+                last
+            );
+            self.device = last;
+        }
+    }
+
+    /// `up_next_count` kept between 1 and 10.
+    pub fn get_up_next_count(&self) -> usize {
+        self.up_next_count.clamp(1, MAX_UP_NEXT_COUNT)
+    }
+
+    /// The library folders as paths.
+    pub fn get_lib_folders_as_paths(&self) -> Vec<PathBuf> {
+        self.lib_folders.iter().map(PathBuf::from).collect()
     }
 
-    pub fn save(&self) {
-        let cwd = std::env::current_dir().unwrap();
+    /// Adds `path` to the library folders and saves the settings. A leading
+    /// `~` is expanded, and the folder is stored canonicalized.
+    ///
+    /// # Errors
+    ///
+    /// Fails without changing anything when `path` can't be opened or isn't
+    /// a directory, or when it is a library folder already or inside of one.
+    /// Fails like [`save`](Self::save) when the settings can't be written.
+    pub fn add_lib_folder(&mut self, path: &str) -> Result<(), SettingsError> {
+        let canonical = std::fs::canonicalize(expand_home(path))
+            .map_err(|e| SettingsError::FolderUnreadable(path.to_string(), e))?;
+        if !canonical.is_dir() {
+            return Err(SettingsError::NotADirectory(canonical));
+        }
+        for folder in self.get_lib_folders_as_paths() {
+            if canonical == folder {
+                return Err(SettingsError::DuplicateFolder(canonical));
+            }
+            if canonical.starts_with(&folder) {
+                return Err(SettingsError::NestedFolder(canonical, folder));
+            }
+        }
+        self.lib_folders
+            .push(canonical.to_string_lossy().to_string());
+        if let Err(e) = self.save() {
+            self.lib_folders.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Writes the settings to `settings.json` in the working directory.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the working directory can't be found or the file can't be
+    /// written.
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let cwd = std::env::current_dir()?;
         let settings_path = cwd.join("settings.json");
-        let settings_contents = serde_json::to_string(&self).unwrap();
-        std::fs::write(settings_path, settings_contents).unwrap();
+        let settings_contents = serde_json::to_string(&self)?;
+        std::fs::write(settings_path, settings_contents)?;
+        Ok(())
     }
 }
 
@@ -58,6 +599,7 @@ struct DeviceWindow {
     audio_interface: Rc<RefCell<AudioInterface>>,
     settings: Rc<RefCell<Settings>>,
     state: ListState,
+    status: Option<String>,
 }
 
 impl DeviceWindow {
@@ -65,12 +607,12 @@ impl DeviceWindow {
         let mut state = ListState::default();
         state.select(Some(0));
         settings.borrow_mut().device = audio_interface.borrow().devices.get_current_device();
-        settings.borrow_mut().dev
         Self {
             title: String::from("Device List"),
             settings,
             audio_interface,
             state,
+            status: None,
         }
     }
 }
@@ -93,17 +635,22 @@ impl Window for DeviceWindow {
         let curr_device = self.audio_interface.borrow().devices.get_current_device();
         devices_vec[curr_device] =
             ListItem::new(devices[curr_device].as_str()).style(Style::default().fg(Color::Yellow));
+        let title = match &self.status {
+            Some(status) => format!("{} - {}", self.get_title(), status),
+            None => self.get_title(),
+        };
         let devices_window = List::new(devices_vec)
-            .block(
-                Block::default()
-                    .title(self.get_title())
-                    .borders(Borders::ALL),
-            )
+            .block(Block::default().title(title).borders(Borders::ALL))
             .style(Style::default().fg(Color::Green))
             .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
             .highlight_symbol(">> ");
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(5)].as_ref())
+            .split(area);
         // get the current device and highlight it a different color:
-        f.render_stateful_widget(devices_window, area, &mut self.state);
+        f.render_stateful_widget(devices_window, chunks[0], &mut self.state);
+        f.render_widget(self.output_panel(), chunks[1]);
         Ok(())
     }
 
@@ -113,7 +660,27 @@ impl Window for DeviceWindow {
             KeyCode::Down => self.previous(),
             KeyCode::Enter => {
                 let selected = self.state.selected().unwrap();
-                self.settings.borrow_mut().device = selected;
+                let switched = self.audio_interface.borrow_mut().switch_device(selected);
+                match switched {
+                    Ok(()) => {
+                        self.settings.borrow_mut().device = selected;
+                        self.status = None;
+                    }
+                    Err(e) => self.status = Some(format!("Failed to switch device: {}", e)),
+                }
+            }
+            // like the other playback settings, applies from the next track
+            KeyCode::Char('m') => {
+                let mut audio_interface = self.audio_interface.borrow_mut();
+                let mapping = audio_interface.get_channel_mapping().next();
+                audio_interface.set_channel_mapping(mapping);
+                self.settings.borrow_mut().channel_mapping = mapping;
+            }
+            KeyCode::Char('r') => {
+                let mut audio_interface = self.audio_interface.borrow_mut();
+                let quality = audio_interface.get_resampler_quality().next();
+                audio_interface.set_resampler_quality(quality);
+                self.settings.borrow_mut().resampler_quality = quality;
             }
             _ => (),
         };
@@ -122,6 +689,28 @@ impl Window for DeviceWindow {
 }
 
 impl DeviceWindow {
+    // how the playing track reaches the device
+    fn output_panel(&self) -> Paragraph<'static> {
+        let audio_interface = self.audio_interface.borrow();
+        let conversion = match audio_interface.get_rate_conversion() {
+            Some((from, to)) => format!("{} Hz -> {} Hz", from, to),
+            None => String::from("None"),
+        };
+        Paragraph::new(vec![
+            Line::from(format!(
+                "Channels: {} (m to change)",
+                audio_interface.get_channel_mapping().name()
+            )),
+            Line::from(format!(
+                "Resampler: {} (r to change)",
+                audio_interface.get_resampler_quality().name()
+            )),
+            Line::from(format!("Sample rate conversion: {}", conversion)),
+        ])
+        .block(Block::default().title("Output").borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green))
+    }
+
     pub fn previous(&mut self) {
         let i = match self.state.selected() {
             Some(i) => {
@@ -163,32 +752,655 @@ impl DeviceWindow {
     }
 }
 
+// smaller buffers lower the latency, larger ones avoid dropouts on a busy system
+const BUFFER_SIZE_PRESETS: [Option<u32>; 6] =
+    [None, Some(64), Some(128), Some(256), Some(512), Some(1024)];
+
+struct BufferWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    status: Option<String>,
+}
+
+impl BufferWindow {
+    fn new(settings: Rc<RefCell<Settings>>, audio_interface: Rc<RefCell<AudioInterface>>) -> Self {
+        Self {
+            title: String::from("Audio Buffer"),
+            settings,
+            audio_interface,
+            status: None,
+        }
+    }
+
+    fn step(&mut self, step: isize) {
+        let current = self.audio_interface.borrow().get_buffer_size();
+        let i = BUFFER_SIZE_PRESETS
+            .iter()
+            .position(|preset| *preset == current)
+            .unwrap_or(0);
+        let next = (i as isize + step).clamp(0, BUFFER_SIZE_PRESETS.len() as isize - 1) as usize;
+        let buffer_size = BUFFER_SIZE_PRESETS[next];
+        if buffer_size == current {
+            return;
+        }
+        // not every device accepts every size, keep the old one if it refuses
+        match self
+            .audio_interface
+            .borrow_mut()
+            .set_buffer_size(buffer_size)
+        {
+            Ok(()) => {
+                self.settings.borrow_mut().audio_buffer_size = buffer_size;
+                self.status = None;
+            }
+            Err(e) => self.status = Some(format!("Could not use that size: {}", e)),
+        }
+    }
+}
+
+impl Window for BufferWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let size = match self.audio_interface.borrow().get_buffer_size() {
+            Some(frames) => format!("{} frames", frames),
+            None => String::from("Device default"),
+        };
+        let buffer_window = Paragraph::new(vec![
+            Line::from(format!("Buffer size: {}", size)),
+            Line::from(""),
+            Line::from("Up/Down to change. Smaller is lower latency, larger avoids dropouts"),
+            Line::from(self.status.clone().unwrap_or_default()),
+        ])
+        .block(
+            Block::default()
+                .title(self.get_title())
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Green));
+        f.render_widget(buffer_window, area);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        match key {
+            KeyCode::Up => self.step(1),
+            KeyCode::Down => self.step(-1),
+            _ => (),
+        };
+        Ok(())
+    }
+}
+
+// depths past this are stepped straight up to no limit
+const MAX_FINITE_SCAN_DEPTH: usize = 32;
+
+struct ScanWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+}
+
+impl ScanWindow {
+    fn new(settings: Rc<RefCell<Settings>>) -> Self {
+        Self {
+            title: String::from("Library Scan"),
+            settings,
+        }
+    }
+}
+
+impl Window for ScanWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let depth = match self.settings.borrow().scan_max_depth {
+            usize::MAX => String::from("Unlimited"),
+            depth => depth.to_string(),
+        };
+        let scan_window = Paragraph::new(vec![
+            Line::from(format!("Max scan depth: {}", depth)),
+            Line::from(""),
+            Line::from("Up/Down to change, applies on the next start"),
+        ])
+        .block(
+            Block::default()
+                .title(self.get_title())
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Green));
+        f.render_widget(scan_window, area);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        let mut settings = self.settings.borrow_mut();
+        match key {
+            KeyCode::Up => {
+                settings.scan_max_depth = match settings.scan_max_depth {
+                    depth if depth >= MAX_FINITE_SCAN_DEPTH => usize::MAX,
+                    depth => depth + 1,
+                }
+            }
+            KeyCode::Down => {
+                settings.scan_max_depth = match settings.scan_max_depth {
+                    usize::MAX => MAX_FINITE_SCAN_DEPTH,
+                    depth => (depth - 1).max(1),
+                }
+            }
+            _ => (),
+        };
+        Ok(())
+    }
+}
+
+struct TtsWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+}
+
+impl TtsWindow {
+    fn new(settings: Rc<RefCell<Settings>>) -> Self {
+        Self {
+            title: String::from("TTS Settings"),
+            settings,
+        }
+    }
+}
+
+impl Window for TtsWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let settings = self.settings.borrow();
+        let tts_window = Paragraph::new(vec![
+            Line::from(format!(
+                "Announce tracks: {}",
+                if settings.tts_enabled { "On" } else { "Off" }
+            )),
+            Line::from(format!(
+                "Announcement volume: {:.0}%",
+                settings.tts_volume * 100.0
+            )),
+            Line::from(""),
+            Line::from("Space to turn on/off, < > to change the volume"),
+            Line::from("Needs espeak-ng to be installed"),
+        ])
+        .block(
+            Block::default()
+                .title(self.get_title())
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Green));
+        f.render_widget(tts_window, area);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        let mut settings = self.settings.borrow_mut();
+        match key {
+            KeyCode::Char(' ') | KeyCode::Enter => settings.tts_enabled = !settings.tts_enabled,
+            KeyCode::Char('<') => settings.tts_volume = (settings.tts_volume - 0.1).max(0.0),
+            KeyCode::Char('>') => settings.tts_volume = (settings.tts_volume + 0.1).min(1.0),
+            _ => (),
+        };
+        Ok(())
+    }
+}
+
+struct StartupTabWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    tab_names: Vec<String>,
+    state: ListState,
+}
+
+impl StartupTabWindow {
+    fn new(settings: Rc<RefCell<Settings>>, tab_names: Vec<String>) -> Self {
+        let mut state = ListState::default();
+        let startup_tab = settings.borrow().startup_tab.clone();
+        state.select(Some(
+            tab_names
+                .iter()
+                .position(|name| *name == startup_tab)
+                .unwrap_or(0),
+        ));
+        Self {
+            title: String::from("Startup Tab"),
+            settings,
+            tab_names,
+            state,
+        }
+    }
+}
+
+impl Window for StartupTabWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let startup_tab = self.settings.borrow().startup_tab.clone();
+        let tabs = self
+            .tab_names
+            .iter()
+            .map(|name| match *name == startup_tab {
+                true => ListItem::new(name.as_str()).style(Style::default().fg(Color::Yellow)),
+                false => ListItem::new(name.as_str()),
+            })
+            .collect::<Vec<_>>();
+        let tabs_window = List::new(tabs)
+            .block(
+                Block::default()
+                    .title(format!("{} - Enter to open on start", self.get_title()))
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(tabs_window, area, &mut self.state);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => self.state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => self.state.select(Some(
+                (selected + 1).min(self.tab_names.len().saturating_sub(1)),
+            )),
+            KeyCode::Enter => {
+                if let Some(name) = self.tab_names.get(selected) {
+                    self.settings.borrow_mut().startup_tab = name.clone();
+                }
+            }
+            _ => (),
+        };
+        Ok(())
+    }
+}
+
+struct ExcludeWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    state: ListState,
+    // the pattern being typed, None while browsing the list
+    input: Option<String>,
+    status: Option<String>,
+}
+
+impl ExcludeWindow {
+    fn new(settings: Rc<RefCell<Settings>>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            title: String::from("Library Exclusions"),
+            settings,
+            state,
+            input: None,
+            status: None,
+        }
+    }
+
+    fn add_pattern(&mut self, pattern: String) {
+        if pattern.trim().is_empty() {
+            return;
+        }
+        match glob::Pattern::new(pattern.trim()) {
+            Ok(_) => {
+                let mut settings = self.settings.borrow_mut();
+                settings
+                    .lib_exclude_patterns
+                    .push(pattern.trim().to_string());
+                self.state
+                    .select(Some(settings.lib_exclude_patterns.len() - 1));
+                self.status = None;
+            }
+            Err(e) => self.status = Some(format!("invalid pattern: {}", e.msg)),
+        }
+    }
+
+    fn remove_selected(&mut self) {
+        let selected = self.state.selected().unwrap_or(0);
+        let mut settings = self.settings.borrow_mut();
+        if selected < settings.lib_exclude_patterns.len() {
+            settings.lib_exclude_patterns.remove(selected);
+            self.state.select(Some(
+                selected.min(settings.lib_exclude_patterns.len().saturating_sub(1)),
+            ));
+        }
+    }
+}
+
+impl Window for ExcludeWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let mut patterns = self
+            .settings
+            .borrow()
+            .lib_exclude_patterns
+            .iter()
+            .map(|pattern| ListItem::new(pattern.clone()))
+            .collect::<Vec<_>>();
+        if let Some(input) = &self.input {
+            patterns.push(
+                ListItem::new(format!("{}_", input)).style(Style::default().fg(Color::Yellow)),
+            );
+        }
+        let title = match &self.status {
+            Some(status) => format!("{} - {}", self.get_title(), status),
+            None => format!(
+                "{} - a to add, d to remove, applies on the next start",
+                self.get_title()
+            ),
+        };
+        let exclude_window = List::new(patterns)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(exclude_window, area, &mut self.state);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        if let Some(input) = &mut self.input {
+            match key {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let pattern = self.input.take().unwrap_or_default();
+                    self.add_pattern(pattern);
+                }
+                KeyCode::Esc => self.input = None,
+                _ => (),
+            }
+            return Ok(());
+        }
+        let len = self.settings.borrow().lib_exclude_patterns.len();
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up if len > 0 => {
+                self.state
+                    .select(Some(if selected == 0 { len - 1 } else { selected - 1 }))
+            }
+            KeyCode::Down if len > 0 => self.state.select(Some((selected + 1) % len)),
+            KeyCode::Char('a') => {
+                self.input = Some(String::new());
+                self.status = None;
+            }
+            KeyCode::Char('d') | KeyCode::Delete => self.remove_selected(),
+            _ => (),
+        };
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        self.input.is_some()
+    }
+}
+
+struct ColumnsWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    state: ListState,
+}
+
+impl ColumnsWindow {
+    fn new(settings: Rc<RefCell<Settings>>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            title: String::from("Library Columns"),
+            settings,
+            state,
+        }
+    }
+
+    fn resize_selected(&mut self, step: i16) {
+        let selected = self.state.selected().unwrap_or(0);
+        let mut settings = self.settings.borrow_mut();
+        if let Some(column) = settings.library_columns.get_mut(selected) {
+            column.width_pct = (column.width_pct as i16 + step).clamp(1, 100) as u8;
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        let selected = self.state.selected().unwrap_or(0);
+        let mut settings = self.settings.borrow_mut();
+        let visible = settings
+            .library_columns
+            .iter()
+            .filter(|column| column.visible)
+            .count();
+        if let Some(column) = settings.library_columns.get_mut(selected) {
+            // hiding every column would leave an empty table
+            if !column.visible || visible > 1 {
+                column.visible = !column.visible;
+            }
+        }
+    }
+}
+
+impl Window for ColumnsWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let columns = self
+            .settings
+            .borrow()
+            .library_columns
+            .iter()
+            .map(|column| {
+                ListItem::new(format!(
+                    "[{}] {} ({}%)",
+                    if column.visible { "x" } else { " " },
+                    column.name,
+                    column.width_pct
+                ))
+            })
+            .collect::<Vec<_>>();
+        let columns_window = List::new(columns)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "{} - Space to show/hide, < > to resize",
+                        self.get_title()
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(columns_window, area, &mut self.state);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        let len = self.settings.borrow().library_columns.len();
+        let selected = self.state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up => {
+                self.state
+                    .select(Some(if selected == 0 { len - 1 } else { selected - 1 }))
+            }
+            KeyCode::Down => self.state.select(Some((selected + 1) % len)),
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_selected(),
+            KeyCode::Char('<') => self.resize_selected(-5),
+            KeyCode::Char('>') => self.resize_selected(5),
+            _ => (),
+        };
+        Ok(())
+    }
+}
+
+struct ResetWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    confirming: bool,
+    status: Option<String>,
+}
+
+impl ResetWindow {
+    fn new(settings: Rc<RefCell<Settings>>, audio_interface: Rc<RefCell<AudioInterface>>) -> Self {
+        Self {
+            title: String::from("Reset to Defaults"),
+            settings,
+            audio_interface,
+            confirming: false,
+            status: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.settings.borrow_mut().reset_to_defaults();
+        if let Err(e) = self.settings.borrow().save() {
+            log!(Level::Error, "saving settings failed: {}", e);
+        }
+        // the volume is the only setting that is applied while running
+        self.audio_interface
+            .borrow_mut()
+            .set_volume(self.settings.borrow().volume);
+        self.status = Some(String::from(
+            "Settings were reset, the rest applies on the next start",
+        ));
+    }
+}
+
+impl Window for ResetWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> std::result::Result<(), io::Error> {
+        let reset_window = Paragraph::new(vec![
+            Line::from("Restores every setting, including the library folders,"),
+            Line::from("to its default value."),
+            Line::from(""),
+            Line::from(self.status.as_deref().unwrap_or("Enter to reset")),
+        ])
+        .block(
+            Block::default()
+                .title(self.get_title())
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Green));
+        f.render_widget(reset_window, area);
+        if self.confirming {
+            let confirm = Paragraph::new("Reset all settings to their defaults? y/n")
+                .style(Style::default().fg(Color::Yellow))
+                .wrap(Wrap { trim: true });
+            render_popup(f, centered_rect(60, 30, area), "Confirm", |inner, f| {
+                f.render_widget(confirm, inner)
+            });
+        }
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> std::result::Result<(), io::Error> {
+        if self.confirming {
+            match key {
+                KeyCode::Char('y') => {
+                    self.confirming = false;
+                    self.reset();
+                }
+                KeyCode::Char('n') | KeyCode::Esc => self.confirming = false,
+                _ => (),
+            }
+            return Ok(());
+        }
+        if key == KeyCode::Enter {
+            self.confirming = true;
+            self.status = None;
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        self.confirming
+    }
+}
+
 pub struct SettingsWindow {
     title: String,
+    #[allow(dead_code)]
     audio_interface: Rc<RefCell<AudioInterface>>,
     state: ListState,
+    #[allow(dead_code)]
     settings: Rc<RefCell<Settings>>,
     selected_window: usize,
     settings_windows: Vec<Box<dyn Window>>,
 }
 
 impl SettingsWindow {
+    // tab_names are the titles of the tabs before this one
     pub fn new(
         settings: Rc<RefCell<Settings>>,
         audio_interface: Rc<RefCell<AudioInterface>>,
+        mut tab_names: Vec<String>,
     ) -> Self {
         let mut state = ListState::default();
         state.select(Some(0));
+        let title = String::from("Settings");
+        tab_names.push(title.clone());
         Self {
-            title: String::from("Settings"),
+            title,
             audio_interface: audio_interface.clone(),
             state,
             selected_window: 0,
             settings: settings.clone(),
-            settings_windows: vec![Box::new(DeviceWindow::new(
-                audio_interface.clone(),
-                settings.clone(),
-            ))],
+            settings_windows: vec![
+                Box::new(DeviceWindow::new(audio_interface.clone(), settings.clone())),
+                Box::new(BufferWindow::new(settings.clone(), audio_interface.clone())),
+                Box::new(ScanWindow::new(settings.clone())),
+                Box::new(ExcludeWindow::new(settings.clone())),
+                Box::new(ColumnsWindow::new(settings.clone())),
+                Box::new(TtsWindow::new(settings.clone())),
+                Box::new(StartupTabWindow::new(settings.clone(), tab_names)),
+                Box::new(ResetWindow::new(settings.clone(), audio_interface.clone())),
+            ],
         }
     }
     pub fn next(&mut self) {
@@ -245,13 +1457,21 @@ impl Window for SettingsWindow {
                 .map(|file| ListItem::new(file.get_title()))
                 .collect::<Vec<_>>(),
         )
-        .block(Block::default().title("Settings").borders(Borders::ALL))
+        .block(
+            Block::default()
+                .title("Settings")
+                .borders(Borders::ALL)
+                .border_style(focus_border_style(self.selected_window == 0)),
+        )
         .style(Style::default().fg(Color::Green))
         .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
         .highlight_symbol(">> ");
         f.render_stateful_widget(list_widget, chunks[0], &mut self.state);
         let selected = self.get_state();
         self.settings_windows[selected].draw(chunks[1], f)?;
+        if self.selected_window == 1 {
+            f.render_widget(FocusBorder, chunks[1]);
+        }
         Ok(())
     }
 
@@ -269,7 +1489,7 @@ impl Window for SettingsWindow {
                 _ => {}
             },
             1 => match key {
-                KeyCode::Left => self.selected_window = 0,
+                KeyCode::Left if !self.is_typing() => self.selected_window = 0,
                 _ => {
                     let num = self.get_state();
                     self.settings_windows[num].handle_input(key)?;
@@ -281,4 +1501,83 @@ impl Window for SettingsWindow {
         }
         Ok(())
     }
+    fn is_typing(&self) -> bool {
+        self.selected_window == 1 && self.settings_windows[self.get_state()].is_typing()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_v0_to_current() {
+        let v0 = json!({
+            "lib_folders": ["/music"],
+            "device": 2,
+            "volume": 0.5,
+        });
+        let settings = Settings::migrate(v0).unwrap();
+        assert_eq!(settings.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(settings.lib_folders, vec![String::from("/music")]);
+        assert_eq!(settings.device, 2);
+        assert_eq!(settings.volume, 0.5);
+        assert_eq!(settings.server_port, default_server_port());
+
+        // saving the migrated settings and loading them again changes nothing
+        let saved = serde_json::to_value(&settings).unwrap();
+        let reloaded = Settings::migrate(saved.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&reloaded).unwrap(), saved);
+    }
+
+    #[test]
+    fn rejects_newer_schema_versions() {
+        let newer = json!({ "schema_version": CURRENT_SCHEMA_VERSION + 1 });
+        assert!(matches!(
+            Settings::migrate(newer),
+            Err(MigrationError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn clamps_missing_device() {
+        let devices = Devices::new(0);
+        let mut settings = Settings {
+            device: usize::MAX,
+            ..Settings::default()
+        };
+        settings.validate_and_fix(&devices);
+        assert!(settings.device < devices.get_device_names().len().max(1));
+    }
+
+    // every case here is turned down before anything is saved
+    #[test]
+    fn rejects_bad_lib_folders() {
+        let root = std::fs::canonicalize(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let mut settings = Settings {
+            lib_folders: vec![root.to_string_lossy().to_string()],
+            ..Settings::default()
+        };
+        let add = |settings: &mut Settings, path: PathBuf| {
+            settings.add_lib_folder(&path.to_string_lossy())
+        };
+        assert!(matches!(
+            add(&mut settings, root.join("no such folder")),
+            Err(SettingsError::FolderUnreadable(..))
+        ));
+        assert!(matches!(
+            add(&mut settings, root.join("Cargo.toml")),
+            Err(SettingsError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            add(&mut settings, root.clone()),
+            Err(SettingsError::DuplicateFolder(_))
+        ));
+        assert!(matches!(
+            add(&mut settings, root.join("src")),
+            Err(SettingsError::NestedFolder(_, parent)) if parent == root
+        ));
+        assert_eq!(settings.lib_folders.len(), 1);
+    }
 }
@@ -0,0 +1,446 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    cache::LibraryCache,
+    settings::Settings,
+    ui::{focus_border_style, Window},
+};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Stdout},
+    rc::Rc,
+    str::FromStr,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Criterion {
+    ArtistContains(String),
+    GenreIs(String),
+    RatingAtLeast(u8),
+    PlayCountAtLeast(u64),
+    YearBetween(i32, i32),
+    DurationLessThan(f64),
+}
+
+impl Criterion {
+    pub fn matches(&self, file: &AudioFile) -> bool {
+        match self {
+            Criterion::ArtistContains(artist) => file
+                .get_artist()
+                .to_lowercase()
+                .contains(&artist.to_lowercase()),
+            Criterion::GenreIs(genre) => file.get_genre().eq_ignore_ascii_case(genre),
+            Criterion::RatingAtLeast(stars) => file.get_rating().unwrap_or(0) >= *stars,
+            Criterion::PlayCountAtLeast(count) => file.get_play_count() >= *count,
+            Criterion::YearBetween(from, to) => (*from..=*to).contains(&file.get_year()),
+            Criterion::DurationLessThan(seconds) => file.get_raw_duration() < *seconds,
+        }
+    }
+}
+
+// criteria are typed in as `kind:value`, e.g. `artist:beatles` or `year:1990-1999`
+impl fmt::Display for Criterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Criterion::ArtistContains(artist) => write!(f, "artist:{}", artist),
+            Criterion::GenreIs(genre) => write!(f, "genre:{}", genre),
+            Criterion::RatingAtLeast(stars) => write!(f, "rating:{}", stars),
+            Criterion::PlayCountAtLeast(count) => write!(f, "plays:{}", count),
+            Criterion::YearBetween(from, to) => write!(f, "year:{}-{}", from, to),
+            Criterion::DurationLessThan(seconds) => write!(f, "shorter:{}", seconds),
+        }
+    }
+}
+
+impl FromStr for Criterion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| String::from("expected kind:value"))?;
+        let value = value.trim();
+        let invalid = |_| format!("invalid value for {}: {}", kind, value);
+        let invalid_float = |_| format!("invalid value for {}: {}", kind, value);
+        match kind.trim() {
+            "artist" => Ok(Criterion::ArtistContains(value.to_string())),
+            "genre" => Ok(Criterion::GenreIs(value.to_string())),
+            "rating" => Ok(Criterion::RatingAtLeast(value.parse().map_err(invalid)?)),
+            "plays" => Ok(Criterion::PlayCountAtLeast(value.parse().map_err(invalid)?)),
+            "year" => {
+                let (from, to) = value.split_once('-').unwrap_or((value, value));
+                Ok(Criterion::YearBetween(
+                    from.trim().parse().map_err(invalid)?,
+                    to.trim().parse().map_err(invalid)?,
+                ))
+            }
+            "shorter" => Ok(Criterion::DurationLessThan(
+                value.parse::<f64>().map_err(invalid_float)?,
+            )),
+            _ => Err(format!(
+                "unknown criterion {}, use artist, genre, rating, plays, year or shorter",
+                kind
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SmartPlaylist {
+    pub name: String,
+    pub criteria: Vec<Criterion>,
+}
+
+impl SmartPlaylist {
+    // a track has to match every criterion
+    pub fn evaluate<'a>(&self, library: &'a [AudioFile]) -> Vec<&'a AudioFile> {
+        library
+            .iter()
+            .filter(|file| {
+                self.criteria
+                    .iter()
+                    .all(|criterion| criterion.matches(file))
+            })
+            .collect()
+    }
+}
+
+enum InputMode {
+    Browse,
+    NewPlaylist,
+    // Some(i) while editing the criterion at index i, None for a new one
+    Criterion(Option<usize>),
+}
+
+pub struct SmartPlaylistWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    cache: Rc<RefCell<LibraryCache>>,
+    music_list: Vec<AudioFile>,
+    playlist_state: ListState,
+    criteria_state: ListState,
+    selected_window: usize,
+    mode: InputMode,
+    input: String,
+    status: String,
+}
+
+impl SmartPlaylistWindow {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+        cache: Rc<RefCell<LibraryCache>>,
+        music_list: &[AudioFile],
+    ) -> Self {
+        let mut playlist_state = ListState::default();
+        playlist_state.select(Some(0));
+        let mut criteria_state = ListState::default();
+        criteria_state.select(Some(0));
+        Self {
+            title: String::from("Smart Playlists"),
+            settings,
+            audio_interface,
+            cache,
+            music_list: music_list.to_vec(),
+            playlist_state,
+            criteria_state,
+            selected_window: 0,
+            mode: InputMode::Browse,
+            input: String::new(),
+            status: String::from(
+                "p: new playlist, a: add criterion, e: edit, d: delete, Enter: enqueue",
+            ),
+        }
+    }
+
+    // ratings and play counts change while rmus runs, so refresh them from the cache
+    fn refresh_music_list(&mut self) {
+        let cache = self.cache.borrow();
+        for file in self.music_list.iter_mut() {
+            cache.apply(file);
+        }
+    }
+
+    fn selected_playlist(&self) -> Option<SmartPlaylist> {
+        let settings = self.settings.borrow();
+        self.playlist_state
+            .selected()
+            .and_then(|i| settings.smart_playlists.get(i))
+            .cloned()
+    }
+
+    fn len(&self) -> usize {
+        match self.selected_window {
+            0 => self.settings.borrow().smart_playlists.len(),
+            _ => self
+                .selected_playlist()
+                .map(|playlist| playlist.criteria.len())
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn next(&mut self) {
+        let len = self.len();
+        let state = match self.selected_window {
+            0 => &mut self.playlist_state,
+            _ => &mut self.criteria_state,
+        };
+        let i = match state.selected() {
+            Some(i) => {
+                if i + 1 >= len {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let len = self.len();
+        let state = match self.selected_window {
+            0 => &mut self.playlist_state,
+            _ => &mut self.criteria_state,
+        };
+        let i = match state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    len.saturating_sub(1)
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        state.select(Some(i));
+    }
+
+    fn enqueue(&mut self) {
+        let Some(playlist) = self.selected_playlist() else {
+            return;
+        };
+        self.refresh_music_list();
+        let mut tracks = playlist
+            .evaluate(&self.music_list)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.status = format!("Queued {} tracks from {}", tracks.len(), playlist.name);
+        self.audio_interface
+            .borrow_mut()
+            .append_to_queue(&mut tracks);
+    }
+
+    fn delete(&mut self) {
+        let mut settings = self.settings.borrow_mut();
+        match self.selected_window {
+            0 => {
+                if let Some(i) = self.playlist_state.selected() {
+                    if i < settings.smart_playlists.len() {
+                        settings.smart_playlists.remove(i);
+                        self.playlist_state.select(Some(i.saturating_sub(1)));
+                    }
+                }
+            }
+            _ => {
+                if let (Some(p), Some(c)) = (
+                    self.playlist_state.selected(),
+                    self.criteria_state.selected(),
+                ) {
+                    if let Some(playlist) = settings.smart_playlists.get_mut(p) {
+                        if c < playlist.criteria.len() {
+                            playlist.criteria.remove(c);
+                            self.criteria_state.select(Some(c.saturating_sub(1)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn submit_input(&mut self) {
+        let input = std::mem::take(&mut self.input);
+        match std::mem::replace(&mut self.mode, InputMode::Browse) {
+            InputMode::Browse => {}
+            InputMode::NewPlaylist => {
+                let mut settings = self.settings.borrow_mut();
+                settings.smart_playlists.push(SmartPlaylist {
+                    name: input,
+                    criteria: Vec::new(),
+                });
+                self.playlist_state
+                    .select(Some(settings.smart_playlists.len() - 1));
+            }
+            InputMode::Criterion(editing) => match input.parse::<Criterion>() {
+                Ok(criterion) => {
+                    let mut settings = self.settings.borrow_mut();
+                    let Some(playlist) = self
+                        .playlist_state
+                        .selected()
+                        .and_then(|i| settings.smart_playlists.get_mut(i))
+                    else {
+                        return;
+                    };
+                    match editing {
+                        Some(i) if i < playlist.criteria.len() => playlist.criteria[i] = criterion,
+                        _ => playlist.criteria.push(criterion),
+                    }
+                }
+                Err(e) => self.status = e,
+            },
+        }
+    }
+}
+
+impl Window for SmartPlaylistWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        self.refresh_music_list();
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+            .split(area);
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(rows[0]);
+        let settings = self.settings.borrow();
+        let playlists = settings
+            .smart_playlists
+            .iter()
+            .map(|playlist| ListItem::new(playlist.name.as_str()))
+            .collect::<Vec<_>>();
+        let selected = self
+            .playlist_state
+            .selected()
+            .and_then(|i| settings.smart_playlists.get(i));
+        let criteria = selected
+            .map(|playlist| {
+                playlist
+                    .criteria
+                    .iter()
+                    .map(|criterion| ListItem::new(criterion.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let preview = match selected {
+            Some(playlist) => format!(
+                "Criteria - {} matching tracks",
+                playlist.evaluate(&self.music_list).len()
+            ),
+            None => String::from("Criteria"),
+        };
+        let playlist_list = List::new(playlists)
+            .block(
+                Block::default()
+                    .title("Playlists")
+                    .borders(Borders::ALL)
+                    .border_style(focus_border_style(self.selected_window == 0)),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let criteria_list = List::new(criteria)
+            .block(
+                Block::default()
+                    .title(preview)
+                    .borders(Borders::ALL)
+                    .border_style(focus_border_style(self.selected_window == 1)),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let status = match self.mode {
+            InputMode::Browse => Paragraph::new(self.status.clone())
+                .block(Block::default().borders(Borders::ALL))
+                .style(Style::default().fg(Color::Green)),
+            InputMode::NewPlaylist => Paragraph::new(self.input.clone())
+                .block(Block::default().title("Playlist Name").borders(Borders::ALL))
+                .style(Style::default().fg(Color::Yellow)),
+            InputMode::Criterion(_) => Paragraph::new(self.input.clone())
+                .block(
+                    Block::default()
+                        .title("Criterion (artist:, genre:, rating:, plays:, year:from-to, shorter:secs)")
+                        .borders(Borders::ALL),
+                )
+                .style(Style::default().fg(Color::Yellow)),
+        };
+        f.render_stateful_widget(playlist_list, chunks[0], &mut self.playlist_state);
+        f.render_stateful_widget(criteria_list, chunks[1], &mut self.criteria_state);
+        f.render_widget(status, rows[1]);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if !matches!(self.mode, InputMode::Browse) {
+            match key {
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Enter => self.submit_input(),
+                KeyCode::Esc => {
+                    self.input.clear();
+                    self.mode = InputMode::Browse;
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        match key {
+            KeyCode::Up => self.previous(),
+            KeyCode::Down => self.next(),
+            KeyCode::Left => self.selected_window = 0,
+            KeyCode::Right if self.selected_playlist().is_some() => {
+                self.selected_window = 1;
+                self.criteria_state.select(Some(0));
+            }
+            KeyCode::Enter => self.enqueue(),
+            // n skips to the next track from any window
+            KeyCode::Char('p') => self.mode = InputMode::NewPlaylist,
+            KeyCode::Char('a') if self.selected_playlist().is_some() => {
+                self.mode = InputMode::Criterion(None);
+            }
+            KeyCode::Char('e') if self.selected_window == 1 => {
+                let i = self.criteria_state.selected();
+                if let Some(criterion) = self
+                    .selected_playlist()
+                    .and_then(|playlist| playlist.criteria.get(i?).cloned())
+                {
+                    self.input = criterion.to_string();
+                    self.mode = InputMode::Criterion(i);
+                }
+            }
+            KeyCode::Char('d') => self.delete(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        !matches!(self.mode, InputMode::Browse)
+    }
+
+    fn on_library_changed(&mut self, music_list: &[AudioFile]) {
+        self.music_list = music_list.to_vec();
+    }
+}
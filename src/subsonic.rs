@@ -0,0 +1,480 @@
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    settings::Settings,
+    ui::Window,
+};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    io::{self, Stdout},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+const API_VERSION: &str = "1.16.1";
+const CLIENT_NAME: &str = "rmus";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SubsonicClient {
+    base_url: String,
+    username: String,
+    // md5(password + salt), the token used by the subsonic token auth scheme
+    password_md5: String,
+    salt: String,
+}
+
+#[derive(Deserialize)]
+struct SubsonicEnvelope {
+    #[serde(rename = "subsonic-response")]
+    response: SubsonicResponse,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubsonicResponse {
+    status: String,
+    error: Option<SubsonicError>,
+    random_songs: Option<SongList>,
+    search_result3: Option<SongList>,
+    playlists: Option<PlaylistList>,
+    playlist: Option<PlaylistEntries>,
+}
+
+#[derive(Deserialize)]
+struct SubsonicError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SongList {
+    #[serde(default)]
+    song: Vec<SubsonicSong>,
+}
+
+#[derive(Deserialize)]
+struct PlaylistList {
+    #[serde(default)]
+    playlist: Vec<SubsonicPlaylist>,
+}
+
+#[derive(Deserialize)]
+struct PlaylistEntries {
+    #[serde(default)]
+    entry: Vec<SubsonicSong>,
+}
+
+#[derive(Deserialize)]
+struct SubsonicSong {
+    id: String,
+    #[serde(default)]
+    title: String,
+    artist: Option<String>,
+    album: Option<String>,
+    year: Option<i32>,
+    duration: Option<f64>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsonicPlaylist {
+    id: String,
+    name: String,
+    #[serde(default)]
+    song_count: usize,
+}
+
+impl SubsonicClient {
+    pub fn new(base_url: &str, username: &str, password: &str) -> Self {
+        let salt = format!(
+            "{:x}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0)
+        );
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username: username.to_string(),
+            password_md5: format!("{:x}", md5::compute(format!("{}{}", password, salt))),
+            salt,
+        }
+    }
+
+    fn request(
+        &self,
+        endpoint: &str,
+        params: &[(&str, &str)],
+    ) -> Result<reqwest::blocking::RequestBuilder, reqwest::Error> {
+        let client = reqwest::blocking::Client::new();
+        Ok(client
+            .get(format!("{}/rest/{}", self.base_url, endpoint))
+            .query(&[
+                ("u", self.username.as_str()),
+                ("t", self.password_md5.as_str()),
+                ("s", self.salt.as_str()),
+                ("v", API_VERSION),
+                ("c", CLIENT_NAME),
+                ("f", "json"),
+            ])
+            .query(params))
+    }
+
+    fn get(
+        &self,
+        endpoint: &str,
+        params: &[(&str, &str)],
+    ) -> Result<SubsonicResponse, reqwest::Error> {
+        let envelope: SubsonicEnvelope = self
+            .request(endpoint, params)?
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(envelope.response)
+    }
+
+    fn to_audio_files(&self, songs: Vec<SubsonicSong>) -> Result<Vec<AudioFile>, reqwest::Error> {
+        songs
+            .into_iter()
+            .map(|song| {
+                Ok(AudioFile::from_stream(
+                    self.stream(&song.id)?,
+                    song.title,
+                    song.artist.unwrap_or_else(|| "Unknown".to_string()),
+                    song.album.unwrap_or_else(|| "Unknown".to_string()),
+                    song.year.unwrap_or(0),
+                    song.duration.unwrap_or(0.0),
+                ))
+            })
+            .collect()
+    }
+
+    pub fn get_random_songs(&self, size: usize) -> Result<Vec<AudioFile>, reqwest::Error> {
+        let response = self.get("getRandomSongs", &[("size", size.to_string().as_str())])?;
+        self.to_audio_files(response.random_songs.map(|l| l.song).unwrap_or_default())
+    }
+
+    pub fn search3(&self, query: &str) -> Result<Vec<AudioFile>, reqwest::Error> {
+        let response = self.get(
+            "search3",
+            &[("query", query), ("artistCount", "0"), ("albumCount", "0")],
+        )?;
+        self.to_audio_files(response.search_result3.map(|l| l.song).unwrap_or_default())
+    }
+
+    pub fn get_playlists(&self) -> Result<Vec<SubsonicPlaylist>, reqwest::Error> {
+        let response = self.get("getPlaylists", &[])?;
+        Ok(response.playlists.map(|l| l.playlist).unwrap_or_default())
+    }
+
+    pub fn get_playlist(&self, id: &str) -> Result<Vec<AudioFile>, reqwest::Error> {
+        let response = self.get("getPlaylist", &[("id", id)])?;
+        self.to_audio_files(response.playlist.map(|l| l.entry).unwrap_or_default())
+    }
+
+    // builds the authenticated url that AudioInterface::play_url downloads
+    pub fn stream(&self, id: &str) -> Result<String, reqwest::Error> {
+        let request = self.request("stream", &[("id", id)])?.build()?;
+        Ok(request.url().to_string())
+    }
+
+    pub fn ping(&self) -> Result<Option<String>, reqwest::Error> {
+        let response = self.get("ping", &[])?;
+        if response.status == "ok" {
+            Ok(None)
+        } else {
+            Ok(Some(
+                response
+                    .error
+                    .map(|e| e.message)
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+            ))
+        }
+    }
+}
+
+enum SubsonicView {
+    Songs(Vec<AudioFile>),
+    Playlists(Vec<SubsonicPlaylist>),
+}
+
+enum InputMode {
+    Browse,
+    Search,
+    // collects server url, username and password in that order
+    Login(Vec<String>),
+}
+
+const LOGIN_PROMPTS: [&str; 3] = ["Server URL", "Username", "Password"];
+
+pub struct SubsonicWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    state: ListState,
+    view: SubsonicView,
+    mode: InputMode,
+    input: String,
+    status: String,
+}
+
+impl SubsonicWindow {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+    ) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        let status = if settings.borrow().subsonic.is_some() {
+            String::from("Press / to search, r for random songs, p for playlists")
+        } else {
+            String::from("No server configured - press Enter to log in")
+        };
+        Self {
+            title: String::from("Subsonic"),
+            settings,
+            audio_interface,
+            state,
+            view: SubsonicView::Songs(Vec::new()),
+            mode: InputMode::Browse,
+            input: String::new(),
+            status,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match &self.view {
+            SubsonicView::Songs(songs) => songs.len(),
+            SubsonicView::Playlists(playlists) => playlists.len(),
+        }
+    }
+
+    pub fn next(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i + 1 >= self.len() {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.len().saturating_sub(1)
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    fn show_songs(&mut self, result: Result<Vec<AudioFile>, reqwest::Error>) {
+        match result {
+            Ok(songs) => {
+                self.status = format!("{} songs found", songs.len());
+                self.view = SubsonicView::Songs(songs);
+                self.state.select(Some(0));
+            }
+            Err(e) => self.status = format!("Request failed: {}", e),
+        }
+    }
+
+    fn client(&self) -> Option<SubsonicClient> {
+        self.settings.borrow().subsonic.clone()
+    }
+
+    fn submit_input(&mut self) {
+        let input = std::mem::take(&mut self.input);
+        match std::mem::replace(&mut self.mode, InputMode::Browse) {
+            InputMode::Browse => {}
+            InputMode::Search => {
+                if let Some(client) = self.client() {
+                    self.show_songs(client.search3(&input));
+                }
+            }
+            InputMode::Login(mut fields) => {
+                fields.push(input);
+                if fields.len() < LOGIN_PROMPTS.len() {
+                    self.mode = InputMode::Login(fields);
+                    return;
+                }
+                let client = SubsonicClient::new(&fields[0], &fields[1], &fields[2]);
+                match client.ping() {
+                    Ok(None) => {
+                        self.status = format!("Logged in to {}", fields[0]);
+                        self.settings.borrow_mut().subsonic = Some(client);
+                    }
+                    Ok(Some(message)) => self.status = format!("Login failed: {}", message),
+                    Err(e) => self.status = format!("Login failed: {}", e),
+                }
+            }
+        }
+    }
+
+    fn select(&mut self) {
+        let Some(i) = self.state.selected() else {
+            return;
+        };
+        match &self.view {
+            SubsonicView::Songs(songs) => {
+                if let Some(song) = songs.get(i) {
+                    self.audio_interface
+                        .borrow_mut()
+                        .append_to_queue(&mut vec![song.clone()]);
+                    self.status = format!("Queued {}", song.get_title());
+                }
+            }
+            SubsonicView::Playlists(playlists) => {
+                if let (Some(playlist), Some(client)) = (playlists.get(i), self.client()) {
+                    let id = playlist.id.clone();
+                    self.show_songs(client.get_playlist(&id));
+                }
+            }
+        }
+    }
+}
+
+impl Window for SubsonicWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let (prompt, text) = match &self.mode {
+            InputMode::Login(fields) => (
+                LOGIN_PROMPTS[fields.len()],
+                if fields.len() == 2 {
+                    "*".repeat(self.input.len())
+                } else {
+                    self.input.clone()
+                },
+            ),
+            _ => ("Search", self.input.clone()),
+        };
+        let input_style = match self.mode {
+            InputMode::Browse => Style::default().fg(Color::Green),
+            _ => Style::default().fg(Color::Yellow),
+        };
+        let search_bar = Paragraph::new(text)
+            .block(Block::default().title(prompt).borders(Borders::ALL))
+            .style(input_style);
+        let (list_title, items) = match &self.view {
+            SubsonicView::Songs(songs) => (
+                "Songs",
+                songs
+                    .iter()
+                    .map(|song| {
+                        ListItem::new(format!(
+                            "{} - {} ({})",
+                            song.get_title(),
+                            song.get_artist(),
+                            song.get_duration()
+                        ))
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            SubsonicView::Playlists(playlists) => (
+                "Playlists",
+                playlists
+                    .iter()
+                    .map(|playlist| {
+                        ListItem::new(format!("{} ({} songs)", playlist.name, playlist.song_count))
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        };
+        let results = List::new(items)
+            .block(Block::default().title(list_title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let status = Paragraph::new(self.status.clone())
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(search_bar, chunks[0]);
+        f.render_stateful_widget(results, chunks[1], &mut self.state);
+        f.render_widget(status, chunks[2]);
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if let InputMode::Browse = self.mode {
+            match key {
+                KeyCode::Up => self.previous(),
+                KeyCode::Down => self.next(),
+                KeyCode::Enter if self.client().is_none() => {
+                    self.mode = InputMode::Login(Vec::new());
+                }
+                KeyCode::Enter => self.select(),
+                KeyCode::Char('/') if self.client().is_some() => self.mode = InputMode::Search,
+                KeyCode::Char('r') => {
+                    if let Some(client) = self.client() {
+                        self.show_songs(client.get_random_songs(50));
+                    }
+                }
+                KeyCode::Char('p') => {
+                    if let Some(client) = self.client() {
+                        match client.get_playlists() {
+                            Ok(playlists) => {
+                                self.status = format!("{} playlists found", playlists.len());
+                                self.view = SubsonicView::Playlists(playlists);
+                                self.state.select(Some(0));
+                            }
+                            Err(e) => self.status = format!("Request failed: {}", e),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            match key {
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Enter => self.submit_input(),
+                KeyCode::Esc => {
+                    self.input.clear();
+                    self.mode = InputMode::Browse;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        !matches!(self.mode, InputMode::Browse)
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    audio::AudioFile,
+    ui::{render_popup, Window},
+};
+use audiotags::Tag;
+use crossterm::event::KeyCode;
+use std::{
+    io::{self, Stdout},
+    path::PathBuf,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+const FIELDS: [&str; 8] = [
+    "Title", "Artist", "Album", "Year", "Track", "Genre", "MBID", "Offset",
+];
+const YEAR: usize = 3;
+const TRACK: usize = 4;
+// a musicbrainz recording id, not written to the file but offered to acoustid
+const RECORDING_ID: usize = 6;
+// seconds of silence to skip at the start, kept in the library cache
+const START_OFFSET: usize = 7;
+
+// popup for editing the tags of a single file. the library window owns it
+// and handles Enter/Esc, everything else edits the fields
+pub struct EditTagsWindow {
+    path: PathBuf,
+    values: Vec<String>,
+    selected: usize,
+}
+
+impl EditTagsWindow {
+    pub fn new(file: &AudioFile) -> Result<Self, io::Error> {
+        let tag = Tag::new()
+            .read_from_path(file.get_path())
+            .map_err(io::Error::other)?;
+        let values = vec![
+            tag.title().unwrap_or_default().to_string(),
+            tag.artist().unwrap_or_default().to_string(),
+            tag.album_title().unwrap_or_default().to_string(),
+            tag.year().map(|year| year.to_string()).unwrap_or_default(),
+            tag.track_number()
+                .map(|track| track.to_string())
+                .unwrap_or_default(),
+            tag.genre().unwrap_or_default().to_string(),
+            String::new(),
+            match file.get_start_offset_secs() {
+                offset if offset > 0.0 => offset.to_string(),
+                _ => String::new(),
+            },
+        ];
+        Ok(Self {
+            path: file.get_path().to_path_buf(),
+            values,
+            selected: 0,
+        })
+    }
+
+    // writes the fields back to the file, empty fields remove the tag
+    pub fn save(&self) -> Result<(), io::Error> {
+        let year = parse_optional::<i32>(&self.values[YEAR], "year")?;
+        let track = parse_optional::<u16>(&self.values[TRACK], "track number")?;
+        parse_optional::<f64>(&self.values[START_OFFSET], "start offset")?;
+        let mut tag = Tag::new()
+            .read_from_path(&self.path)
+            .map_err(io::Error::other)?;
+        match self.values[0].as_str() {
+            "" => tag.remove_title(),
+            title => tag.set_title(title),
+        }
+        match self.values[1].as_str() {
+            "" => tag.remove_artist(),
+            artist => tag.set_artist(artist),
+        }
+        match self.values[2].as_str() {
+            "" => tag.remove_album_title(),
+            album => tag.set_album_title(album),
+        }
+        match year {
+            Some(year) => tag.set_year(year),
+            None => tag.remove_year(),
+        }
+        match track {
+            Some(track) => tag.set_track_number(track),
+            None => tag.remove_track_number(),
+        }
+        match self.values[5].as_str() {
+            "" => tag.remove_genre(),
+            genre => tag.set_genre(genre),
+        }
+        tag.write_to_path(&self.path.to_string_lossy())
+            .map_err(io::Error::other)
+    }
+
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn recording_id(&self) -> Option<&str> {
+        Some(self.values[RECORDING_ID].trim()).filter(|id| !id.is_empty())
+    }
+
+    // copies the saved fields onto the library's copy of the file
+    pub fn apply(&self, file: &mut AudioFile) {
+        let or_unknown = |value: &String| {
+            if value.is_empty() {
+                String::from("Unknown")
+            } else {
+                value.clone()
+            }
+        };
+        file.set_tags(
+            or_unknown(&self.values[0]),
+            or_unknown(&self.values[1]),
+            or_unknown(&self.values[2]),
+        );
+        file.set_year(self.values[YEAR].parse().unwrap_or(0));
+        file.set_genre(or_unknown(&self.values[5]));
+        file.set_start_offset_secs(self.values[START_OFFSET].parse().unwrap_or(0.0));
+    }
+}
+
+fn parse_optional<T: std::str::FromStr>(value: &str, name: &str) -> Result<Option<T>, io::Error> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse::<T>().map(Some).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid {}: {}", name, value),
+        )
+    })
+}
+
+impl Window for EditTagsWindow {
+    fn get_title(&self) -> String {
+        String::from("Edit Tags - Tab to move, Enter to save, Esc to cancel")
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let lines = FIELDS
+            .iter()
+            .zip(self.values.iter())
+            .enumerate()
+            .map(|(i, (field, value))| {
+                if i == self.selected {
+                    Line::from(Span::styled(
+                        format!("{:>7}: {}_", field, value),
+                        Style::default().fg(Color::Yellow),
+                    ))
+                } else {
+                    Line::from(format!("{:>7}: {}", field, value))
+                }
+            })
+            .collect::<Vec<_>>();
+        let editor = Paragraph::new(lines).style(Style::default().fg(Color::Green));
+        render_popup(f, area, &self.get_title(), |inner, f| {
+            f.render_widget(editor, inner)
+        });
+        Ok(())
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        match key {
+            KeyCode::Tab | KeyCode::Down => self.selected = (self.selected + 1) % FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                self.selected = (self.selected + FIELDS.len() - 1) % FIELDS.len()
+            }
+            KeyCode::Char(c) => self.values[self.selected].push(c),
+            KeyCode::Backspace => {
+                self.values[self.selected].pop();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_typing(&self) -> bool {
+        true
+    }
+}
@@ -1,7 +1,89 @@
-use crate::ui::Window;
+use crate::{
+    audio::{AudioFile, AudioInterface},
+    log,
+    log::Level,
+    ui::Window,
+};
+use crossterm::event::KeyCode;
+use reqwest::{Method, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::json;
-use std::{cell::RefCell, fs, rc::Rc};
+use std::{
+    cell::RefCell,
+    fmt, fs,
+    io::{self, Stdout},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs},
+    Frame,
+};
 
+// favorites are fetched in one page, enough for browsing from the terminal
+const PAGE_LIMIT: &str = "100";
+const COLLECTION_TABS: [&str; 3] = ["Tracks", "Albums", "Playlists"];
+// tidal asks for the token to be polled no faster than this
+const AUTHORIZATION_INTERVAL: Duration = Duration::from_secs(5);
+const AUTHORIZATION_ATTEMPTS: u32 = 6;
+
+enum Authorization {
+    Waiting(u32),
+    Authorized(TidalTokenResponse),
+    Failed(String),
+}
+
+// what the token endpoint answers with once the device is authorized
+#[derive(Deserialize)]
+struct TidalTokenResponse {
+    access_token: String,
+    refresh_token: String,
+    token_type: String,
+    // seconds
+    expires_in: u64,
+    #[serde(rename = "countryCode", default)]
+    country_code: String,
+}
+
+// tidal_session.json. the tokens are null when it was saved logged out
+#[derive(Deserialize)]
+struct SavedSession {
+    client_id: String,
+    device_code: String,
+    country_code: Option<String>,
+    token_type: Option<String>,
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+}
+
+/// A login to Tidal and the requests made with it.
+///
+/// The session is kept in `tidal_session.json` in the working directory.
+/// Logging in uses the device flow. [`login_oauth`](Self::login_oauth) asks
+/// for a device code, the user authorizes it in a browser, and
+/// [`start_authorization`](Self::start_authorization) waits for the tokens.
+///
+/// # Examples
+///
+/// ```no_run
+/// use music_player::tidal::{TidalItem, TidalSession};
+///
+/// let mut session = TidalSession::new();
+/// if session.is_logged_in() {
+///     for item in session.favorite_tracks()? {
+///         if let TidalItem::Track(track) = item {
+///             println!("{}", session.to_audio_file(&track)?.get_title());
+///         }
+///     }
+/// }
+/// # Ok::<(), reqwest::Error>(())
+/// ```
 pub struct TidalSession {
     client_id: String,
     url: String,
@@ -11,9 +93,136 @@ pub struct TidalSession {
     access_token: Option<String>,
     refresh_token: Option<String>,
     log: String,
+    // looked up from the session the first time the collection is opened
+    user_id: Option<u64>,
+    // set while the device code is being polled for a token
+    authorization: Option<Receiver<Authorization>>,
+    // a fresh stream url for the track that plays next, see prefetch_stream_url
+    next_stream_url: Option<(u64, String)>,
+    prefetch: Option<(u64, Receiver<Result<String, reqwest::Error>>)>,
+}
+
+#[derive(Debug)]
+pub enum TidalError {
+    NotLoggedIn,
+    // playlists can only be changed along with the ETag they were last read with
+    MissingETag,
+    Request(reqwest::Error),
+}
+
+impl fmt::Display for TidalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TidalError::NotLoggedIn => write!(f, "not logged in to Tidal"),
+            TidalError::MissingETag => write!(f, "Tidal sent the playlist without an ETag"),
+            TidalError::Request(e) => write!(f, "request failed: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for TidalError {
+    fn from(e: reqwest::Error) -> Self {
+        TidalError::Request(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    items: Vec<T>,
+}
+
+// favorites come wrapped along with when they were added
+#[derive(Deserialize)]
+struct Favorite<T> {
+    item: T,
+}
+
+#[derive(Clone, Deserialize)]
+struct TidalArtist {
+    name: String,
+}
+
+#[derive(Clone, Deserialize)]
+struct TidalAlbumTitle {
+    title: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TidalTrack {
+    id: u64,
+    title: String,
+    #[serde(default)]
+    duration: f64,
+    artist: Option<TidalArtist>,
+    album: Option<TidalAlbumTitle>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TidalAlbum {
+    id: u64,
+    title: String,
+    artist: Option<TidalArtist>,
+    #[serde(default)]
+    number_of_tracks: usize,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TidalPlaylist {
+    uuid: String,
+    title: String,
+    #[serde(default)]
+    number_of_tracks: usize,
+}
+
+#[derive(Clone)]
+pub enum TidalItem {
+    Track(TidalTrack),
+    Album(TidalAlbum),
+    Playlist(TidalPlaylist),
+}
+
+impl TidalItem {
+    fn describe(&self) -> String {
+        let artist = |artist: &Option<TidalArtist>| {
+            artist
+                .as_ref()
+                .map(|artist| artist.name.clone())
+                .unwrap_or_else(|| String::from("Unknown"))
+        };
+        match self {
+            TidalItem::Track(track) => format!("{} - {}", track.title, artist(&track.artist)),
+            TidalItem::Album(album) => format!(
+                "{} - {} ({} tracks)",
+                album.title,
+                artist(&album.artist),
+                album.number_of_tracks
+            ),
+            TidalItem::Playlist(playlist) => {
+                format!("{} ({} tracks)", playlist.title, playlist.number_of_tracks)
+            }
+        }
+    }
+
+    // the favorites endpoint for this kind of item, and the form field POST takes
+    fn favorites_path(&self) -> (&'static str, &'static str, String) {
+        match self {
+            TidalItem::Track(track) => ("tracks", "trackIds", track.id.to_string()),
+            TidalItem::Album(album) => ("albums", "albumIds", album.id.to_string()),
+            TidalItem::Playlist(playlist) => ("playlists", "uuids", playlist.uuid.clone()),
+        }
+    }
 }
 
 impl TidalSession {
+    /// Writes the session to `tidal_session.json`, replacing the old file.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the working directory can't be found or the file can't be
+    /// replaced.
     pub fn save(&self) {
         //remove old tidal_session.json
         let cwd = std::env::current_dir().unwrap();
@@ -35,6 +244,16 @@ impl TidalSession {
         std::fs::write("tidal_session.json", pretty).unwrap();
     }
 
+    /// Loads the session saved in `tidal_session.json`. Without a saved
+    /// session, it starts a logged out one with the client id from the first
+    /// line of `CREDENTIALS.txt`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when neither file can be read, or when the saved session isn't
+    /// valid JSON.
+    // reads files and can panic, which Default shouldn't
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         // check if tidal_session.json exists
         let cwd = std::env::current_dir().unwrap();
@@ -52,42 +271,41 @@ impl TidalSession {
                 country_code: None,
                 log: "Empty".to_string(),
                 url: "https://api.tidal.com/v1/".to_string(),
+                user_id: None,
+                authorization: None,
+                next_stream_url: None,
+                prefetch: None,
             }
         } else {
-            // read tidal_session.json
-            let tidal_json: serde_json::Value =
-                serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
-            let client_id = tidal_json["client_id"].to_string();
-            let device_code = tidal_json["device_code"].to_string();
-            let access_token = tidal_json["access_token"].to_string();
-            let refresh_token = tidal_json["refresh_token"].to_string();
-            let token_type = tidal_json["token_type"].to_string();
-            let country_code = tidal_json["country_code"].to_string();
-            // remove quotes from all the above:
-            let client_id = client_id[1..client_id.len() - 1].to_string();
-            let device_code = device_code[1..device_code.len() - 1].to_string();
-            let access_token = access_token[1..access_token.len() - 1].to_string();
-            let refresh_token = refresh_token[1..refresh_token.len() - 1].to_string();
-            let token_type = token_type[1..token_type.len() - 1].to_string();
-            let country_code = country_code[1..country_code.len() - 1].to_string();
-            let log = serde_json::to_string_pretty(&tidal_json).unwrap();
+            let log = fs::read_to_string(path).unwrap();
+            let saved: SavedSession = serde_json::from_str(&log).unwrap();
             Self {
-                client_id,
-                device_code,
-                country_code: Some(country_code),
-                access_token: Some(access_token),
-                refresh_token: Some(refresh_token),
-                token_type: Some(token_type),
+                client_id: saved.client_id,
+                device_code: saved.device_code,
+                country_code: saved.country_code,
+                access_token: saved.access_token,
+                refresh_token: saved.refresh_token,
+                token_type: saved.token_type,
                 log,
                 url: "https://api.tidal.com/v1/".to_string(),
+                user_id: None,
+                authorization: None,
+                next_stream_url: None,
+                prefetch: None,
             }
         }
     }
 
+    /// Asks Tidal for a device code to authorize. The response shows up in
+    /// the session log.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the request fails or Tidal's response isn't JSON.
     pub fn login_oauth(&mut self) {
         // inital request
         self.log = "beginning request".to_string();
-        let url = format!("https://auth.tidal.com/v1/oauth2/device_authorization");
+        let url = "https://auth.tidal.com/v1/oauth2/device_authorization";
         let mut header = reqwest::header::HeaderMap::new();
         header.insert(
             "Content-Type",
@@ -106,54 +324,399 @@ impl TidalSession {
             .unwrap();
         let response_text = response.text().unwrap();
         let json: serde_json::Value = serde_json::from_str(response_text.as_str()).unwrap();
-        self.device_code = json["deviceCode"].to_string();
-        // remove quotes from device code:
-        self.device_code = self.device_code[1..self.device_code.len() - 1].to_string();
+        self.device_code = json["deviceCode"].as_str().unwrap_or_default().to_string();
         let pretty = serde_json::to_string_pretty(&json).unwrap();
         self.log = format!("response: {}\n device code: {}", pretty, self.device_code);
     }
 
-    fn post_after_user(&mut self) -> String {
-        let client = reqwest::blocking::Client::new();
-        let url = "https://auth.tidal.com/v1/oauth2/token";
-        let mut header = reqwest::header::HeaderMap::new();
-        header.insert(
-            "Content-Type",
-            "application/x-www-form-urlencoded".parse().unwrap(),
+    /// Polls Tidal for the tokens on a thread of its own, until the device
+    /// code is authorized or the attempts run out.
+    /// [`poll_authorization`](Self::poll_authorization) picks up how it went.
+    /// Does nothing while a poll is already running.
+    pub fn start_authorization(&mut self) {
+        if self.authorization.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let client_id = self.client_id.clone();
+        let device_code = self.device_code.clone();
+        thread::spawn(move || {
+            for attempt in 1..=AUTHORIZATION_ATTEMPTS {
+                if sender.send(Authorization::Waiting(attempt)).is_err() {
+                    return;
+                }
+                let result = match request_token(&client_id, &device_code) {
+                    Ok(Some(token)) => Authorization::Authorized(token),
+                    Ok(None) if attempt < AUTHORIZATION_ATTEMPTS => {
+                        thread::sleep(AUTHORIZATION_INTERVAL);
+                        continue;
+                    }
+                    Ok(None) => Authorization::Failed(String::from(
+                        "the device wasn't authorized in time, press e to start again",
+                    )),
+                    Err(e) => Authorization::Failed(format!("request failed: {}", e)),
+                };
+                let _ = sender.send(result);
+                return;
+            }
+        });
+        self.authorization = Some(receiver);
+    }
+
+    /// Takes in the tokens, or the reason authorization failed, once the
+    /// thread from [`start_authorization`](Self::start_authorization) has
+    /// them. The session log follows the progress.
+    pub fn poll_authorization(&mut self) {
+        let Some(receiver) = &self.authorization else {
+            return;
+        };
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                Authorization::Waiting(attempt) => {
+                    self.log = format!(
+                        "Waiting for authorization… attempt {}/{}",
+                        attempt, AUTHORIZATION_ATTEMPTS
+                    );
+                }
+                Authorization::Authorized(token) => {
+                    self.log = format!(
+                        "Logged in, the access token expires in {} seconds",
+                        token.expires_in
+                    );
+                    self.access_token = Some(token.access_token);
+                    self.refresh_token = Some(token.refresh_token);
+                    self.token_type = Some(token.token_type);
+                    self.country_code = Some(token.country_code).filter(|code| !code.is_empty());
+                    finished = true;
+                }
+                Authorization::Failed(message) => {
+                    self.log = message;
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.authorization = None;
+        }
+    }
+}
+
+// None while the user still hasn't authorized the device
+fn fetch_stream_url(request: reqwest::blocking::RequestBuilder) -> Result<String, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct StreamUrl {
+        url: String,
+    }
+    let stream: StreamUrl = request.send()?.error_for_status()?.json()?;
+    Ok(stream.url)
+}
+
+fn request_token(
+    client_id: &str,
+    device_code: &str,
+) -> Result<Option<TidalTokenResponse>, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let url = "https://auth.tidal.com/v1/oauth2/token";
+    let mut header = reqwest::header::HeaderMap::new();
+    header.insert(
+        "Content-Type",
+        "application/x-www-form-urlencoded".parse().unwrap(),
+    );
+    let response = client
+        .post(url)
+        .query(&[
+            ("client_id", client_id),
+            ("client_secret", client_id),
+            ("device_code", device_code),
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ("scope", "r_usr"),
+        ])
+        .headers(header)
+        .send()?;
+    if response.status() == StatusCode::BAD_REQUEST {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.json()?))
+}
+
+// the collection api, only usable once logged in
+impl TidalSession {
+    /// Whether the session has an access token.
+    pub fn is_logged_in(&self) -> bool {
+        self.access_token.is_some()
+    }
+
+    fn request(&self, method: Method, path: &str) -> reqwest::blocking::RequestBuilder {
+        reqwest::blocking::Client::new()
+            .request(method, format!("{}{}", self.url, path))
+            .bearer_auth(self.access_token.as_deref().unwrap_or_default())
+            .query(&[("countryCode", self.country_code.as_deref().unwrap_or("US"))])
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, reqwest::Error> {
+        self.request(Method::GET, path)
+            .query(&[("limit", PAGE_LIMIT)])
+            .send()?
+            .error_for_status()?
+            .json()
+    }
+
+    fn user_id(&mut self) -> Result<u64, reqwest::Error> {
+        if let Some(user_id) = self.user_id {
+            return Ok(user_id);
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Session {
+            user_id: u64,
+        }
+        let session: Session = self.get("sessions")?;
+        self.user_id = Some(session.user_id);
+        Ok(session.user_id)
+    }
+
+    /// The tracks in the user's favorites.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a request fails, usually because the session has expired.
+    pub fn favorite_tracks(&mut self) -> Result<Vec<TidalItem>, reqwest::Error> {
+        let path = format!("users/{}/favorites/tracks", self.user_id()?);
+        let page: Page<Favorite<TidalTrack>> = self.get(&path)?;
+        Ok(page
+            .items
+            .into_iter()
+            .map(|favorite| TidalItem::Track(favorite.item))
+            .collect())
+    }
+
+    /// The albums in the user's favorites.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`favorite_tracks`](Self::favorite_tracks).
+    pub fn favorite_albums(&mut self) -> Result<Vec<TidalItem>, reqwest::Error> {
+        let path = format!("users/{}/favorites/albums", self.user_id()?);
+        let page: Page<Favorite<TidalAlbum>> = self.get(&path)?;
+        Ok(page
+            .items
+            .into_iter()
+            .map(|favorite| TidalItem::Album(favorite.item))
+            .collect())
+    }
+
+    /// The user's playlists.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`favorite_tracks`](Self::favorite_tracks).
+    pub fn playlists(&mut self) -> Result<Vec<TidalItem>, reqwest::Error> {
+        let path = format!("users/{}/playlists", self.user_id()?);
+        let page: Page<TidalPlaylist> = self.get(&path)?;
+        Ok(page.items.into_iter().map(TidalItem::Playlist).collect())
+    }
+
+    /// The tracks of an album or playlist. A track has none.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`favorite_tracks`](Self::favorite_tracks).
+    pub fn tracks_of(&self, item: &TidalItem) -> Result<Vec<TidalItem>, reqwest::Error> {
+        let path = match item {
+            TidalItem::Album(album) => format!("albums/{}/tracks", album.id),
+            TidalItem::Playlist(playlist) => format!("playlists/{}/tracks", playlist.uuid),
+            TidalItem::Track(_) => return Ok(Vec::new()),
+        };
+        let page: Page<TidalTrack> = self.get(&path)?;
+        Ok(page.items.into_iter().map(TidalItem::Track).collect())
+    }
+
+    fn stream_url_request(&self, track_id: u64) -> reqwest::blocking::RequestBuilder {
+        self.request(Method::GET, &format!("tracks/{}/streamUrl", track_id))
+            .query(&[("soundQuality", "HIGH")])
+    }
+
+    /// Fetches a stream URL for the track that plays next, on a thread of its
+    /// own.
+    ///
+    /// Stream URLs expire, and asking for one takes long enough to leave a
+    /// gap between tracks. [`stream_url_for`](Self::stream_url_for) hands the
+    /// URL out once it has arrived. Does nothing when the URL is already
+    /// fetched or on its way.
+    pub fn prefetch_stream_url(&mut self, track_id: u64) {
+        let fetched = self
+            .next_stream_url
+            .as_ref()
+            .is_some_and(|(id, _)| *id == track_id);
+        let fetching = self
+            .prefetch
+            .as_ref()
+            .is_some_and(|(id, _)| *id == track_id);
+        if fetched || fetching {
+            return;
+        }
+        let request = self.stream_url_request(track_id);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(fetch_stream_url(request));
+        });
+        self.prefetch = Some((track_id, receiver));
+    }
+
+    /// The URL from [`prefetch_stream_url`](Self::prefetch_stream_url) for
+    /// `track_id`, once it has arrived. A failed fetch is logged and gives
+    /// `None`.
+    pub fn stream_url_for(&mut self, track_id: u64) -> Option<&str> {
+        if let Some((id, receiver)) = &self.prefetch {
+            match receiver.try_recv() {
+                Ok(Ok(url)) => {
+                    self.next_stream_url = Some((*id, url));
+                    self.prefetch = None;
+                }
+                Ok(Err(e)) => {
+                    log!(Level::Warn, "could not prefetch a tidal stream: {}", e);
+                    self.prefetch = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.prefetch = None,
+            }
+        }
+        match &self.next_stream_url {
+            Some((id, url)) if *id == track_id => Some(url),
+            _ => None,
+        }
+    }
+
+    /// A playable track, streamed from a fresh stream URL.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the stream URL can't be fetched.
+    pub fn to_audio_file(&self, track: &TidalTrack) -> Result<AudioFile, reqwest::Error> {
+        let mut file = AudioFile::from_stream(
+            fetch_stream_url(self.stream_url_request(track.id))?,
+            track.title.clone(),
+            track
+                .artist
+                .as_ref()
+                .map(|artist| artist.name.clone())
+                .unwrap_or_else(|| String::from("Unknown")),
+            track
+                .album
+                .as_ref()
+                .map(|album| album.title.clone())
+                .unwrap_or_else(|| String::from("Unknown")),
+            0,
+            track.duration,
         );
-        let response2 = client
-            .post(url)
-            .query(&[
-                ("client_id", self.client_id.as_str()),
-                ("client_secret", self.client_id.as_str()),
-                ("device_code", self.device_code.as_str()),
-                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
-                ("scope", "r_usr"),
+        file.set_tidal_id(Some(track.id));
+        Ok(file)
+    }
+
+    /// Creates an empty playlist called `name` and returns its id.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TidalError::NotLoggedIn`] without a session. Returns
+    /// [`TidalError::Request`] when a request fails.
+    pub fn create_playlist(&mut self, name: &str) -> Result<String, TidalError> {
+        if !self.is_logged_in() {
+            return Err(TidalError::NotLoggedIn);
+        }
+        let path = format!("users/{}/playlists", self.user_id()?);
+        let playlist: TidalPlaylist = self
+            .request(Method::POST, &path)
+            .form(&[("title", name), ("description", "")])
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(playlist.uuid)
+    }
+
+    /// Adds tracks to the end of a playlist.
+    ///
+    /// Tidal only changes a playlist along with the ETag it was last read
+    /// with, so the playlist is read first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TidalError::NotLoggedIn`] without a session, and
+    /// [`TidalError::MissingETag`] when the playlist comes without an ETag.
+    /// Returns [`TidalError::Request`] when a request fails, like when a track
+    /// is already in the playlist.
+    pub fn add_tracks_to_playlist(
+        &self,
+        playlist_id: &str,
+        track_ids: &[u64],
+    ) -> Result<(), TidalError> {
+        if !self.is_logged_in() {
+            return Err(TidalError::NotLoggedIn);
+        }
+        let path = format!("playlists/{}", playlist_id);
+        let playlist = self
+            .request(Method::GET, &path)
+            .send()?
+            .error_for_status()?;
+        let etag = playlist
+            .headers()
+            .get(reqwest::header::ETAG)
+            .cloned()
+            .ok_or(TidalError::MissingETag)?;
+        let track_ids = track_ids
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        self.request(Method::POST, &format!("{}/items", path))
+            .header(reqwest::header::IF_NONE_MATCH, etag)
+            .form(&[
+                ("trackIds", track_ids.as_str()),
+                ("onArtifactNotFound", "FAIL"),
+                ("onDupes", "FAIL"),
             ])
-            .headers(header)
-            .send()
-            .unwrap();
-        if response2.status().is_success() {
-            let json: serde_json::Value = serde_json::from_str(&response2.text().unwrap()).unwrap();
-            let access_token = json["access_token"].to_string();
-            let refresh_token = json["refresh_token"].to_string();
-            let token_type = json["token_type"].to_string();
-            let country_code = json["countryCode"].to_string();
-            // remove quotes from both tokens:
-            self.access_token = Some(access_token[1..access_token.len() - 1].to_string());
-            self.refresh_token = Some(refresh_token[1..refresh_token.len() - 1].to_string());
-            self.token_type = Some(token_type[1..token_type.len() - 1].to_string());
-            self.country_code = Some(country_code[1..country_code.len() - 1].to_string());
-            serde_json::to_string_pretty(&json).unwrap()
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Adds `item` to the user's favorites, or removes it.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a request fails.
+    pub fn set_favorite(&mut self, item: &TidalItem, favorite: bool) -> Result<(), reqwest::Error> {
+        let (kind, field, id) = item.favorites_path();
+        let path = format!("users/{}/favorites/{}", self.user_id()?, kind);
+        let request = if favorite {
+            self.request(Method::POST, &path)
+                .form(&[(field, id.as_str())])
         } else {
-            format!("{}\n\n{}", response2.status(), response2.text().unwrap())
-        }
+            self.request(Method::DELETE, &format!("{}/{}", path, id))
+        };
+        request.send()?.error_for_status()?;
+        Ok(())
     }
 }
 
+// an item in the collection list, with whether it is in the user's favorites
+struct Entry {
+    item: TidalItem,
+    favorite: bool,
+}
+
 pub struct TidalWindow {
     pub session: Rc<RefCell<TidalSession>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
     title: String,
+    // index into COLLECTION_TABS
+    tab: usize,
+    // None until the tab has been loaded
+    collection: [Option<Vec<Entry>>; 3],
+    // an album or playlist opened from the collection, with its tracks
+    opened: Option<(String, Vec<Entry>)>,
+    state: ListState,
+    status: String,
 }
 
 impl Window for TidalWindow {
@@ -163,25 +726,95 @@ impl Window for TidalWindow {
 
     fn draw(
         &mut self,
-        area: tui::prelude::Rect,
-        f: &mut tui::Frame<tui::prelude::CrosstermBackend<std::io::Stdout>>,
-    ) -> Result<(), std::io::Error> {
-        let output = tui::widgets::Paragraph::new(self.session.borrow().log.clone());
-        f.render_widget(output, area);
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        self.session.borrow_mut().poll_authorization();
+        if !self.session.borrow().is_logged_in() {
+            let output = Paragraph::new(self.session.borrow().log.clone()).block(
+                Block::default()
+                    .title("e to log in, a once the device is authorized")
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(output, area);
+            return Ok(());
+        }
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let tabs = Tabs::new(COLLECTION_TABS.iter().map(|tab| Line::from(*tab)).collect())
+            .block(
+                Block::default()
+                    .title("My Collection - Left/Right to switch")
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().fg(Color::Yellow))
+            .select(self.tab);
+        let (list_title, entries) = match &self.opened {
+            Some((title, entries)) => (format!("{} - Esc to go back", title), Some(entries)),
+            None => (
+                String::from(COLLECTION_TABS[self.tab]),
+                self.collection[self.tab].as_ref(),
+            ),
+        };
+        let items = entries
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| {
+                        let heart = if entry.favorite { "♥" } else { "♡" };
+                        ListItem::new(format!("{} {}", heart, entry.item.describe()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let list = List::new(items)
+            .block(Block::default().title(list_title).borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+        let status = Paragraph::new(self.status.clone())
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(tabs, chunks[0]);
+        f.render_stateful_widget(list, chunks[1], &mut self.state);
+        f.render_widget(status, chunks[2]);
         Ok(())
     }
 
-    fn handle_input(&mut self, key: crossterm::event::KeyCode) -> Result<(), std::io::Error> {
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
         match key {
-            crossterm::event::KeyCode::Char('q') => {
+            KeyCode::Char('q') => {
                 std::process::exit(0);
             }
-            crossterm::event::KeyCode::Char('e') => {
+            KeyCode::Char('e') => {
                 self.session.borrow_mut().login_oauth();
             }
-            crossterm::event::KeyCode::Char('f') => {
-                let mut session = self.session.borrow_mut();
-                session.log = session.post_after_user();
+            KeyCode::Char('a') => self.session.borrow_mut().start_authorization(),
+            _ if !self.session.borrow().is_logged_in() => {}
+            KeyCode::Left => self.switch_tab(COLLECTION_TABS.len() - 1),
+            KeyCode::Right => self.switch_tab(1),
+            KeyCode::Up => self.select(-1),
+            KeyCode::Down => self.select(1),
+            KeyCode::Enter => self.open_selected(),
+            KeyCode::Esc => {
+                self.opened = None;
+                self.state.select(Some(0));
+            }
+            KeyCode::Char('f') => self.toggle_favorite(),
+            KeyCode::Char('r') => {
+                self.opened = None;
+                self.state.select(Some(0));
+                self.load_tab();
             }
             _ => {}
         }
@@ -190,10 +823,139 @@ impl Window for TidalWindow {
 }
 
 impl TidalWindow {
-    pub fn new(session: Rc<RefCell<TidalSession>>) -> Self {
+    pub fn new(
+        session: Rc<RefCell<TidalSession>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+    ) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
         Self {
             session,
+            audio_interface,
             title: "Tidal".to_string(),
+            tab: 0,
+            collection: [None, None, None],
+            opened: None,
+            state,
+            status: String::from("r to load, Enter to play or open, f to favorite or unfavorite"),
+        }
+    }
+
+    fn entries(&self) -> &[Entry] {
+        match &self.opened {
+            Some((_, entries)) => entries,
+            None => self.collection[self.tab].as_deref().unwrap_or_default(),
+        }
+    }
+
+    fn entries_mut(&mut self) -> Option<&mut Vec<Entry>> {
+        match &mut self.opened {
+            Some((_, entries)) => Some(entries),
+            None => self.collection[self.tab].as_mut(),
+        }
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.entries().len();
+        if len == 0 {
+            return;
+        }
+        let i = self.state.selected().unwrap_or(0) as isize + offset;
+        self.state.select(Some(i.rem_euclid(len as isize) as usize));
+    }
+
+    // each tab is fetched the first time it is shown
+    fn switch_tab(&mut self, offset: usize) {
+        self.tab = (self.tab + offset) % COLLECTION_TABS.len();
+        self.opened = None;
+        self.state.select(Some(0));
+        if self.collection[self.tab].is_none() {
+            self.load_tab();
+        }
+    }
+
+    fn load_tab(&mut self) {
+        let mut session = self.session.borrow_mut();
+        let result = match self.tab {
+            0 => session.favorite_tracks(),
+            1 => session.favorite_albums(),
+            _ => session.playlists(),
+        };
+        drop(session);
+        match result {
+            Ok(items) => {
+                self.status = format!("{} {}", items.len(), COLLECTION_TABS[self.tab]);
+                // the user's own playlists aren't favorites of theirs
+                let favorite = self.tab != 2;
+                self.collection[self.tab] = Some(
+                    items
+                        .into_iter()
+                        .map(|item| Entry { item, favorite })
+                        .collect(),
+                );
+            }
+            Err(e) => self.status = format!("Request failed: {}", e),
+        }
+    }
+
+    fn selected_item(&self) -> Option<TidalItem> {
+        self.entries()
+            .get(self.state.selected().unwrap_or(0))
+            .map(|entry| entry.item.clone())
+    }
+
+    // tracks are queued, albums and playlists list their tracks
+    fn open_selected(&mut self) {
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let session = self.session.borrow();
+        match &item {
+            TidalItem::Track(track) => match session.to_audio_file(track) {
+                Ok(file) => {
+                    self.status = format!("Queued {}", track.title);
+                    self.audio_interface
+                        .borrow_mut()
+                        .append_to_queue(&mut vec![file]);
+                }
+                Err(e) => self.status = format!("Request failed: {}", e),
+            },
+            TidalItem::Album(_) | TidalItem::Playlist(_) => match session.tracks_of(&item) {
+                Ok(tracks) => {
+                    let entries = tracks
+                        .into_iter()
+                        .map(|item| Entry {
+                            item,
+                            favorite: false,
+                        })
+                        .collect();
+                    self.opened = Some((item.describe(), entries));
+                    self.state.select(Some(0));
+                }
+                Err(e) => self.status = format!("Request failed: {}", e),
+            },
+        }
+    }
+
+    fn toggle_favorite(&mut self) {
+        let i = self.state.selected().unwrap_or(0);
+        let Some(entry) = self.entries().get(i) else {
+            return;
+        };
+        let (item, favorite) = (entry.item.clone(), !entry.favorite);
+        let result = self.session.borrow_mut().set_favorite(&item, favorite);
+        match result {
+            Ok(()) => {
+                if let Some(entry) = self.entries_mut().and_then(|entries| entries.get_mut(i)) {
+                    entry.favorite = favorite;
+                }
+                self.status = format!(
+                    "{} {}",
+                    if favorite { "Added" } else { "Removed" },
+                    item.describe()
+                );
+            }
+            Err(e) => self.status = format!("Request failed: {}", e),
         }
     }
 }
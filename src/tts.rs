@@ -0,0 +1,34 @@
+use crate::audio::AudioFile;
+use std::{
+    io,
+    process::Command,
+    sync::mpsc::{self, Receiver},
+};
+
+fn announcement_text(file: &AudioFile) -> String {
+    format!("Now playing: {} by {}", file.get_title(), file.get_artist())
+}
+
+// wav data for the text, from espeak-ng
+pub fn synthesize(text: &str) -> Result<Vec<u8>, io::Error> {
+    let output = Command::new("espeak-ng")
+        .arg("--stdout")
+        .arg(text)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+// espeak-ng takes a moment to start, so the speech is made on its own thread
+pub fn announce(file: &AudioFile) -> Receiver<Result<Vec<u8>, io::Error>> {
+    let text = announcement_text(file);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(synthesize(&text));
+    });
+    receiver
+}
@@ -1,69 +1,238 @@
+use chrono::Utc;
 use crossterm::{
-    event::{poll, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen},
+    event::{
+        poll, Event, KeyCode, KeyEvent, KeyboardEnhancementFlags, MediaKeyCode,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType,
+        EnterAlternateScreen, LeaveAlternateScreen,
+    },
 };
 use std::{
     cell::RefCell,
-    io::{self, Stdout},
+    io::{self, Stdout, Write},
     rc::Rc,
-    time::Duration,
+    sync::mpsc::{Receiver, TryRecvError},
+    time::{Duration, Instant},
 };
 use tui::Terminal;
 use tui::{
     backend::CrosstermBackend,
+    buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    text::Line,
-    widgets::{Block, Borders, Paragraph, Tabs},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Gauge, List, ListItem, Paragraph, Tabs, Widget},
     Frame,
 };
 
+#[cfg(unix)]
+use crate::ipc::{Command, IpcServer};
 use crate::settings::Settings;
 use crate::{
-    audio::{AudioFile, AudioInterface},
+    audio::{AudioFile, AudioInterface, RepeatMode},
+    bookmarks::BookmarkPrompt,
+    cache::LibraryCache,
+    library::{seconds_to_formatted_time, LibraryWindow},
+    listenbrainz::Scrobbler,
+    log,
+    log::{Level, LogWindow},
+    mpd_compat::MpdServer,
+    mpris::{MediaCommand, MprisServer},
+    notification::{notify_track, DesktopNotifier, Notifier},
+    server::ApiServer,
     tidal::TidalSession,
+    tts,
+    update_checker::{Release, UpdateChecker},
 };
 
 const TICK_RATE: Duration = Duration::from_millis(500);
+const VOLUME_STEP: f32 = 0.05;
+const FULLSCREEN_BADGE_DURATION: Duration = Duration::from_secs(1);
+// how often the library scan progress is redrawn during startup
+const SCAN_REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+// how long before the end of a track the next tidal stream url is fetched
+const TIDAL_PREFETCH_SECS: f64 = 10.0;
+// listenbrainz counts a listen after half the track or this long, whichever is first
+const LISTEN_THRESHOLD: Duration = Duration::from_secs(240);
 
+/// A tab, or a pane inside one, that draws itself and handles keys.
+///
+/// Only [`draw`](Window::draw) has to be implemented. The UI sends most
+/// keys to the window of the current tab after checking them against the
+/// global keybindings.
+///
+/// # Examples
+///
+/// ```
+/// use music_player::ui::Window;
+/// use std::io::{self, Stdout};
+/// use tui::{backend::CrosstermBackend, layout::Rect, widgets::Paragraph, Frame};
+///
+/// struct Hello;
+///
+/// impl Window for Hello {
+///     fn get_title(&self) -> String {
+///         String::from("Hello")
+///     }
+///
+///     fn draw(
+///         &mut self,
+///         area: Rect,
+///         f: &mut Frame<CrosstermBackend<Stdout>>,
+///     ) -> Result<(), io::Error> {
+///         f.render_widget(Paragraph::new("Hello"), area);
+///         Ok(())
+///     }
+/// }
+///
+/// assert_eq!(Hello.get_title(), "Hello");
+/// ```
 pub trait Window {
+    /// The title shown in the tab bar. Tabs are also found by their title,
+    /// like the startup tab.
     fn get_title(&self) -> String {
         String::from("Window")
     }
 
+    /// Draws the window into `area`.
+    ///
+    /// # Errors
+    ///
+    /// An error is logged along with the window's title. The rest of the UI
+    /// keeps drawing.
     fn draw(
         &mut self,
         area: Rect,
         f: &mut Frame<CrosstermBackend<Stdout>>,
     ) -> Result<(), io::Error>;
 
+    /// Handles a key the global keybindings didn't take.
+    ///
+    /// # Errors
+    ///
+    /// Errors end the UI's run loop and are returned from [`UI::run`].
     fn handle_input(&mut self, _key: KeyCode) -> Result<(), io::Error> {
         Ok(())
     }
+
+    /// Whether a text input in the window has focus. While it does, every key
+    /// goes to the window instead of the global keybindings.
+    fn is_typing(&self) -> bool {
+        false
+    }
+
+    /// Handles a key along with its modifiers. Most windows only need the key
+    /// code and implement [`handle_input`](Window::handle_input) instead,
+    /// which this calls by default.
+    ///
+    /// # Errors
+    ///
+    /// Errors end the UI's run loop, like `handle_input`'s.
+    fn handle_key(&mut self, key: KeyEvent) -> Result<(), io::Error> {
+        self.handle_input(key.code)
+    }
+
+    /// Called on every window right before the settings are saved on quit.
+    fn on_quit(&mut self) {}
+
+    /// Called when the window's tab is switched to, and on the tab shown at
+    /// startup. Use it for work that only needs doing while the window is
+    /// shown.
+    fn on_activate(&mut self) {}
+
+    /// Called when the window's tab is switched away from.
+    fn on_deactivate(&mut self) {}
+
+    /// Called on every window with the new track list when the library
+    /// changes after it starts. That is a rescan, a reload of the folders
+    /// or duplicates being removed. Windows that keep their own copy of the
+    /// tracks replace it here.
+    fn on_library_changed(&mut self, _music_list: &[AudioFile]) {}
+
+    /// How long the UI waits for a key before drawing again while this window
+    /// is shown.
+    fn tick_rate(&self) -> Duration {
+        TICK_RATE
+    }
+}
+
+// for windows that other windows need to reach, like the library
+impl<W: Window> Window for Rc<RefCell<W>> {
+    fn get_title(&self) -> String {
+        self.borrow().get_title()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        self.borrow_mut().draw(area, f)
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        self.borrow_mut().handle_input(key)
+    }
+
+    fn is_typing(&self) -> bool {
+        self.borrow().is_typing()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<(), io::Error> {
+        self.borrow_mut().handle_key(key)
+    }
+
+    fn on_quit(&mut self) {
+        self.borrow_mut().on_quit()
+    }
+
+    fn on_activate(&mut self) {
+        self.borrow_mut().on_activate()
+    }
+
+    fn on_deactivate(&mut self) {
+        self.borrow_mut().on_deactivate()
+    }
+
+    fn on_library_changed(&mut self, music_list: &[AudioFile]) {
+        self.borrow_mut().on_library_changed(music_list)
+    }
+
+    fn tick_rate(&self) -> Duration {
+        self.borrow().tick_rate()
+    }
 }
 
 pub struct UpNextWindow {
     title: String,
     audio_interface: Rc<RefCell<AudioInterface>>,
-    next_up: Option<AudioFile>,
+    settings: Rc<RefCell<Settings>>,
+    upcoming: Vec<AudioFile>,
 }
 
 impl UpNextWindow {
-    fn new(audio_interface: Rc<RefCell<AudioInterface>>) -> Self {
+    fn new(audio_interface: Rc<RefCell<AudioInterface>>, settings: Rc<RefCell<Settings>>) -> Self {
         Self {
             audio_interface,
+            settings,
             title: String::from("Up Next"),
-            next_up: None,
+            upcoming: Vec::new(),
         }
     }
 
     fn update_up_next(&mut self) {
-        if let Some(next) = self.audio_interface.borrow().get_next() {
-            self.next_up = Some(next.clone());
-        } else {
-            self.next_up = None;
-        }
+        let count = self.settings.borrow().get_up_next_count();
+        self.upcoming = self
+            .audio_interface
+            .borrow()
+            .get_upcoming(count)
+            .into_iter()
+            .cloned()
+            .collect();
     }
 }
 
@@ -78,16 +247,26 @@ impl Window for UpNextWindow {
         f: &mut Frame<CrosstermBackend<Stdout>>,
     ) -> Result<(), io::Error> {
         self.update_up_next();
-        let up_next = Paragraph::new(match &self.next_up {
-            Some(audio_file) => format!(
-                "{} by {}",
-                audio_file.get_title().clone(),
-                audio_file.get_artist().clone()
-            ),
-            None => String::from("Nothing"),
-        })
-        .block(Block::default().title("Next Up:").borders(Borders::ALL))
-        .style(Style::default().fg(Color::Green));
+        let mut items = self
+            .upcoming
+            .iter()
+            .enumerate()
+            .map(|(i, audio_file)| {
+                ListItem::new(format!(
+                    "{}. {} - {} ({})",
+                    i + 1,
+                    audio_file.get_title(),
+                    audio_file.get_artist(),
+                    audio_file.get_duration()
+                ))
+            })
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            items.push(ListItem::new("Nothing"));
+        }
+        let up_next = List::new(items)
+            .block(Block::default().title("Next Up:").borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green));
         f.render_widget(up_next, area);
         Ok(())
     }
@@ -97,6 +276,33 @@ impl Window for UpNextWindow {
     }
 }
 
+/// The terminal UI: the tab bar, the window of the current tab, up next
+/// and the now playing bar.
+///
+/// It also owns everything that has to be looked after between frames.
+/// That covers the IPC socket, the HTTP and MPD servers, MPRIS,
+/// scrobbling and track announcements.
+///
+/// # Examples
+///
+/// ```no_run
+/// use music_player::audio::{AudioInterface, Devices};
+/// use music_player::cache::LibraryCache;
+/// use music_player::settings::Settings;
+/// use music_player::tidal::TidalSession;
+/// use music_player::ui::UI;
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// let settings = Rc::new(RefCell::new(Settings::load().map_err(|e| e.to_string())?));
+/// let devices = Devices::new(settings.borrow().get_device());
+/// let (stream, sink) = Devices::open_stream(devices.get_device_by_index_or_default(0), None)?;
+/// let audio_interface = Rc::new(RefCell::new(AudioInterface::new(stream, sink, devices, None)));
+/// let tidal_session = Rc::new(RefCell::new(TidalSession::new()));
+/// let cache = Rc::new(RefCell::new(LibraryCache::load()));
+/// let mut ui = UI::new(settings, audio_interface, tidal_session, cache)?;
+/// ui.run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 pub struct UI {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     windows: Vec<Box<dyn Window>>,
@@ -104,31 +310,91 @@ pub struct UI {
     pub audio_interface: Rc<RefCell<AudioInterface>>,
     pub tidal_session: Rc<RefCell<TidalSession>>,
     pub settings: Rc<RefCell<Settings>>,
+    pub cache: Rc<RefCell<LibraryCache>>,
+    notifier: Box<dyn Notifier>,
+    // None when the socket couldn't be created, the tui works fine without it
+    #[cfg(unix)]
+    ipc: Option<IpcServer>,
+    api_server: Option<ApiServer>,
+    mpd_server: Option<MpdServer>,
+    // None without a session bus, media keys then only work through the terminal
+    mpris: Option<MprisServer>,
+    // whether the terminal was asked to report media keys, undone on drop
+    keyboard_enhanced: bool,
+    // hides the tab bar, up next and now playing bar
+    fullscreen: bool,
+    fullscreen_toggled_at: Option<Instant>,
+    // leaves the alternate screen and redraws a single status line in place
+    minimode: bool,
+    // naming a bookmark for the current position, opened with B from any window
+    bookmark_prompt: Option<BookmarkPrompt>,
+    // toggled with ! over whatever window is showing
+    log_window: Option<LogWindow>,
+    // None unless check_for_updates is set
+    update_checker: Option<UpdateChecker>,
+    // asked for the open album's track position for the now playing bar
+    library_window: Option<Rc<RefCell<LibraryWindow>>>,
+    // None unless listenbrainz_token is set
+    scrobbler: Option<Scrobbler>,
+    // the track playing now, when it started and the unix time it started at
+    listening: Option<(AudioFile, Instant, i64)>,
+    // speech for the track that just started, while espeak-ng makes it
+    announcement: Option<Receiver<Result<Vec<u8>, io::Error>>>,
 }
 
 impl UI {
+    fn switch_tab(&mut self, tab: usize) {
+        self.windows[self.current_tab].on_deactivate();
+        self.current_tab = tab;
+        self.windows[self.current_tab].on_activate();
+    }
+
     fn next_tab(&mut self) {
-        self.current_tab = (self.current_tab + 1) % self.windows.len();
+        self.switch_tab((self.current_tab + 1) % self.windows.len());
     }
 
     fn previous_tab(&mut self) {
         if self.current_tab > 0 {
-            self.current_tab -= 1;
+            self.switch_tab(self.current_tab - 1);
         } else {
-            self.current_tab = self.windows.len() - 1;
+            self.switch_tab(self.windows.len() - 1);
         }
     }
 
+    /// Takes over the terminal: raw mode, the alternate screen, and media keys
+    /// where the terminal supports them. Everything is undone when the UI is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the terminal can't be set up.
     pub fn new(
         settings: Rc<RefCell<Settings>>,
         audio_interface: Rc<RefCell<AudioInterface>>,
         tidal_session: Rc<RefCell<TidalSession>>,
+        cache: Rc<RefCell<LibraryCache>>,
     ) -> Result<Self, io::Error> {
         let stdout = io::stdout();
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
         enable_raw_mode()?;
         execute!(io::stdout(), EnterAlternateScreen)?;
+        // terminals only send media keys with the kitty keyboard protocol enabled
+        let keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false)
+            && execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )
+            .is_ok();
+        let update_checker = settings
+            .borrow()
+            .check_for_updates
+            .then(UpdateChecker::start);
+        let scrobbler = settings
+            .borrow()
+            .listenbrainz_token
+            .clone()
+            .map(Scrobbler::start);
         Ok(Self {
             terminal,
             windows: Vec::new(),
@@ -136,25 +402,265 @@ impl UI {
             tidal_session,
             audio_interface,
             settings,
+            cache,
+            notifier: Box::new(DesktopNotifier),
+            #[cfg(unix)]
+            ipc: IpcServer::start()
+                .map_err(|e| log!(Level::Warn, "ipc socket unavailable: {}", e))
+                .ok(),
+            api_server: None,
+            mpd_server: None,
+            mpris: MprisServer::start()
+                .map_err(|e| log!(Level::Warn, "mpris unavailable: {}", e))
+                .ok(),
+            keyboard_enhanced,
+            fullscreen: false,
+            fullscreen_toggled_at: None,
+            minimode: false,
+            bookmark_prompt: None,
+            log_window: None,
+            update_checker,
+            library_window: None,
+            scrobbler,
+            listening: None,
+            announcement: None,
         })
     }
 
+    /// Adds a tab after the existing ones.
     pub fn push_window(&mut self, window: Box<dyn Window>) {
         self.windows.push(window);
     }
 
+    /// The titles of the tabs added so far, in order.
+    pub fn tab_names(&self) -> Vec<String> {
+        self.windows.iter().map(|w| w.get_title()).collect()
+    }
+
+    // falls back to the first tab when the saved name matches none of them,
+    // say after a tab was renamed
+    fn select_startup_tab(&mut self) {
+        let startup_tab = self.settings.borrow().startup_tab.clone();
+        self.current_tab = match self
+            .windows
+            .iter()
+            .position(|w| w.get_title() == startup_tab)
+        {
+            Some(i) => i,
+            None => {
+                log!(
+                    Level::Warn,
+                    "no tab named {}, starting on the first tab",
+                    startup_tab
+                );
+                0
+            }
+        };
+        // nothing was active before, so there is nothing to deactivate
+        self.windows[self.current_tab].on_activate();
+    }
+
+    /// Serves the HTTP API's requests between frames.
+    pub fn set_api_server(&mut self, api_server: ApiServer) {
+        self.api_server = Some(api_server);
+    }
+
+    fn poll_announcement(&mut self) {
+        let Some(receiver) = &self.announcement else {
+            return;
+        };
+        let speech = match receiver.try_recv() {
+            Ok(speech) => speech,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.announcement = None;
+                return;
+            }
+        };
+        self.announcement = None;
+        let volume = self.settings.borrow().tts_volume;
+        let played = speech.and_then(|wav| {
+            self.audio_interface
+                .borrow_mut()
+                .play_announcement(wav, volume)
+        });
+        if let Err(e) = played {
+            log!(Level::Warn, "track announcement failed: {}", e);
+        }
+    }
+
+    // gets a fresh url for a tidal track coming up next, and swaps it into
+    // the queue once it is there so the track starts without a new request
+    fn prefetch_tidal_stream(&self) {
+        let mut audio_interface = self.audio_interface.borrow_mut();
+        let Some(next_id) = audio_interface.get_next().and_then(AudioFile::get_tidal_id) else {
+            return;
+        };
+        let mut tidal_session = self.tidal_session.borrow_mut();
+        if let Some(url) = tidal_session.stream_url_for(next_id) {
+            audio_interface.set_next_stream_url(url);
+            return;
+        }
+        let Some(playing) = audio_interface.get_currently_playing() else {
+            return;
+        };
+        let remaining = playing.get_playing_duration() - audio_interface.get_sink_length() as f64;
+        if remaining <= TIDAL_PREFETCH_SECS {
+            tidal_session.prefetch_stream_url(next_id);
+        }
+    }
+
+    // submits the track that just stopped if it played long enough. time
+    // spent paused counts too, the sink position is already the next track's
+    fn scrobble(&mut self, playing: Option<&AudioFile>) {
+        let Some(scrobbler) = &self.scrobbler else {
+            return;
+        };
+        if let Some((file, started, listened_at)) = self.listening.take() {
+            let threshold = LISTEN_THRESHOLD.min(Duration::from_secs_f64(
+                file.get_raw_duration().max(0.0) / 2.0,
+            ));
+            if started.elapsed() >= threshold {
+                scrobbler.scrobble(&file, listened_at);
+            }
+        }
+        self.listening = playing.map(|file| (file.clone(), Instant::now(), Utc::now().timestamp()));
+    }
+
+    /// The library window the now playing bar asks for the open album.
+    pub fn set_library_window(&mut self, library_window: Rc<RefCell<LibraryWindow>>) {
+        self.library_window = Some(library_window);
+    }
+
+    /// Serves MPD clients between frames.
+    pub fn set_mpd_server(&mut self, mpd_server: MpdServer) {
+        self.mpd_server = Some(mpd_server);
+    }
+
+    /// Shows the library window until its scan is done. The other windows are
+    /// built from the library, so startup waits here. Returns false when the
+    /// user quits with `q` or Esc before the scan finishes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the terminal can't be drawn to or read from.
+    pub fn wait_for_scan(&mut self, library_window: &mut LibraryWindow) -> Result<bool, io::Error> {
+        self.terminal.clear()?;
+        while library_window.is_scanning() {
+            self.terminal.draw(|f| {
+                if let Err(e) = library_window.draw(f.size(), f) {
+                    log!(Level::Error, "Error drawing library scan: {}", e);
+                }
+            })?;
+            if poll(SCAN_REDRAW_INTERVAL)? {
+                if let Event::Key(key) = crossterm::event::read()? {
+                    if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Draws and handles keys until the user quits, then saves the settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics when no window has been added.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the terminal can't be drawn to or read from, or when a
+    /// window fails to handle a key.
     pub fn run(&mut self) -> Result<(), io::Error> {
-        let mut up_next = UpNextWindow::new(self.audio_interface.clone());
+        let mut up_next = UpNextWindow::new(self.audio_interface.clone(), self.settings.clone());
+        self.select_startup_tab();
         self.terminal.clear()?;
+        let mut last_playing: Option<AudioFile> = None;
+        let mut last_save = Instant::now();
         loop {
             self.draw(&mut up_next)?;
+            #[cfg(unix)]
+            self.handle_ipc();
+            self.handle_mpris();
+            self.handle_servers();
+            self.poll_library();
+            self.prefetch_tidal_stream();
+            self.poll_announcement();
             self.audio_interface.borrow_mut().handle_queue();
-            if poll(TICK_RATE)? {
+            self.auto_save(&mut last_save);
+            let playing = self
+                .audio_interface
+                .borrow()
+                .get_currently_playing()
+                .clone();
+            if playing != last_playing {
+                self.scrobble(playing.as_ref());
+                if let Some(file) = &playing {
+                    let mut cache = self.cache.borrow_mut();
+                    cache.update(file, |data| data.play_count += 1);
+                    if let Err(e) = cache.save() {
+                        log!(Level::Warn, "saving the library cache failed: {}", e);
+                    }
+                    // a terminal without a notification daemon just doesn't get them
+                    if self.settings.borrow().notifications_enabled {
+                        let _ = notify_track(self.notifier.as_ref(), file);
+                    }
+                    if self.settings.borrow().tts_enabled {
+                        self.announcement = Some(tts::announce(file));
+                    }
+                }
+                last_playing = playing;
+            }
+            if poll(self.windows[self.current_tab].tick_rate())? {
                 if let Event::Key(key) = crossterm::event::read()? {
+                    // media keys work whatever has focus
+                    if let KeyCode::Media(media_key) = key.code {
+                        if let Some(command) = media_command(media_key) {
+                            self.run_media_command(command);
+                        }
+                        continue;
+                    }
+                    if self.minimode {
+                        // there is no window on screen, so only playback keys apply
+                        match key.code {
+                            KeyCode::Char('q') => {
+                                self.quit();
+                                break;
+                            }
+                            KeyCode::Char('c') => self.audio_interface.borrow_mut().toggle_pause(),
+                            KeyCode::Char('n') => self.audio_interface.borrow_mut().skip_to_next(),
+                            KeyCode::Char('N') => {
+                                self.audio_interface.borrow_mut().skip_to_previous()
+                            }
+                            KeyCode::Char('+') => self.change_volume(VOLUME_STEP),
+                            KeyCode::Char('-') => self.change_volume(-VOLUME_STEP),
+                            KeyCode::Char('M') => self.toggle_minimode()?,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if let Some(prompt) = &mut self.bookmark_prompt {
+                        if prompt.handle_input(key.code, &mut self.cache.borrow_mut()) {
+                            self.bookmark_prompt = None;
+                        }
+                        continue;
+                    }
+                    if let Some(log_window) = &mut self.log_window {
+                        match key.code {
+                            KeyCode::Char('!') | KeyCode::Esc => self.log_window = None,
+                            _ => log_window.handle_input(key.code)?,
+                        }
+                        continue;
+                    }
+                    if self.windows[self.current_tab].is_typing() {
+                        self.windows[self.current_tab].handle_key(key)?;
+                        continue;
+                    }
                     match key.code {
                         KeyCode::Char('q') => {
-                            self.settings.borrow().save();
-                            self.tidal_session.borrow().save();
+                            self.quit();
                             break;
                         }
                         KeyCode::Char('h') => {
@@ -166,23 +672,376 @@ impl UI {
                         KeyCode::Char('c') => {
                             self.audio_interface.borrow_mut().toggle_pause();
                         }
+                        KeyCode::Char('n') => self.audio_interface.borrow_mut().skip_to_next(),
+                        KeyCode::Char('N') => self.audio_interface.borrow_mut().skip_to_previous(),
+                        KeyCode::Char('+') => self.change_volume(VOLUME_STEP),
+                        KeyCode::Char('-') => self.change_volume(-VOLUME_STEP),
+                        KeyCode::Char('s') => {
+                            self.audio_interface.borrow_mut().toggle_shuffle();
+                        }
+                        KeyCode::Char('S') => {
+                            self.audio_interface.borrow_mut().toggle_smart_shuffle();
+                        }
+                        KeyCode::Char('R') => {
+                            self.audio_interface.borrow_mut().cycle_repeat();
+                        }
+                        KeyCode::Char('z') => {
+                            self.fullscreen = !self.fullscreen;
+                            self.fullscreen_toggled_at = Some(Instant::now());
+                        }
+                        KeyCode::Char('M') => self.toggle_minimode()?,
+                        KeyCode::Char('B') => self.open_bookmark_prompt(),
+                        KeyCode::Char('!') => self.log_window = Some(LogWindow::new()),
+                        // only while the banner is up, the library uses U otherwise
+                        KeyCode::Char('U') if self.newer_release().is_some() => {
+                            self.open_release_page()
+                        }
                         _ => {
-                            self.windows[self.current_tab].handle_input(key.code)?;
+                            self.windows[self.current_tab].handle_key(key)?;
                         }
                     }
-                } else {
                 }
             }
         }
         Ok(())
     }
 
+    // so changes survive a crash or a kill, failures only go to the log
+    // tracks are looked up in the library as it is now, it changes with rescans
+    fn handle_servers(&self) {
+        let library_window = self
+            .library_window
+            .as_ref()
+            .map(|library_window| library_window.borrow());
+        let library = library_window
+            .as_ref()
+            .map(|library_window| library_window.get_music_list().as_slice())
+            .unwrap_or_default();
+        if let Some(api_server) = &self.api_server {
+            api_server.handle(&mut self.audio_interface.borrow_mut(), library);
+        }
+        if let Some(mpd_server) = &self.mpd_server {
+            let mut audio_interface = self.audio_interface.borrow_mut();
+            mpd_server.handle(&mut audio_interface, library);
+            // setvol is saved like a volume change from the keyboard
+            self.settings.borrow_mut().volume = audio_interface.get_volume();
+        }
+    }
+
+    // scans finish while any tab is shown. whatever was built from the old
+    // track list is given the new one
+    fn poll_library(&mut self) {
+        let Some(library_window) = &self.library_window else {
+            return;
+        };
+        let music_list = {
+            let mut library_window = library_window.borrow_mut();
+            library_window.poll_scans();
+            if !library_window.take_library_changed() {
+                return;
+            }
+            library_window.get_music_list().clone()
+        };
+        for window in &mut self.windows {
+            window.on_library_changed(&music_list);
+        }
+        self.audio_interface.borrow_mut().set_library(music_list);
+    }
+
+    fn auto_save(&self, last_save: &mut Instant) {
+        let interval = self.settings.borrow().auto_save_interval_secs;
+        if interval == 0 || last_save.elapsed() < Duration::from_secs(interval) {
+            return;
+        }
+        *last_save = Instant::now();
+        if let Err(e) = self.settings.borrow().save() {
+            log!(Level::Warn, "auto-saving settings failed: {}", e);
+        }
+    }
+
+    fn quit(&mut self) {
+        for window in self.windows.iter_mut() {
+            window.on_quit();
+        }
+        if let Err(e) = self.settings.borrow().save() {
+            log!(Level::Error, "saving settings failed: {}", e);
+        }
+        self.tidal_session.borrow().save();
+    }
+
+    fn newer_release(&self) -> Option<Release> {
+        self.update_checker
+            .as_ref()
+            .and_then(UpdateChecker::newer_release)
+    }
+
+    fn open_release_page(&self) {
+        if let Some(release) = self.newer_release() {
+            if let Err(e) = open::that(&release.html_url) {
+                log!(Level::Warn, "could not open {}: {}", release.html_url, e);
+            }
+        }
+    }
+
+    fn open_bookmark_prompt(&mut self) {
+        let audio_interface = self.audio_interface.borrow();
+        if let Some(file) = audio_interface.get_currently_playing() {
+            let position = audio_interface.get_sink_length() as f64;
+            self.bookmark_prompt = Some(BookmarkPrompt::new(file.clone(), position));
+        }
+    }
+
+    fn toggle_minimode(&mut self) -> Result<(), io::Error> {
+        self.minimode = !self.minimode;
+        if self.minimode {
+            execute!(io::stdout(), LeaveAlternateScreen)?;
+        } else {
+            execute!(io::stdout(), EnterAlternateScreen)?;
+            // the buffers still hold the last full frame, force a complete redraw
+            self.terminal.clear()?;
+        }
+        Ok(())
+    }
+
+    fn handle_mpris(&mut self) {
+        let Some(mpris) = &self.mpris else {
+            return;
+        };
+        for command in mpris.pending() {
+            self.run_media_command(command);
+        }
+        let audio_interface = self.audio_interface.borrow();
+        mpris.set_status(match audio_interface.get_currently_playing() {
+            None => "Stopped",
+            Some(_) if audio_interface.get_paused() => "Paused",
+            Some(_) => "Playing",
+        });
+    }
+
+    fn run_media_command(&self, command: MediaCommand) {
+        let mut audio_interface = self.audio_interface.borrow_mut();
+        let paused = audio_interface.get_paused();
+        match command {
+            MediaCommand::Play if paused => audio_interface.toggle_pause(),
+            MediaCommand::Pause if !paused => audio_interface.toggle_pause(),
+            MediaCommand::PlayPause => audio_interface.toggle_pause(),
+            // there's no stopped state, wait at the start of the track instead
+            MediaCommand::Stop => {
+                let _ = audio_interface.seek(0.0);
+                if !paused {
+                    audio_interface.toggle_pause();
+                }
+            }
+            MediaCommand::Next => audio_interface.skip_to_next(),
+            MediaCommand::Previous => audio_interface.skip_to_previous(),
+            MediaCommand::Play | MediaCommand::Pause => {}
+        }
+    }
+
+    #[cfg(unix)]
+    fn handle_ipc(&mut self) {
+        let Some(ipc) = &self.ipc else {
+            return;
+        };
+        for request in ipc.pending() {
+            let mut audio_interface = self.audio_interface.borrow_mut();
+            let response = match request.command {
+                Command::Play => {
+                    if audio_interface.get_paused() {
+                        audio_interface.toggle_pause();
+                    }
+                    String::from("OK")
+                }
+                Command::Pause => {
+                    if !audio_interface.get_paused() {
+                        audio_interface.toggle_pause();
+                    }
+                    String::from("OK")
+                }
+                Command::Next => {
+                    audio_interface.skip_to_next();
+                    String::from("OK")
+                }
+                Command::Prev => {
+                    audio_interface.skip_to_previous();
+                    String::from("OK")
+                }
+                Command::Volume(volume) => {
+                    audio_interface.set_volume(volume);
+                    self.settings.borrow_mut().volume = audio_interface.get_volume();
+                    String::from("OK")
+                }
+                Command::Status => crate::server::status(&audio_interface).to_string(),
+            };
+            request.reply(response);
+        }
+    }
+
+    fn change_volume(&mut self, step: f32) {
+        let mut audio_interface = self.audio_interface.borrow_mut();
+        let volume = audio_interface.get_volume() + step;
+        audio_interface.set_volume(volume);
+        self.settings.borrow_mut().volume = audio_interface.get_volume();
+    }
+
+    fn now_playing_bar(&self) -> Gauge<'static> {
+        let audio_interface = self.audio_interface.borrow();
+        let status = format!(
+            "Vol {:.0}%  Shuffle: {}  Repeat: {}",
+            audio_interface.get_volume() * 100.0,
+            if audio_interface.get_smart_shuffle() {
+                "Smart"
+            } else if audio_interface.get_shuffle() {
+                "On"
+            } else {
+                "Off"
+            },
+            audio_interface.get_repeat().name()
+        );
+        let status = match self
+            .library_window
+            .as_ref()
+            .and_then(|library_window| library_window.borrow().album_context_label())
+        {
+            Some(album) => format!("{}  {}", album, status),
+            None => status,
+        };
+        let (label, ratio) = match audio_interface.get_currently_playing() {
+            Some(audiofile) => {
+                let (left, right) = match audio_interface.get_paused() {
+                    true => ("⋫", "⋪"),
+                    false => ("►", "◄"),
+                };
+                let label = format!(
+                    "{} {} - {} - {} / {} {}  {}",
+                    left,
+                    audiofile.get_artist(),
+                    audiofile.get_title(),
+                    seconds_to_formatted_time(audio_interface.get_sink_length()),
+                    seconds_to_formatted_time(audiofile.get_playing_duration() as usize),
+                    right,
+                    status
+                );
+                (label, audio_interface.get_position_ratio())
+            }
+            None => (format!("Nothing Playing  {}", status), 0.0),
+        };
+        Gauge::default()
+            .block(Block::default().title("Now Playing").borders(Borders::ALL))
+            .style(Style::default().fg(Color::Green).bg(Color::Black))
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+            .label(label)
+            .ratio(ratio)
+    }
+
+    fn minimode_line(&self) -> String {
+        let audio_interface = self.audio_interface.borrow();
+        let flags = format!(
+            "{}{}",
+            if audio_interface.get_shuffle() {
+                "[S]"
+            } else {
+                ""
+            },
+            match audio_interface.get_repeat() {
+                RepeatMode::None => "",
+                _ => "[R]",
+            }
+        );
+        let volume = format!("VOL:{:.0}%", audio_interface.get_volume() * 100.0);
+        match audio_interface.get_currently_playing() {
+            Some(audiofile) => format!(
+                "{} {} - {} [{}/{}] {} {}",
+                if audio_interface.get_paused() {
+                    "⏸"
+                } else {
+                    "▶"
+                },
+                audiofile.get_artist(),
+                audiofile.get_title(),
+                seconds_to_formatted_time(audio_interface.get_sink_length()),
+                audiofile.get_duration(),
+                volume,
+                flags
+            ),
+            None => format!("■ Nothing Playing {} {}", volume, flags),
+        }
+    }
+
+    fn draw_minimode(&mut self) -> Result<(), io::Error> {
+        let width = self.terminal.size()?.width as usize;
+        // wrapping onto a second line would break the in place redraw
+        let line: String = self
+            .minimode_line()
+            .trim_end()
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect();
+        let mut stdout = io::stdout();
+        queue!(
+            stdout,
+            Print('\r'),
+            Print(line),
+            Clear(ClearType::UntilNewLine)
+        )?;
+        stdout.flush()
+    }
+
+    fn draw_fullscreen(&mut self) -> Result<(), io::Error> {
+        // a badge in the corner for a second after switching, so the change is noticed
+        let show_badge = self
+            .fullscreen_toggled_at
+            .map(|toggled_at| toggled_at.elapsed() < FULLSCREEN_BADGE_DURATION)
+            .unwrap_or(false);
+        self.terminal.draw(|f| {
+            let area = f.size();
+            if let Err(e) = self.windows[self.current_tab].draw(area, f) {
+                log!(
+                    Level::Error,
+                    "Error drawing window ({}): {}",
+                    self.windows[self.current_tab].get_title(),
+                    e
+                )
+            };
+            if show_badge && area.width >= 3 {
+                let badge = Paragraph::new("[F]").style(Style::default().fg(Color::Yellow));
+                f.render_widget(badge, Rect::new(area.x + area.width - 3, area.y, 3, 1));
+            }
+        })?;
+        Ok(())
+    }
+
     fn draw(&mut self, up_next: &mut UpNextWindow) -> Result<(), io::Error> {
+        if self.minimode {
+            return self.draw_minimode();
+        }
+        if self.fullscreen {
+            return self.draw_fullscreen();
+        }
+        let now_playing = self.now_playing_bar();
+        let release = self.newer_release();
+        let mut area = self.terminal.size()?;
+        // the banner takes the top line, above the margin
+        let banner_area = release.as_ref().map(|_| {
+            let banner_area = Rect { height: 1, ..area };
+            area.y += 1;
+            area.height = area.height.saturating_sub(1);
+            banner_area
+        });
+        // tall enough for every upcoming track plus the border
+        let up_next_height = self.settings.borrow().get_up_next_count() as u16 + 2;
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
-            .split(self.terminal.size()?);
+            .constraints(
+                [
+                    Constraint::Length(up_next_height),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(area);
         let top_chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(75), Constraint::Percentage(25)].as_ref())
@@ -202,29 +1061,66 @@ impl UI {
             .constraints([Constraint::Percentage(100)].as_ref())
             .split(chunks[1]);
         self.terminal.draw(|f| {
+            if let (Some(release), Some(banner_area)) = (&release, banner_area) {
+                let banner = Paragraph::new(format!(
+                    "⬆ rmus {} is available! Press U to open release page",
+                    release.tag_name
+                ))
+                .style(Style::default().fg(Color::Black).bg(Color::Yellow));
+                f.render_widget(banner, banner_area);
+            }
             f.render_widget(window_tabs, top_chunks[0]);
+            f.render_widget(now_playing, chunks[2]);
             if let Err(e) = up_next.draw(top_chunks[1], f) {
-                println!("Error drawing up next: {}", e);
+                log!(Level::Error, "Error drawing up next: {}", e);
             };
             if let Err(e) = self.windows[self.current_tab].draw(remaining_space[0], f) {
-                eprintln!(
+                log!(
+                    Level::Error,
                     "Error drawing window ({}): {}",
                     self.windows[self.current_tab].get_title(),
                     e
                 )
             };
+            if let Some(prompt) = &self.bookmark_prompt {
+                prompt.draw(remaining_space[0], f);
+            }
+            if let Some(log_window) = &mut self.log_window {
+                if let Err(e) = log_window.draw(remaining_space[0], f) {
+                    log!(Level::Error, "Error drawing log: {}", e);
+                }
+            }
         })?;
         Ok(())
     }
 }
 
+fn media_command(key: MediaKeyCode) -> Option<MediaCommand> {
+    match key {
+        MediaKeyCode::Play => Some(MediaCommand::Play),
+        MediaKeyCode::Pause => Some(MediaCommand::Pause),
+        MediaKeyCode::PlayPause => Some(MediaCommand::PlayPause),
+        MediaKeyCode::Stop => Some(MediaCommand::Stop),
+        MediaKeyCode::TrackNext => Some(MediaCommand::Next),
+        MediaKeyCode::TrackPrevious => Some(MediaCommand::Previous),
+        _ => None,
+    }
+}
+
 impl Drop for UI {
     fn drop(&mut self) {
+        if self.minimode {
+            // leave the status line behind instead of printing over it
+            println!("\r");
+        }
         println!("Dropping UI");
+        if self.keyboard_enhanced {
+            let _ = execute!(self.terminal.backend_mut(), PopKeyboardEnhancementFlags);
+        }
         disable_raw_mode().unwrap();
         execute!(
             self.terminal.backend_mut(),
-            crossterm::terminal::LeaveAlternateScreen,
+            LeaveAlternateScreen,
             crossterm::event::DisableMouseCapture
         )
         .unwrap();
@@ -232,6 +1128,59 @@ impl Drop for UI {
     }
 }
 
+// the border of whichever panel in a window keys go to is yellow, the rest green
+pub fn focus_border_style(focused: bool) -> Style {
+    Style::default().fg(if focused { Color::Yellow } else { Color::Green })
+}
+
+// recolours the border a panel drew itself, for panels whose block the
+// window doesn't build, keeping their title
+pub struct FocusBorder;
+
+impl Widget for FocusBorder {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+        let style = focus_border_style(true);
+        for x in area.left()..area.right() {
+            buf.get_mut(x, area.top()).set_style(style);
+            buf.get_mut(x, area.bottom() - 1).set_style(style);
+        }
+        for y in area.top()..area.bottom() {
+            buf.get_mut(area.left(), y).set_style(style);
+            buf.get_mut(area.right() - 1, y).set_style(style);
+        }
+    }
+}
+
+// draws the frame every popup shares over area: a shadow one cell down and
+// to the right, then a cleared box with a double border. content_fn draws the
+// popup's contents inside the border and its result is passed back
+pub fn render_popup<T>(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    title: &str,
+    content_fn: impl FnOnce(Rect, &mut Frame<CrosstermBackend<Stdout>>) -> T,
+) -> T {
+    let shadow = Rect::new(area.x + 1, area.y + 1, area.width, area.height).intersection(f.size());
+    f.render_widget(
+        Block::default().style(Style::default().bg(Color::DarkGray)),
+        shadow,
+    );
+    f.render_widget(tui::widgets::Clear, area);
+    let block = Block::default()
+        .title(Span::styled(
+            title.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    content_fn(inner, f)
+}
+
 pub fn centered_rect(x: u16, y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
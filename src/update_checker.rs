@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/AbhayFernandes/rmus/releases/latest";
+
+#[derive(Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+// looks for a newer release in the background, so a slow or missing
+// network doesn't hold up startup
+pub struct UpdateChecker {
+    available: Arc<AtomicBool>,
+    release: Arc<Mutex<Option<Release>>>,
+}
+
+impl UpdateChecker {
+    pub fn start() -> Self {
+        let available = Arc::new(AtomicBool::new(false));
+        let release = Arc::new(Mutex::new(None));
+        let (thread_available, thread_release) = (available.clone(), release.clone());
+        std::thread::spawn(move || {
+            // no network or a rate limited api just means no banner
+            let Ok(latest) = fetch_latest() else {
+                return;
+            };
+            if is_newer(&latest.tag_name, env!("CARGO_PKG_VERSION")) {
+                *thread_release.lock().unwrap() = Some(latest);
+                thread_available.store(true, Ordering::Relaxed);
+            }
+        });
+        Self { available, release }
+    }
+
+    // set once a release newer than this build has been found
+    pub fn newer_release(&self) -> Option<Release> {
+        if !self.available.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.release.lock().unwrap().clone()
+    }
+}
+
+fn fetch_latest() -> Result<Release, reqwest::Error> {
+    // github rejects api requests without a user agent
+    reqwest::blocking::Client::new()
+        .get(LATEST_RELEASE_URL)
+        .header("User-Agent", concat!("rmus/", env!("CARGO_PKG_VERSION")))
+        .send()?
+        .error_for_status()?
+        .json()
+}
+
+// compares dotted version numbers, ignoring a leading v and anything after a -
+fn is_newer(tag: &str, current: &str) -> bool {
+    let parse = |version: &str| {
+        version
+            .trim_start_matches('v')
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    };
+    parse(tag) > parse(current)
+}
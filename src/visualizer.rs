@@ -0,0 +1,285 @@
+use crate::{audio::AudioInterface, settings::Settings, ui::Window};
+use crossterm::event::KeyCode;
+use rustfft::{num_complex::Complex, FftPlanner};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    io::{self, Stdout},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+// number of samples fed to the fft, taken from the end of the sample buffer
+const FFT_SIZE: usize = 1024;
+// magnitudes below this many decibels are drawn as empty columns
+const SPECTRUM_FLOOR_DB: f64 = -60.0;
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum VisualizerMode {
+    Waveform,
+    #[default]
+    Bars,
+    Spectrum,
+}
+
+impl VisualizerMode {
+    fn next(self) -> Self {
+        match self {
+            VisualizerMode::Waveform => VisualizerMode::Bars,
+            VisualizerMode::Bars => VisualizerMode::Spectrum,
+            VisualizerMode::Spectrum => VisualizerMode::Waveform,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            VisualizerMode::Waveform => "Waveform",
+            VisualizerMode::Bars => "Bars",
+            VisualizerMode::Spectrum => "Spectrum",
+        }
+    }
+}
+
+// the highest level a column reached recently, held like a vu meter's peak light
+#[derive(Clone, Copy)]
+struct Peak {
+    level: f64,
+    reached_at: Instant,
+}
+
+pub struct AudioVisualizerWindow {
+    title: String,
+    settings: Rc<RefCell<Settings>>,
+    audio_interface: Rc<RefCell<AudioInterface>>,
+    fft_planner: FftPlanner<f64>,
+    // one per column of the bars or spectrum, emptied when the width or mode changes
+    peaks: Vec<Peak>,
+    last_draw: Instant,
+}
+
+impl AudioVisualizerWindow {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        audio_interface: Rc<RefCell<AudioInterface>>,
+    ) -> Self {
+        Self {
+            title: String::from("Visualizer"),
+            settings,
+            audio_interface,
+            fft_planner: FftPlanner::new(),
+            peaks: Vec::new(),
+            last_draw: Instant::now(),
+        }
+    }
+
+    // raises each peak to its column's level, or lets it fall once it has
+    // been held for peak_hold_secs
+    fn update_peaks(&mut self, levels: &[f64]) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_draw).as_secs_f64();
+        self.last_draw = now;
+        let settings = self.settings.borrow();
+        let hold = Duration::from_secs_f32(settings.peak_hold_secs.max(0.0));
+        let fall = settings.peak_fall_rate.max(0.0) as f64 * elapsed;
+        if self.peaks.len() != levels.len() {
+            self.peaks = levels
+                .iter()
+                .map(|&level| Peak {
+                    level,
+                    reached_at: now,
+                })
+                .collect();
+            return;
+        }
+        for (peak, &level) in self.peaks.iter_mut().zip(levels) {
+            if level >= peak.level {
+                *peak = Peak {
+                    level,
+                    reached_at: now,
+                };
+            } else if now.duration_since(peak.reached_at) >= hold {
+                peak.level = (peak.level - fall).max(level);
+            }
+        }
+    }
+
+    // splits the samples into one chunk per terminal column
+    fn columns(samples: &[f32], width: usize) -> Vec<&[f32]> {
+        if samples.is_empty() || width == 0 {
+            return Vec::new();
+        }
+        let chunk_size = (samples.len() / width).max(1);
+        samples.chunks(chunk_size).take(width).collect()
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        let sum = samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>();
+        (sum / samples.len() as f64).sqrt().min(1.0)
+    }
+
+    fn peak(samples: &[f32]) -> f64 {
+        samples
+            .iter()
+            .map(|s| (*s as f64).abs())
+            .fold(0.0, f64::max)
+            .min(1.0)
+    }
+
+    // magnitude of each column's share of the frequency bins, scaled to 0..1.
+    // bins are spread logarithmically so the low end isn't squashed into a few columns
+    fn spectrum(&mut self, samples: &[f32], width: usize) -> Vec<f64> {
+        if samples.len() < FFT_SIZE || width == 0 {
+            return Vec::new();
+        }
+        let mut buffer = samples[samples.len() - FFT_SIZE..]
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                // hann window to keep the edges of the slice from smearing the spectrum
+                let window =
+                    0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / FFT_SIZE as f64).cos();
+                Complex::new(*s as f64 * window, 0.0)
+            })
+            .collect::<Vec<_>>();
+        self.fft_planner
+            .plan_fft_forward(FFT_SIZE)
+            .process(&mut buffer);
+        let bins = FFT_SIZE / 2;
+        (0..width)
+            .map(|column| {
+                let start = (bins as f64).powf(column as f64 / width as f64) as usize;
+                let end = ((bins as f64).powf((column + 1) as f64 / width as f64) as usize)
+                    .clamp(start + 1, bins);
+                let magnitude = buffer[start.min(bins - 1)..end]
+                    .iter()
+                    .map(|c| c.norm())
+                    .fold(0.0, f64::max)
+                    / FFT_SIZE as f64;
+                let db = 20.0 * magnitude.max(f64::EPSILON).log10();
+                ((db - SPECTRUM_FLOOR_DB) / -SPECTRUM_FLOOR_DB).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+// draws one column per value using half block characters, so every cell
+// holds two steps. each column covers the half cells in [start, end),
+// counted from the top of the area. peaks are the half cell each column's
+// peak reaches, marked in the cell above it
+fn render_columns(spans: &[(usize, usize)], peaks: &[usize], height: usize) -> Vec<Line<'static>> {
+    (0..height)
+        .map(|row| {
+            let top = row * 2;
+            let bottom = top + 1;
+            spans
+                .iter()
+                .enumerate()
+                .map(|(column, (start, end))| {
+                    let filled = |half: usize| half >= *start && half < *end;
+                    let peak = peaks.get(column).is_some_and(|&peak| {
+                        peak < height * 2 && (peak / 2).checked_sub(1) == Some(row)
+                    });
+                    match (filled(top), filled(bottom)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) if peak => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect::<String>()
+                .into()
+        })
+        .collect()
+}
+
+impl Window for AudioVisualizerWindow {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn draw(
+        &mut self,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) -> Result<(), io::Error> {
+        let mode = self.settings.borrow().visualizer_mode;
+        let block = Block::default()
+            .title(format!("Visualizer - {}", mode.name()))
+            .borders(Borders::ALL);
+        let inner = block.inner(area);
+        let width = inner.width as usize;
+        let height = inner.height as usize;
+        let half_cells = height * 2;
+        let samples = match self.audio_interface.borrow().get_currently_playing() {
+            Some(_) => self.audio_interface.borrow().get_samples(),
+            None => Vec::new(),
+        };
+        // bars and spectrum grow up from the bottom, as levels from 0 to 1
+        let levels = match mode {
+            VisualizerMode::Waveform => Vec::new(),
+            VisualizerMode::Bars => AudioVisualizerWindow::columns(&samples, width)
+                .into_iter()
+                .map(AudioVisualizerWindow::rms)
+                .collect::<Vec<_>>(),
+            VisualizerMode::Spectrum => self.spectrum(&samples, width),
+        };
+        self.update_peaks(&levels);
+        let from_bottom = |level: f64| half_cells - (level * half_cells as f64) as usize;
+        let spans = match mode {
+            VisualizerMode::Waveform => AudioVisualizerWindow::columns(&samples, width)
+                .into_iter()
+                .map(|column| {
+                    // mirrored around the middle of the area
+                    let extent = (AudioVisualizerWindow::peak(column) * height as f64) as usize;
+                    (height - extent, height + extent)
+                })
+                .collect::<Vec<_>>(),
+            VisualizerMode::Bars | VisualizerMode::Spectrum => levels
+                .iter()
+                .map(|&level| (from_bottom(level), half_cells))
+                .collect::<Vec<_>>(),
+        };
+        let peaks = self
+            .peaks
+            .iter()
+            .map(|peak| from_bottom(peak.level))
+            .collect::<Vec<_>>();
+        let visualizer = Paragraph::new(render_columns(&spans, &peaks, height))
+            .block(block)
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(visualizer, area);
+        Ok(())
+    }
+
+    fn tick_rate(&self) -> Duration {
+        Duration::from_millis(50)
+    }
+
+    fn on_activate(&mut self) {
+        self.audio_interface.borrow_mut().set_loopback_active(true);
+    }
+
+    fn on_deactivate(&mut self) {
+        self.audio_interface.borrow_mut().set_loopback_active(false);
+        // levels from before the tab was left would hang on when it comes back
+        self.peaks.clear();
+    }
+
+    fn handle_input(&mut self, key: KeyCode) -> Result<(), io::Error> {
+        if let KeyCode::Char('m') = key {
+            let mut settings = self.settings.borrow_mut();
+            settings.visualizer_mode = settings.visualizer_mode.next();
+            self.peaks.clear();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use music_player::audio::{AudioFile, AudioInterface, Devices, OutputStream, RepeatMode};
+use std::{
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+use tempfile::TempDir;
+
+// a quarter second tone, short enough to play through in a test
+const TONE: &[u8] = include_bytes!("fixtures/tone.wav");
+
+// plays into a headless stream, so no output device is needed
+fn audio_interface() -> AudioInterface {
+    let (stream, sink) = OutputStream::headless(2, 44_100);
+    AudioInterface::new(stream, sink, Devices::new(0), None)
+}
+
+// the fixture written out under its own name, so tracks can be told apart by path
+fn track(dir: &TempDir, name: &str) -> AudioFile {
+    let path = dir.path().join(format!("{}.wav", name));
+    std::fs::write(&path, TONE).unwrap();
+    AudioFile::new(&path.to_string_lossy().to_string()).unwrap()
+}
+
+fn playing_path(audio_interface: &AudioInterface) -> Option<PathBuf> {
+    audio_interface
+        .get_currently_playing()
+        .as_ref()
+        .map(|file| file.get_path().to_path_buf())
+}
+
+fn queue_paths(audio_interface: &AudioInterface) -> Vec<PathBuf> {
+    audio_interface
+        .get_queue()
+        .iter()
+        .map(|file| file.get_path().to_path_buf())
+        .collect()
+}
+
+// ticks the queue like the ui does until the current track has finished
+fn wait_for_track_end(audio_interface: &mut AudioInterface) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while audio_interface.get_currently_playing().is_some() {
+        assert!(Instant::now() < deadline, "the track never finished");
+        audio_interface.handle_queue();
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn append_starts_playing() {
+    let mut audio_interface = audio_interface();
+    let dir = TempDir::new().unwrap();
+    let first = track(&dir, "first");
+    let second = track(&dir, "second");
+    audio_interface.append_to_queue(&mut vec![first.clone(), second.clone()]);
+    assert!(audio_interface.get_currently_playing().is_some());
+    assert_eq!(
+        playing_path(&audio_interface).as_deref(),
+        Some(first.get_path())
+    );
+    assert_eq!(queue_paths(&audio_interface), vec![second.get_path()]);
+}
+
+#[test]
+fn hard_clear_stops_playback() {
+    let mut audio_interface = audio_interface();
+    let dir = TempDir::new().unwrap();
+    audio_interface.append_to_queue(&mut vec![track(&dir, "first"), track(&dir, "second")]);
+    audio_interface.hard_clear_queue();
+    assert!(audio_interface.get_currently_playing().is_none());
+    assert_eq!(audio_interface.get_sink_length(), 0);
+    assert!(audio_interface.get_queue().is_empty());
+}
+
+#[test]
+fn shuffle_plays_every_track_once() {
+    let mut audio_interface = audio_interface();
+    let dir = TempDir::new().unwrap();
+    audio_interface.toggle_shuffle();
+    assert!(audio_interface.get_shuffle());
+    let tracks = ["a", "b", "c", "d"].map(|name| track(&dir, name));
+    audio_interface.append_to_queue(&mut tracks.to_vec());
+    let mut played = queue_paths(&audio_interface);
+    played.extend(playing_path(&audio_interface));
+    played.sort();
+    let mut expected = tracks
+        .iter()
+        .map(|file| file.get_path().to_path_buf())
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(played, expected);
+    audio_interface.toggle_shuffle();
+    assert!(!audio_interface.get_shuffle());
+}
+
+#[test]
+fn repeat_modes_cycle() {
+    let mut audio_interface = audio_interface();
+    assert!(audio_interface.get_repeat() == RepeatMode::None);
+    audio_interface.cycle_repeat();
+    assert!(audio_interface.get_repeat() == RepeatMode::All);
+    audio_interface.cycle_repeat();
+    assert!(audio_interface.get_repeat() == RepeatMode::One);
+    audio_interface.cycle_repeat();
+    assert!(audio_interface.get_repeat() == RepeatMode::None);
+}
+
+#[test]
+fn repeat_all_requeues_at_the_back() {
+    let mut audio_interface = audio_interface();
+    let dir = TempDir::new().unwrap();
+    audio_interface.cycle_repeat();
+    let first = track(&dir, "first");
+    let second = track(&dir, "second");
+    audio_interface.append_to_queue(&mut vec![first.clone(), second.clone()]);
+    wait_for_track_end(&mut audio_interface);
+    assert_eq!(
+        queue_paths(&audio_interface),
+        vec![second.get_path(), first.get_path()]
+    );
+}
+
+#[test]
+fn repeat_one_requeues_at_the_front() {
+    let mut audio_interface = audio_interface();
+    let dir = TempDir::new().unwrap();
+    audio_interface.cycle_repeat();
+    audio_interface.cycle_repeat();
+    // shuffle is ignored while a single track repeats
+    audio_interface.toggle_shuffle();
+    let first = track(&dir, "first");
+    let second = track(&dir, "second");
+    audio_interface.append_to_queue(&mut vec![first.clone(), second.clone()]);
+    wait_for_track_end(&mut audio_interface);
+    assert_eq!(
+        queue_paths(&audio_interface),
+        vec![first.get_path(), second.get_path()]
+    );
+    audio_interface.handle_queue();
+    assert_eq!(
+        playing_path(&audio_interface).as_deref(),
+        Some(first.get_path())
+    );
+}
@@ -0,0 +1,130 @@
+use music_player::audio::AudioFile;
+use music_player::library::{recursive_file_walk, scan_duplicates};
+use std::{collections::HashSet, fs, path::Path};
+use tempfile::TempDir;
+
+fn touch(path: &Path) {
+    fs::write(path, b"").unwrap();
+}
+
+// audio files at the top and one level down, among files that aren't audio.
+// returns the paths of the audio files
+fn library_tree(root: &Path) -> HashSet<String> {
+    let nested = root.join("nested");
+    fs::create_dir(&nested).unwrap();
+    let audio = [
+        root.join("one.mp3"),
+        root.join("two.flac"),
+        root.join("three.ogg"),
+        nested.join("four.mp3"),
+        nested.join("five.flac"),
+    ];
+    for path in &audio {
+        touch(path);
+    }
+    for path in [
+        root.join("notes.txt"),
+        root.join("cover.jpg"),
+        nested.join("folder.jpg"),
+        nested.join("tracklist.txt"),
+    ] {
+        touch(&path);
+    }
+    audio
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+#[test]
+fn finds_only_audio_files() {
+    let dir = TempDir::new().unwrap();
+    let expected = library_tree(dir.path());
+    let files = recursive_file_walk(
+        dir.path(),
+        usize::MAX,
+        &[],
+        &mut HashSet::new(),
+        &mut Vec::new(),
+    );
+    assert_eq!(files.len(), expected.len());
+    assert_eq!(files.into_iter().collect::<HashSet<_>>(), expected);
+}
+
+#[test]
+fn depth_limits_the_walk() {
+    let dir = TempDir::new().unwrap();
+    library_tree(dir.path());
+    let files = recursive_file_walk(dir.path(), 1, &[], &mut HashSet::new(), &mut Vec::new());
+    assert_eq!(files.len(), 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_loop_is_walked_once() {
+    let dir = TempDir::new().unwrap();
+    let expected = library_tree(dir.path());
+    // nested/loop points back up at the root
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("nested").join("loop")).unwrap();
+    let files = recursive_file_walk(
+        dir.path(),
+        usize::MAX,
+        &[],
+        &mut HashSet::new(),
+        &mut Vec::new(),
+    );
+    assert_eq!(files.len(), expected.len());
+    assert_eq!(files.into_iter().collect::<HashSet<_>>(), expected);
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_names_are_kept() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    let dir = TempDir::new().unwrap();
+    touch(&dir.path().join(OsStr::from_bytes(b"caf\xe9.mp3")));
+    let files = recursive_file_walk(dir.path(), 1, &[], &mut HashSet::new(), &mut Vec::new());
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with(".mp3"));
+}
+
+#[test]
+fn missing_folder_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let missing = dir.path().join("gone");
+    let mut errors = Vec::new();
+    let files = recursive_file_walk(&missing, 1, &[], &mut HashSet::new(), &mut errors);
+    assert!(files.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, missing);
+}
+
+// the tone padded past the 64 KB that is hashed first, so copies can differ
+// only further in
+fn padded_tone(path: &Path, last_byte: u8) {
+    let mut contents = include_bytes!("fixtures/tone.wav").to_vec();
+    contents.resize(128 * 1024, 0);
+    *contents.last_mut().unwrap() = last_byte;
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn duplicates_need_the_same_full_contents() {
+    let dir = TempDir::new().unwrap();
+    let paths = ["a.wav", "b.wav", "c.wav"].map(|name| dir.path().join(name));
+    padded_tone(&paths[0], 0);
+    padded_tone(&paths[1], 0);
+    padded_tone(&paths[2], 1);
+    let files = paths
+        .iter()
+        .map(|path| AudioFile::new(&path.to_string_lossy().to_string()).unwrap())
+        .collect::<Vec<_>>();
+    let duplicates = scan_duplicates(&files);
+    assert_eq!(duplicates.len(), 1);
+    let mut group = duplicates[0]
+        .iter()
+        .map(|file| file.get_path().to_path_buf())
+        .collect::<Vec<_>>();
+    group.sort();
+    assert_eq!(group, paths[..2]);
+}